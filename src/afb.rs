@@ -0,0 +1,269 @@
+use std::fmt;
+
+use crate::rtcp::{RtcpError, RtcpPacket};
+
+// AppFeedback is implemented by application layer feedback messages carried in DataAFB.  Each message type
+// is identified by its four-character name that immediately follows the common feedback header.
+pub trait AppFeedback: Sized {
+    const NAME: [u8; 4];
+
+    // Parses application data that follows the name.
+    fn from_data(data: &[u8]) -> Result<Self, RtcpError>;
+
+    // Appends application data that follows the name.
+    fn write_data(&self, buf: &mut Vec<u8>);
+}
+
+// DataAFB encapsulates data for Application Layer Feedback packet (RFC 4585 section 6.4).
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |V=2|P|  FMT=15 |  PT=PSFB=206  |             length            |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                     SSRC of packet sender                     |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                      SSRC of media source                     |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                          name (ASCII)                         |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                   application-dependent data                ...
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+#[derive(Clone, Eq, PartialEq)]
+pub struct DataAFB<'a> {
+	sender_ssrc: u32,      // SSRC of packet sender
+	media_ssrc:  u32,      // SSRC of media source
+	name:        [u8; 4],  // Four-character name of the feedback type
+	data:        &'a [u8], // Application-dependent data
+}
+
+impl<'a> DataAFB<'a> {
+    // Feedback message type of application layer feedback.
+    pub const FMT: u8 = 15;
+
+    const FIXED_SIZE: usize = 12;
+
+    pub fn new(sender_ssrc: u32, media_ssrc: u32, name: [u8; 4], data: &'a [u8]) -> DataAFB<'a> {
+        DataAFB { sender_ssrc, media_ssrc, name, data }
+    }
+
+    pub fn from_packet(packet: &RtcpPacket<'a>) -> Result<DataAFB<'a>, RtcpError> {
+        if packet.payload_type() != RtcpPacket::PSFB {
+            return Err(RtcpError::InvalidPayloadType(packet.payload_type()))
+        }
+        if packet.count() != DataAFB::FMT {
+            return Err(RtcpError::InvalidFormat(packet.count()))
+        }
+        let slice = packet.payload();
+        if slice.len() < DataAFB::FIXED_SIZE {
+            return Err(RtcpError::InvalidLen(slice.len()))
+        }
+        Ok(DataAFB {
+            sender_ssrc: u32::from_be_bytes([slice[0], slice[1], slice[2], slice[3]]),
+            media_ssrc: u32::from_be_bytes([slice[4], slice[5], slice[6], slice[7]]),
+            name: [slice[8], slice[9], slice[10], slice[11]],
+            data: &slice[DataAFB::FIXED_SIZE..],
+        })
+    }
+
+    pub fn sender_ssrc(&self) -> u32 {
+        self.sender_ssrc
+    }
+
+    pub fn media_ssrc(&self) -> u32 {
+        self.media_ssrc
+    }
+
+    pub fn name(&self) -> [u8; 4] {
+        self.name
+    }
+
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    // Decodes application data as the feedback type registered under the packet name.
+    pub fn decode<T: AppFeedback>(&self) -> Result<T, RtcpError> {
+        if self.name != T::NAME {
+            return Err(RtcpError::InvalidName(self.name))
+        }
+        T::from_data(self.data)
+    }
+
+    // Serializes feedback into a complete RTCP packet.  Application data is zero padded to 32 bit boundary.
+    pub fn to_vec(&self) -> Vec<u8> {
+        DataAFB::build(self.sender_ssrc, self.media_ssrc, self.name, |buf| buf.extend_from_slice(self.data))
+    }
+
+    // Serializes typed application feedback into a complete RTCP packet.
+    pub fn encode<T: AppFeedback>(sender_ssrc: u32, media_ssrc: u32, feedback: &T) -> Vec<u8> {
+        DataAFB::build(sender_ssrc, media_ssrc, T::NAME, |buf| feedback.write_data(buf))
+    }
+
+    fn build<F: FnOnce(&mut Vec<u8>)>(sender_ssrc: u32, media_ssrc: u32, name: [u8; 4], write: F) -> Vec<u8> {
+        let mut buf = Vec::<u8>::with_capacity(RtcpPacket::HEADER_SIZE + DataAFB::FIXED_SIZE);
+        RtcpPacket::write_header(&mut buf, DataAFB::FMT, RtcpPacket::PSFB, RtcpPacket::HEADER_SIZE);
+        buf.extend_from_slice(&sender_ssrc.to_be_bytes());
+        buf.extend_from_slice(&media_ssrc.to_be_bytes());
+        buf.extend_from_slice(&name);
+        write(&mut buf);
+        buf.resize(buf.len().div_ceil(4) * 4, 0);
+        let length = (buf.len() / 4 - 1) as u16;
+        buf[2..4].copy_from_slice(&length.to_be_bytes());
+        buf
+    }
+}
+
+impl<'a> fmt::Debug for DataAFB<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        f.debug_struct("DataAFB")
+            .field("sender_ssrc", &self.sender_ssrc)
+            .field("media_ssrc", &self.media_ssrc)
+            .field("name", &String::from_utf8_lossy(&self.name))
+            .field("data_len", &self.data.len())
+            .finish()
+    }
+}
+
+// DataREMB encapsulates data for Receiver Estimated Maximum Bitrate feedback (draft-alvestrand-rmcat-remb)
+// that follows the name in DataAFB.  Media source SSRC of the enclosing packet is always 0.
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  Unique identifier 'R' 'E' 'M' 'B'                            |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  Num SSRC     | BR Exp    |  BR Mantissa                      |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |   SSRC feedback                                               |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |  ...                                                          |
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DataREMB {
+	bitrate: u64,      // Estimated maximum bitrate in bits per second
+	ssrcs:   Vec<u32>, // SSRCs the estimate applies to
+}
+
+impl DataREMB {
+    const MAX_MANTISSA: u64 = 0x3FFFF;
+
+    pub fn new(bitrate: u64, ssrcs: Vec<u32>) -> DataREMB {
+        DataREMB { bitrate, ssrcs }
+    }
+
+    pub fn bitrate(&self) -> u64 {
+        self.bitrate
+    }
+
+    pub fn ssrcs(&self) -> &[u32] {
+        &self.ssrcs
+    }
+
+    // Splits bitrate into 6 bit exponent and 18 bit mantissa, rounding down when precision is lost.
+    fn encode_bitrate(bitrate: u64) -> (u8, u32) {
+        let mut exp = 0u8;
+        while (bitrate >> exp) > DataREMB::MAX_MANTISSA {
+            exp += 1;
+        }
+        (exp, (bitrate >> exp) as u32)
+    }
+}
+
+impl AppFeedback for DataREMB {
+    const NAME: [u8; 4] = *b"REMB";
+
+    fn from_data(data: &[u8]) -> Result<Self, RtcpError> {
+        if data.len() < 4 {
+            return Err(RtcpError::InvalidLen(data.len()))
+        }
+        let count = data[0] as usize;
+        if data.len() < 4 + count * 4 {
+            return Err(RtcpError::InvalidLen(data.len()))
+        }
+        let exp = (data[1] >> 2) as u32;
+        let mantissa = u32::from_be_bytes([0, data[1] & 0x03, data[2], data[3]]) as u64;
+        let bitrate = mantissa.checked_shl(exp)
+            .filter(|bitrate| (bitrate >> exp) == mantissa)
+            .unwrap_or(u64::MAX);
+        let ssrcs = data[4..4 + count * 4]
+            .chunks_exact(4)
+            .map(|chunk| u32::from_be_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect();
+        Ok(DataREMB { bitrate, ssrcs })
+    }
+
+    fn write_data(&self, buf: &mut Vec<u8>) {
+        let (exp, mantissa) = DataREMB::encode_bitrate(self.bitrate);
+        let mantissa = mantissa.to_be_bytes();
+        buf.push(usize::min(self.ssrcs.len(), u8::MAX as usize) as u8);
+        buf.push((exp << 2) | mantissa[1]);
+        buf.extend_from_slice(&mantissa[2..]);
+        for ssrc in self.ssrcs.iter().take(u8::MAX as usize) {
+            buf.extend_from_slice(&ssrc.to_be_bytes());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_remb_packet() {
+        let data: [u8; 24] = [
+            0x8f, 0xce, 0x00, 0x05, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00,
+            0x00, 0x00, 0x52, 0x45, 0x4d, 0x42, 0x01, 0x1a, 0x1f, 0x40,
+            0x23, 0x45, 0x67, 0x89,
+        ];
+        let packet = RtcpPacket::from_slice(&data).unwrap();
+        let feedback = DataAFB::from_packet(&packet).unwrap();
+        assert_eq!(1, feedback.sender_ssrc());
+        assert_eq!(0, feedback.media_ssrc());
+        assert_eq!(*b"REMB", feedback.name());
+        let remb: DataREMB = feedback.decode().unwrap();
+        assert_eq!(0x21f40 << 6, remb.bitrate());
+        assert_eq!(&[0x23456789][..], remb.ssrcs());
+    }
+
+    #[test]
+    fn parse_truncated_remb() {
+        let data: [u8; 20] = [
+            0x8f, 0xce, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00,
+            0x00, 0x00, 0x52, 0x45, 0x4d, 0x42, 0x01, 0x1a, 0x1f, 0x40,
+        ];
+        let packet = RtcpPacket::from_slice(&data).unwrap();
+        let feedback = DataAFB::from_packet(&packet).unwrap();
+        let error = feedback.decode::<DataREMB>().unwrap_err();
+        assert!(matches!(error, RtcpError::InvalidLen(4)))
+    }
+
+    #[test]
+    fn decode_unknown_name() {
+        let data = DataAFB::new(1, 2, *b"ABCD", &[1, 2, 3]).to_vec();
+        assert_eq!(20, data.len());
+        let packet = RtcpPacket::from_slice(&data).unwrap();
+        let feedback = DataAFB::from_packet(&packet).unwrap();
+        assert_eq!(&[1u8, 2, 3, 0][..], feedback.data());
+        let error = feedback.decode::<DataREMB>().unwrap_err();
+        assert!(matches!(error, RtcpError::InvalidName(name) if name == *b"ABCD"))
+    }
+
+    #[test]
+    fn encode_bitrate() {
+        assert_eq!((0, 0x3FFFF), DataREMB::encode_bitrate(0x3FFFF));
+        assert_eq!((1, 0x20000), DataREMB::encode_bitrate(0x40000));
+        assert_eq!((2, 250000), DataREMB::encode_bitrate(1_000_000));
+        assert_eq!((46, 0x3FFFF), DataREMB::encode_bitrate(u64::MAX));
+    }
+
+    #[test]
+    fn serialize_round_trip() {
+        let remb = DataREMB::new(2_500_000, vec![0x11111111, 0x22222222]);
+        let data = DataAFB::encode(0xABCDEF01, 0, &remb);
+        assert_eq!(28, data.len());
+        let packet = RtcpPacket::from_slice(&data).unwrap();
+        let feedback = DataAFB::from_packet(&packet).unwrap();
+        assert_eq!(0xABCDEF01, feedback.sender_ssrc());
+        let decoded: DataREMB = feedback.decode().unwrap();
+        assert_eq!(2_500_000 >> 4 << 4, decoded.bitrate());
+        assert_eq!(remb.ssrcs(), decoded.ssrcs());
+    }
+}
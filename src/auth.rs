@@ -0,0 +1,348 @@
+use std::fmt;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use md5::Md5;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+
+#[derive(Debug)]
+pub enum AuthError {
+    // None of the challenges uses a scheme and algorithm this crate implements.
+    NoSupportedChallenge,
+    MissingParameter(&'static str),
+}
+
+// Challenge of WWW-Authenticate header (RFC 7235 section 2.1).  Parameter names are case-insensitive and
+// values are unquoted.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Challenge {
+    pub scheme: String,
+    pub params: Vec<(String, String)>,
+}
+
+impl Challenge {
+    // Parses all challenges of a header value.  Vendors put several challenges in one header and leave
+    // parameter values unquoted, both are accepted.
+    pub fn parse_list(value: &str) -> Vec<Challenge> {
+        let mut challenges: Vec<Challenge> = Vec::new();
+        let mut rest = value;
+        loop {
+            rest = rest.trim_start_matches([' ', '\t', ',']);
+            if rest.is_empty() {
+                return challenges
+            }
+            let end = rest.find([' ', '\t', ',', '=']).unwrap_or(rest.len());
+            let (token, after) = rest.split_at(end);
+            let after_token = after.trim_start();
+            match (after_token.strip_prefix('='), challenges.last_mut()) {
+                (Some(value), Some(challenge)) => {
+                    let (value, remaining) = Challenge::parse_value(value.trim_start());
+                    challenge.params.push((token.to_string(), value));
+                    rest = remaining;
+                },
+                // A scheme is followed by its parameters or token68, a bare token after a parameter continues an
+                // unquoted list such as qop=auth,auth-int.
+                (None, Some(Challenge { params, .. })) if !after.starts_with([' ', '\t']) && !params.is_empty() => {
+                    if let Some((_, value)) = params.last_mut() {
+                        value.push(',');
+                        value.push_str(token);
+                    }
+                    rest = after;
+                },
+                _ => {
+                    challenges.push(Challenge { scheme: token.to_string(), params: Vec::new() });
+                    rest = after;
+                },
+            }
+        }
+    }
+
+    // Quoted string with backslash escapes, or anything up to the next comma.
+    fn parse_value(text: &str) -> (String, &str) {
+        let Some(quoted) = text.strip_prefix('"') else {
+            let end = text.find(',').unwrap_or(text.len());
+            return (text[..end].trim().to_string(), &text[end..])
+        };
+        let mut value = String::new();
+        let mut chars = quoted.char_indices();
+        while let Some((index, c)) = chars.next() {
+            match c {
+                '\\' => value.extend(chars.next().map(|(_, c)| c)),
+                '"' => return (value, &quoted[index + 1..]),
+                c => value.push(c),
+            }
+        }
+        (value, "")
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.params.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+    }
+
+    pub fn is_basic(&self) -> bool {
+        self.scheme.eq_ignore_ascii_case("Basic")
+    }
+
+    pub fn is_digest(&self) -> bool {
+        self.scheme.eq_ignore_ascii_case("Digest")
+    }
+
+    // Server asks to retry with a fresh nonce rather than rejecting credentials.
+    pub fn is_stale(&self) -> bool {
+        self.get("stale").is_some_and(|stale| stale.eq_ignore_ascii_case("true"))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DigestAlgorithm {
+    Md5,
+    Md5Sess,
+    Sha256,
+    Sha256Sess,
+}
+
+impl DigestAlgorithm {
+    // Missing algorithm parameter means MD5.
+    pub fn from_name(name: Option<&str>) -> Option<DigestAlgorithm> {
+        match name.map(str::to_ascii_uppercase).as_deref() {
+            None | Some("MD5") => Some(DigestAlgorithm::Md5),
+            Some("MD5-SESS") => Some(DigestAlgorithm::Md5Sess),
+            Some("SHA-256") => Some(DigestAlgorithm::Sha256),
+            Some("SHA-256-SESS") => Some(DigestAlgorithm::Sha256Sess),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DigestAlgorithm::Md5 => "MD5",
+            DigestAlgorithm::Md5Sess => "MD5-sess",
+            DigestAlgorithm::Sha256 => "SHA-256",
+            DigestAlgorithm::Sha256Sess => "SHA-256-sess",
+        }
+    }
+
+    fn is_session(&self) -> bool {
+        matches!(self, DigestAlgorithm::Md5Sess | DigestAlgorithm::Sha256Sess)
+    }
+
+    // Preference when a server offers several digest challenges.
+    fn strength(&self) -> u8 {
+        match self {
+            DigestAlgorithm::Md5 | DigestAlgorithm::Md5Sess => 1,
+            DigestAlgorithm::Sha256 | DigestAlgorithm::Sha256Sess => 2,
+        }
+    }
+
+    fn hash(&self, data: &str) -> String {
+        let digest = match self {
+            DigestAlgorithm::Md5 | DigestAlgorithm::Md5Sess => Md5::digest(data.as_bytes()).to_vec(),
+            DigestAlgorithm::Sha256 | DigestAlgorithm::Sha256Sess => Sha256::digest(data.as_bytes()).to_vec(),
+        };
+        digest.iter().map(|byte| format!("{:02x}", byte)).collect()
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Scheme {
+    Basic,
+    Digest {
+        realm: String,
+        nonce: String,
+        opaque: Option<String>,
+        algorithm: DigestAlgorithm,
+        // Server offered qop=auth, otherwise RFC 2069 compatible response is computed.
+        qop: bool,
+    },
+}
+
+// Authenticator answers challenges with Authorization header values for Basic (RFC 7617) and Digest
+// (RFC 2617, RFC 7616) schemes.  Digest nonce count grows with each request using the same nonce.
+#[derive(Clone)]
+pub struct Authenticator {
+    username: String,
+    password: String,
+    scheme: Scheme,
+    nc: u32,
+    cnonce: String,
+}
+
+impl Authenticator {
+    // Picks the strongest supported challenge: digest with SHA-256, digest with MD5, then basic.
+    pub fn new(username: &str, password: &str, challenges: &[Challenge]) -> Result<Authenticator, AuthError> {
+        let mut authenticator = Authenticator {
+            username: username.to_string(),
+            password: password.to_string(),
+            scheme: Scheme::Basic,
+            nc: 0,
+            cnonce: String::new(),
+        };
+        authenticator.update(challenges)?;
+        Ok(authenticator)
+    }
+
+    // Takes new challenges, e.g. after a stale nonce, keeping credentials.
+    pub fn update(&mut self, challenges: &[Challenge]) -> Result<(), AuthError> {
+        let digest = challenges.iter()
+            .filter(|challenge| challenge.is_digest())
+            .filter_map(|challenge| DigestAlgorithm::from_name(challenge.get("algorithm")).map(|algorithm| (challenge, algorithm)))
+            .max_by_key(|(_, algorithm)| algorithm.strength());
+        self.scheme = match digest {
+            Some((challenge, algorithm)) => Scheme::Digest {
+                realm: challenge.get("realm").ok_or(AuthError::MissingParameter("realm"))?.to_string(),
+                nonce: challenge.get("nonce").ok_or(AuthError::MissingParameter("nonce"))?.to_string(),
+                opaque: challenge.get("opaque").map(String::from),
+                algorithm,
+                qop: challenge.get("qop").is_some_and(|qop| qop.split(',').any(|qop| qop.trim().eq_ignore_ascii_case("auth"))),
+            },
+            None if challenges.iter().any(Challenge::is_basic) => Scheme::Basic,
+            None => return Err(AuthError::NoSupportedChallenge),
+        };
+        self.nc = 0;
+        let mut cnonce = [0u8; 8];
+        rand::thread_rng().fill_bytes(&mut cnonce);
+        self.cnonce = cnonce.iter().map(|byte| format!("{:02x}", byte)).collect();
+        Ok(())
+    }
+
+    pub fn is_digest(&self) -> bool {
+        matches!(self.scheme, Scheme::Digest { .. })
+    }
+
+    // Authorization header value for the request.
+    pub fn authorization(&mut self, method: &str, uri: &str) -> String {
+        let Scheme::Digest { realm, nonce, opaque, algorithm, qop } = &self.scheme else {
+            let credentials = format!("{}:{}", self.username, self.password);
+            return format!("Basic {}", STANDARD.encode(credentials))
+        };
+        self.nc += 1;
+        let nc = format!("{:08x}", self.nc);
+        let mut ha1 = algorithm.hash(&format!("{}:{}:{}", self.username, realm, self.password));
+        if algorithm.is_session() {
+            ha1 = algorithm.hash(&format!("{}:{}:{}", ha1, nonce, self.cnonce));
+        }
+        let ha2 = algorithm.hash(&format!("{}:{}", method, uri));
+        let response = match qop {
+            true => algorithm.hash(&format!("{}:{}:{}:{}:auth:{}", ha1, nonce, nc, self.cnonce, ha2)),
+            false => algorithm.hash(&format!("{}:{}:{}", ha1, nonce, ha2)),
+        };
+
+        let mut value = format!("Digest username={}, realm={}, nonce={}, uri={}, response={}",
+            Quoted(&self.username), Quoted(realm), Quoted(nonce), Quoted(uri), Quoted(&response));
+        // Servers predating RFC 7616 may choke on an explicit default algorithm.
+        if *algorithm != DigestAlgorithm::Md5 {
+            value.push_str(&format!(", algorithm={}", algorithm.as_str()));
+        }
+        if let Some(opaque) = opaque {
+            value.push_str(&format!(", opaque={}", Quoted(opaque)));
+        }
+        if *qop {
+            value.push_str(&format!(", qop=auth, nc={}, cnonce={}", nc, Quoted(&self.cnonce)));
+        }
+        value
+    }
+}
+
+// Password is left out of debug output.
+impl fmt::Debug for Authenticator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        f.debug_struct("Authenticator")
+            .field("username", &self.username)
+            .field("scheme", &self.scheme)
+            .field("nc", &self.nc)
+            .finish()
+    }
+}
+
+struct Quoted<'a>(&'a str);
+
+impl fmt::Display for Quoted<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "\"{}\"", self.0.replace('\\', "\\\\").replace('"', "\\\""))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_challenges() {
+        let challenges = Challenge::parse_list("Digest realm=\"IP Camera(C2358)\", nonce=\"a2f1\\\"b\", stale=FALSE, Basic realm=\"IP Camera(C2358)\"");
+        assert_eq!(2, challenges.len());
+        assert!(challenges[0].is_digest());
+        assert_eq!(Some("IP Camera(C2358)"), challenges[0].get("REALM"));
+        assert_eq!(Some("a2f1\"b"), challenges[0].get("nonce"));
+        assert!(!challenges[0].is_stale());
+        assert!(challenges[1].is_basic());
+
+        let challenges = Challenge::parse_list("Digest realm=RTSP Server,nonce=1234abcd, algorithm=MD5,qop=\"auth,auth-int\"");
+        assert_eq!(1, challenges.len());
+        assert_eq!(Some("RTSP Server"), challenges[0].get("realm"));
+        assert_eq!(Some("1234abcd"), challenges[0].get("nonce"));
+        assert_eq!(Some("auth,auth-int"), challenges[0].get("qop"));
+
+        let challenges = Challenge::parse_list("Digest realm=RTSP Server, qop=auth,auth-int, nonce=1234abcd, Basic realm=RTSP Server");
+        assert_eq!(2, challenges.len());
+        assert_eq!(Some("auth,auth-int"), challenges[0].get("qop"));
+        assert_eq!(Some("1234abcd"), challenges[0].get("nonce"));
+        assert!(challenges[1].is_basic());
+        let challenges = Challenge::parse_list("Negotiate, Basic realm=camera");
+        assert_eq!(vec!["Negotiate", "Basic"], challenges.iter().map(|challenge| challenge.scheme.as_str()).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn answer_md5_challenge() {
+        // Example of RFC 2617 section 3.5.
+        let challenges = Challenge::parse_list("Digest realm=\"testrealm@host.com\", qop=\"auth,auth-int\", \
+            nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", opaque=\"5ccc069c403ebaf9f0171e9517f40e41\"");
+        let mut authenticator = Authenticator::new("Mufasa", "Circle Of Life", &challenges).unwrap();
+        authenticator.cnonce = "0a4f113b".to_string();
+        assert_eq!("Digest username=\"Mufasa\", realm=\"testrealm@host.com\", nonce=\"dcd98b7102dd2f0e8b11d0f600bfb0c093\", \
+            uri=\"/dir/index.html\", response=\"6629fae49393a05397450978507c4ef1\", opaque=\"5ccc069c403ebaf9f0171e9517f40e41\", \
+            qop=auth, nc=00000001, cnonce=\"0a4f113b\"", authenticator.authorization("GET", "/dir/index.html"));
+        assert!(authenticator.authorization("GET", "/dir/index.html").contains("nc=00000002"));
+    }
+
+    #[test]
+    fn answer_sha256_challenge() {
+        // Example of RFC 7616 section 3.9.1, SHA-256 is preferred over MD5 offered alongside.
+        let challenges = Challenge::parse_list("Digest realm=\"http-auth@example.org\", qop=\"auth, auth-int\", algorithm=SHA-256, \
+            nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\", \
+            Digest realm=\"http-auth@example.org\", qop=\"auth, auth-int\", algorithm=MD5, \
+            nonce=\"7ypf/xlj9XXwfDPEoM4URrv/xwf94BcCAzFZH4GiTo0v\", opaque=\"FQhe/qaU925kfnzjCev0ciny7QMkPqMAFRtzCUYo5tdS\"");
+        assert_eq!(2, challenges.len());
+        let mut authenticator = Authenticator::new("Mufasa", "Circle of Life", &challenges).unwrap();
+        authenticator.cnonce = "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ".to_string();
+        let value = authenticator.authorization("GET", "/dir/index.html");
+        assert!(value.contains("response=\"753927fa0e85d155564e2e272a28d1802ca10daf4496794697cf8db5856cb6c1\""));
+        assert!(value.contains("algorithm=SHA-256"));
+
+        authenticator.update(&challenges[1..]).unwrap();
+        authenticator.cnonce = "f2/wE4q74E6zIJEtWaHKaf5wv/H5QzzpXusqGemxURZJ".to_string();
+        assert!(authenticator.authorization("GET", "/dir/index.html").contains("response=\"8ca523f5e9506fed4657c9700eebdbec\""));
+    }
+
+    #[test]
+    fn answer_basic_and_session_challenges() {
+        let mut authenticator = Authenticator::new("Aladdin", "open sesame", &Challenge::parse_list("Basic realm=\"WallyWorld\"")).unwrap();
+        assert!(!authenticator.is_digest());
+        assert_eq!("Basic QWxhZGRpbjpvcGVuIHNlc2FtZQ==", authenticator.authorization("DESCRIBE", "rtsp://camera/"));
+
+        let challenges = Challenge::parse_list("Digest realm=\"r\", nonce=\"n\", algorithm=MD5-sess, qop=auth");
+        let mut authenticator = Authenticator::new("u", "p", &challenges).unwrap();
+        authenticator.cnonce = "c".to_string();
+        // HA1 = MD5(MD5("u:r:p") ":n:c"), response = MD5(HA1 ":n:00000001:c:auth:" MD5("PLAY:rtsp://x"))
+        let ha1 = DigestAlgorithm::Md5.hash(&format!("{}:n:c", DigestAlgorithm::Md5.hash("u:r:p")));
+        let expected = DigestAlgorithm::Md5.hash(&format!("{}:n:00000001:c:auth:{}", ha1, DigestAlgorithm::Md5.hash("PLAY:rtsp://x")));
+        assert!(authenticator.authorization("PLAY", "rtsp://x").contains(&expected));
+
+        let challenges = Challenge::parse_list("Digest realm=\"r\", nonce=\"n\"");
+        let mut authenticator = Authenticator::new("u", "p", &challenges).unwrap();
+        let value = authenticator.authorization("OPTIONS", "*");
+        assert!(!value.contains("qop") && !value.contains("algorithm"));
+
+        assert!(matches!(Authenticator::new("u", "p", &Challenge::parse_list("Negotiate")).unwrap_err(), AuthError::NoSupportedChallenge));
+        assert!(matches!(Authenticator::new("u", "p", &Challenge::parse_list("Digest realm=r")).unwrap_err(), AuthError::MissingParameter("nonce")));
+    }
+}
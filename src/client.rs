@@ -0,0 +1,1056 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::auth::{AuthError, Authenticator, Challenge};
+use crate::headers::{parse_public, resolve_control, SessionHeader, TransportSpec};
+use crate::interleaved::{ChannelMap, InterleavedFrame};
+use crate::mux::PacketKind;
+use crate::onvif::ReplayOptions;
+use crate::rtp::RtpPacket;
+use crate::rtsp::{Headers, Message, Method, Request, Response, RtspError, RtspItem, RtspParser, RtspVersion};
+use crate::sdp::{Attribute, Attributes, SdpError, SessionDescription};
+
+#[derive(Debug)]
+pub enum ClientError {
+    Rtsp(RtspError),
+    Sdp(SdpError),
+    Auth(AuthError),
+    // Operation is not allowed in the state the client is or will be in once queued requests complete.
+    InvalidState(ClientState),
+    // Stream index has no media section in the session description.
+    UnknownStream(usize),
+    // Method does not exist in the protocol version in use, e.g. RECORD in RTSP 2.0.
+    UnsupportedMethod(Method, RtspVersion),
+    // Interleaved data not queued as bytes waiting in poll_transmit() reached the send buffer size.
+    SendBufferFull,
+    // Successful response lacks a header required to continue.
+    MissingHeader(&'static str),
+    // 3xx status with target from Location header.
+    Redirect(u16, Option<String>),
+    // 401 status with challenges from WWW-Authenticate headers, reported when no credentials are set or they
+    // were rejected.
+    Unauthorized(Vec<String>),
+    Forbidden,
+    NotFound,
+    MethodNotAllowed,
+    SessionNotFound,
+    MethodNotValidInThisState,
+    InvalidRange,
+    UnsupportedTransport,
+    NotEnoughBandwidth,
+    ServiceUnavailable,
+    // 551 status with feature tags of Require header listed in Unsupported header.
+    OptionNotSupported(Vec<String>),
+    // Any other non-2xx status with its reason phrase.
+    Status(u16, String),
+}
+
+impl From<RtspError> for ClientError {
+    fn from(error: RtspError) -> Self {
+        ClientError::Rtsp(error)
+    }
+}
+
+impl From<AuthError> for ClientError {
+    fn from(error: AuthError) -> Self {
+        ClientError::Auth(error)
+    }
+}
+
+impl From<SdpError> for ClientError {
+    fn from(error: SdpError) -> Self {
+        ClientError::Sdp(error)
+    }
+}
+
+impl ClientError {
+    // Error for non-2xx response, None for success.
+    pub fn from_response(response: &Response) -> Option<ClientError> {
+        Some(match response.status {
+            200..=299 => return None,
+            300..=399 => ClientError::Redirect(response.status, response.headers.get("Location").map(String::from)),
+            401 => ClientError::Unauthorized(response.headers.get_all(Headers::WWW_AUTHENTICATE).map(String::from).collect()),
+            403 => ClientError::Forbidden,
+            404 => ClientError::NotFound,
+            405 => ClientError::MethodNotAllowed,
+            453 => ClientError::NotEnoughBandwidth,
+            454 => ClientError::SessionNotFound,
+            455 => ClientError::MethodNotValidInThisState,
+            457 => ClientError::InvalidRange,
+            461 => ClientError::UnsupportedTransport,
+            503 => ClientError::ServiceUnavailable,
+            551 => ClientError::OptionNotSupported(response.headers.get(Headers::UNSUPPORTED)
+                .map(|value| value.split(',').map(|tag| tag.trim().to_string()).collect())
+                .unwrap_or_default()),
+            status => ClientError::Status(status, response.reason.clone()),
+        })
+    }
+}
+
+// Client states of RFC 2326 appendix A.1.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ClientState {
+    Init,
+    Ready,
+    Playing,
+    Recording,
+}
+
+#[derive(Debug)]
+pub enum ClientEvent {
+    // Methods supported by the server.
+    Options(Vec<Method>),
+    Described(Box<SessionDescription>),
+    // Server accepted the description of a published presentation.
+    Announced,
+    // Transport chosen by the server for the stream.
+    SetUp { stream: usize, transport: TransportSpec },
+    // Range and RTP-Info headers of PLAY response.
+    Playing { range: Option<String>, rtp_info: Option<String> },
+    Paused,
+    Recording,
+    TornDown,
+    // PLAY_NOTIFY of RTSP 2.0 server with Notify-Reason, e.g. "end-of-stream", and Range headers.
+    PlayNotify { reason: String, range: Option<String> },
+    // Interleaved data of a set up stream.
+    Frame { stream: usize, kind: PacketKind, frame: InterleavedFrame },
+    // Request failed, requests queued after it are dropped.
+    Failed { method: Method, error: ClientError },
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Pending {
+    Options,
+    Describe,
+    Announce,
+    Setup(usize),
+    Play,
+    Pause,
+    Record,
+    Teardown,
+    KeepAlive,
+}
+
+// RtspClient is a sans-IO RTSP 1.0 client.  Methods queue requests that are sent one at a time, each after
+// response to the previous one arrives, as many cameras do not cope with pipelining.  Bytes received on the
+// connection are fed to handle_input(), bytes to send are taken from poll_transmit() and outcomes are
+// reported by poll_event().  A keepalive is sent at half of the session timeout.  With credentials set, a
+// request answered with 401 is repeated once with Authorization and later requests carry it as well.
+// A client set to RTSP 2.0 falls back to 1.0 when the server answers 505 or responds with 1.0.  Published
+// streams are announced, set up with mode=record and sent as interleaved data, which is refused once more
+// than the send buffer size waits in poll_transmit(), so a slow connection holds back the encoder instead of
+// growing the queue without bound.
+pub struct RtspClient {
+    url: String,
+    version: RtspVersion,
+    user_agent: String,
+    // Feature tags sent in Require header of DESCRIBE, SETUP and PLAY, e.g. ONVIF backchannel.
+    require: Vec<String>,
+    cseq: u32,
+    state: ClientState,
+    // State expected once all queued requests succeed, used to validate new requests.
+    planned: ClientState,
+    session: Option<SessionHeader>,
+    base: Option<String>,
+    description: Option<SessionDescription>,
+    public: Vec<Method>,
+    // Media-Properties and Accept-Ranges of the latest RTSP 2.0 SETUP or PLAY response.
+    media_properties: Option<String>,
+    accept_ranges: Vec<String>,
+    transports: Vec<(usize, TransportSpec)>,
+    channels: ChannelMap,
+    parser: RtspParser,
+    queue: VecDeque<(Request, Pending)>,
+    // CSeq, request and whether it is a retry after 401.
+    outstanding: Option<(u32, Request, Pending, bool)>,
+    credentials: Option<(String, String)>,
+    authenticator: Option<Authenticator>,
+    retrying: bool,
+    transmit: VecDeque<Vec<u8>>,
+    // Bytes waiting in transmit and the limit for adding interleaved data.
+    buffered: usize,
+    send_buffer: usize,
+    events: VecDeque<ClientEvent>,
+    keepalive: Option<Instant>,
+}
+
+impl RtspClient {
+    pub const DEFAULT_SEND_BUFFER: usize = 256 * 1024;
+
+    pub fn new(url: impl Into<String>) -> RtspClient {
+        RtspClient {
+            url: url.into(),
+            version: RtspVersion::V1_0,
+            user_agent: concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")).to_string(),
+            require: Vec::new(),
+            cseq: 0,
+            state: ClientState::Init,
+            planned: ClientState::Init,
+            session: None,
+            base: None,
+            description: None,
+            public: Vec::new(),
+            media_properties: None,
+            accept_ranges: Vec::new(),
+            transports: Vec::new(),
+            channels: ChannelMap::new(),
+            parser: RtspParser::new(),
+            queue: VecDeque::new(),
+            outstanding: None,
+            credentials: None,
+            authenticator: None,
+            retrying: false,
+            transmit: VecDeque::new(),
+            buffered: 0,
+            send_buffer: RtspClient::DEFAULT_SEND_BUFFER,
+            events: VecDeque::new(),
+            keepalive: None,
+        }
+    }
+
+    pub fn set_user_agent(&mut self, user_agent: impl Into<String>) {
+        self.user_agent = user_agent.into();
+    }
+
+    // Version of requests sent from now on, may still fall back to 1.0 on server response.
+    pub fn set_version(&mut self, version: RtspVersion) {
+        self.version = version;
+    }
+
+    pub fn version(&self) -> RtspVersion {
+        self.version
+    }
+
+    // Requires a server feature, e.g. onvif::BACKCHANNEL to have the device describe its audio outputs.
+    pub fn add_require(&mut self, feature: impl Into<String>) {
+        self.require.push(feature.into());
+    }
+
+    pub fn set_credentials(&mut self, username: impl Into<String>, password: impl Into<String>) {
+        self.credentials = Some((username.into(), password.into()));
+        self.authenticator = None;
+    }
+
+    pub fn set_send_buffer(&mut self, size: usize) {
+        self.send_buffer = size;
+    }
+
+    // Bytes queued for poll_transmit().
+    pub fn buffered(&self) -> usize {
+        self.buffered
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    pub fn state(&self) -> ClientState {
+        self.state
+    }
+
+    pub fn session(&self) -> Option<&SessionHeader> {
+        self.session.as_ref()
+    }
+
+    pub fn session_description(&self) -> Option<&SessionDescription> {
+        self.description.as_ref()
+    }
+
+    pub fn media_properties(&self) -> Option<&str> {
+        self.media_properties.as_deref()
+    }
+
+    // Range formats the server accepts for the session, e.g. "npt" or "clock".
+    pub fn accept_ranges(&self) -> &[String] {
+        &self.accept_ranges
+    }
+
+    pub fn transport(&self, stream: usize) -> Option<&TransportSpec> {
+        self.transports.iter().find(|(id, _)| *id == stream).map(|(_, transport)| transport)
+    }
+
+    // Host and port to send datagrams of a stream set up over UDP to: source of Transport header or host of
+    // the URL, and server port.
+    pub fn udp_destination(&self, stream: usize, kind: PacketKind) -> Option<(String, u16)> {
+        let transport = self.transport(stream).filter(|transport| !transport.is_tcp())?;
+        let (rtp, rtcp) = transport.server_port()?;
+        let port = match kind {
+            PacketKind::Rtp => rtp,
+            _ => rtcp.unwrap_or(rtp.wrapping_add(1)),
+        };
+        let host = match transport.source() {
+            Some(source) => source.to_string(),
+            None => {
+                let rest = self.url.split_once("://").map_or(self.url.as_str(), |(_, rest)| rest);
+                let authority = rest.split('/').next().unwrap_or_default();
+                let host = authority.rsplit_once('@').map_or(authority, |(_, host)| host);
+                // Port of the URL, not of an IPv6 address.
+                match host.rsplit_once(':') {
+                    Some((name, port)) if !port.contains(']') && port.parse::<u16>().is_ok() => name.to_string(),
+                    _ => host.to_string(),
+                }
+            },
+        };
+        Some((host.trim_start_matches('[').trim_end_matches(']').to_string(), port))
+    }
+
+    pub fn channels(&self) -> &ChannelMap {
+        &self.channels
+    }
+
+    // Base URL for relative control URLs: Content-Base, Content-Location or the request URL.
+    pub fn base_url(&self) -> &str {
+        self.base.as_deref().unwrap_or(&self.url)
+    }
+
+    // URL of aggregate control of the presentation.
+    pub fn aggregate_url(&self) -> String {
+        resolve_control(self.base_url(), self.description.as_ref().and_then(|description| description.control()))
+    }
+
+    pub fn control_url(&self, stream: usize) -> Option<String> {
+        let media = self.description.as_ref()?.media.get(stream)?;
+        Some(resolve_control(&self.aggregate_url(), media.control()))
+    }
+
+    pub fn options(&mut self) {
+        let request = Request::new(Method::Options, self.url.clone(), 0);
+        self.enqueue(request, Pending::Options);
+    }
+
+    pub fn describe(&mut self) {
+        let mut request = Request::new(Method::Describe, self.url.clone(), 0);
+        request.headers.set(Headers::ACCEPT, "application/sdp");
+        self.set_require(&mut request);
+        self.enqueue(request, Pending::Describe);
+    }
+
+    // Publishes a presentation (RFC 2326 section 10.3).  Media sections without control attribute get
+    // "streamid=<index>" so that each stream has its own SETUP URL.
+    pub fn announce(&mut self, description: &SessionDescription) -> Result<(), ClientError> {
+        if self.version >= RtspVersion::V2_0 {
+            return Err(ClientError::UnsupportedMethod(Method::Announce, self.version))
+        }
+        if self.planned != ClientState::Init {
+            return Err(ClientError::InvalidState(self.planned))
+        }
+        let mut description = description.clone();
+        for (index, media) in description.media.iter_mut().enumerate() {
+            if media.control().is_none() {
+                media.attributes.push(Attribute::value("control", format!("streamid={}", index)));
+            }
+        }
+        let mut request = Request::new(Method::Announce, self.url.clone(), 0);
+        request.headers.set(Headers::CONTENT_TYPE, "application/sdp");
+        request.body = description.to_string().into_bytes();
+        self.base = None;
+        self.description = Some(description);
+        self.enqueue(request, Pending::Announce);
+        Ok(())
+    }
+
+    pub fn setup(&mut self, stream: usize, transport: TransportSpec) -> Result<(), ClientError> {
+        if matches!(self.planned, ClientState::Playing | ClientState::Recording) {
+            return Err(ClientError::InvalidState(self.planned))
+        }
+        let url = self.control_url(stream).ok_or(ClientError::UnknownStream(stream))?;
+        let mut request = Request::new(Method::Setup, url, 0);
+        request.headers.set(Headers::TRANSPORT, transport);
+        self.set_require(&mut request);
+        self.planned = ClientState::Ready;
+        self.enqueue(request, Pending::Setup(stream));
+        Ok(())
+    }
+
+    // Starts or repositions playback, range is the value of Range header, e.g. "npt=0-".
+    pub fn play(&mut self, range: Option<&str>) -> Result<(), ClientError> {
+        let mut headers = Headers::new();
+        if let Some(range) = range {
+            headers.set(Headers::RANGE, range);
+        }
+        self.play_with_headers(headers)
+    }
+
+    // Starts playback of a recording with ONVIF replay headers.  The session should have been set up with
+    // Require: onvif-replay, see add_require.
+    pub fn replay(&mut self, options: &ReplayOptions) -> Result<(), ClientError> {
+        let mut headers = Headers::new();
+        options.apply(&mut headers);
+        self.play_with_headers(headers)
+    }
+
+    fn play_with_headers(&mut self, headers: Headers) -> Result<(), ClientError> {
+        if !matches!(self.planned, ClientState::Ready | ClientState::Playing) {
+            return Err(ClientError::InvalidState(self.planned))
+        }
+        let mut request = Request::new(Method::Play, self.aggregate_url(), 0);
+        for (name, value) in headers.iter() {
+            request.headers.set(name, value);
+        }
+        self.set_require(&mut request);
+        self.planned = ClientState::Playing;
+        self.enqueue(request, Pending::Play);
+        Ok(())
+    }
+
+    pub fn pause(&mut self) -> Result<(), ClientError> {
+        if !matches!(self.planned, ClientState::Playing | ClientState::Recording) {
+            return Err(ClientError::InvalidState(self.planned))
+        }
+        let request = Request::new(Method::Pause, self.aggregate_url(), 0);
+        self.planned = ClientState::Ready;
+        self.enqueue(request, Pending::Pause);
+        Ok(())
+    }
+
+    pub fn record(&mut self, range: Option<&str>) -> Result<(), ClientError> {
+        if self.version >= RtspVersion::V2_0 {
+            return Err(ClientError::UnsupportedMethod(Method::Record, self.version))
+        }
+        if !matches!(self.planned, ClientState::Ready | ClientState::Recording) {
+            return Err(ClientError::InvalidState(self.planned))
+        }
+        let mut request = Request::new(Method::Record, self.aggregate_url(), 0);
+        if let Some(range) = range {
+            request.headers.set(Headers::RANGE, range);
+        }
+        self.planned = ClientState::Recording;
+        self.enqueue(request, Pending::Record);
+        Ok(())
+    }
+
+    pub fn teardown(&mut self) -> Result<(), ClientError> {
+        if self.planned == ClientState::Init {
+            return Err(ClientError::InvalidState(self.planned))
+        }
+        let request = Request::new(Method::Teardown, self.aggregate_url(), 0);
+        self.planned = ClientState::Init;
+        self.enqueue(request, Pending::Teardown);
+        Ok(())
+    }
+
+    // Queues interleaved data of a stream set up over TCP.
+    pub fn send_frame(&mut self, stream: usize, kind: PacketKind, data: Vec<u8>) -> Result<(), ClientError> {
+        let frame = self.channels.frame(stream, kind, data)?.ok_or(ClientError::UnknownStream(stream))?;
+        let data = frame.to_vec();
+        if self.buffered + data.len() > self.send_buffer {
+            return Err(ClientError::SendBufferFull)
+        }
+        self.queue_transmit(data);
+        Ok(())
+    }
+
+    // Queues RTP packet of a stream set up over TCP, e.g. one made by RtpPacketizer.
+    pub fn send_rtp(&mut self, stream: usize, packet: &RtpPacket) -> Result<(), ClientError> {
+        self.send_frame(stream, PacketKind::Rtp, packet.to_vec())
+    }
+
+    fn set_require(&self, request: &mut Request) {
+        if !self.require.is_empty() {
+            request.headers.set(Headers::REQUIRE, self.require.join(", "));
+        }
+    }
+
+    fn queue_transmit(&mut self, data: Vec<u8>) {
+        self.buffered += data.len();
+        self.transmit.push_back(data);
+    }
+
+    pub fn handle_input(&mut self, data: &[u8], now: Instant) -> Result<(), ClientError> {
+        self.parser.push(data);
+        while let Some(item) = self.parser.poll_item()? {
+            match item {
+                RtspItem::Interleaved(frame) => {
+                    // Frames on channels that were not set up are garbage.
+                    if let Some((stream, kind)) = self.channels.route(&frame) {
+                        self.events.push_back(ClientEvent::Frame { stream, kind, frame });
+                    }
+                },
+                RtspItem::Message(Message::Response(response)) => self.handle_response(response, now),
+                RtspItem::Message(Message::Request(request)) => self.handle_request(request),
+            }
+        }
+        Ok(())
+    }
+
+    // Answers requests of the server, other than OPTIONS and PLAY_NOTIFY (e.g. ANNOUNCE or SET_PARAMETER) they
+    // are not supported.
+    fn handle_request(&mut self, request: Request) {
+        let status = match request.method {
+            Method::Options => Response::OK,
+            Method::PlayNotify => {
+                if let Some(properties) = request.headers.get(Headers::MEDIA_PROPERTIES) {
+                    self.media_properties = Some(properties.to_string());
+                }
+                self.events.push_back(ClientEvent::PlayNotify {
+                    reason: request.headers.get(Headers::NOTIFY_REASON).unwrap_or_default().to_string(),
+                    range: request.headers.get(Headers::RANGE).map(String::from),
+                });
+                Response::OK
+            },
+            _ => Response::NOT_IMPLEMENTED,
+        };
+        let mut response = Response::new(status, request.cseq());
+        response.version = request.version;
+        if let (Some(session), Some(_)) = (&self.session, request.headers.get(Headers::SESSION)) {
+            response.headers.set(Headers::SESSION, &session.id);
+        }
+        self.queue_transmit(response.to_vec());
+    }
+
+    pub fn poll_transmit(&mut self) -> Option<Vec<u8>> {
+        let data = self.transmit.pop_front()?;
+        self.buffered -= data.len();
+        Some(data)
+    }
+
+    pub fn poll_event(&mut self) -> Option<ClientEvent> {
+        self.events.pop_front()
+    }
+
+    pub fn poll_timeout(&self) -> Option<Instant> {
+        self.keepalive
+    }
+
+    pub fn handle_timeout(&mut self, now: Instant) {
+        let Some(keepalive) = self.keepalive else {
+            return
+        };
+        if now < keepalive {
+            return
+        }
+        self.keepalive = Some(now + self.keepalive_interval());
+        // Any request in flight refreshes the session as well.
+        if self.outstanding.is_some() || !self.queue.is_empty() {
+            return
+        }
+        let method = if self.public.contains(&Method::GetParameter) { Method::GetParameter } else { Method::Options };
+        let request = Request::new(method, self.aggregate_url(), 0);
+        self.enqueue(request, Pending::KeepAlive);
+    }
+
+    fn keepalive_interval(&self) -> Duration {
+        let timeout = self.session.as_ref().and_then(|session| session.timeout).unwrap_or(SessionHeader::DEFAULT_TIMEOUT);
+        Duration::from_millis(timeout.max(2) * 500)
+    }
+
+    fn enqueue(&mut self, request: Request, pending: Pending) {
+        self.queue.push_back((request, pending));
+        self.flush();
+    }
+
+    // Sends next queued request unless one is awaiting response.
+    fn flush(&mut self) {
+        if self.outstanding.is_some() {
+            return
+        }
+        let Some((mut request, pending)) = self.queue.pop_front() else {
+            return
+        };
+        self.cseq += 1;
+        request.version = self.version;
+        request.headers.set(Headers::CSEQ, self.cseq);
+        request.headers.set(Headers::USER_AGENT, &self.user_agent);
+        if let Some(session) = &self.session {
+            request.headers.set(Headers::SESSION, &session.id);
+        }
+        if let Some(authenticator) = &mut self.authenticator {
+            request.headers.set(Headers::AUTHORIZATION, authenticator.authorization(request.method.as_str(), &request.uri));
+        }
+        self.queue_transmit(request.to_vec());
+        self.outstanding = Some((self.cseq, request, pending, std::mem::take(&mut self.retrying)));
+    }
+
+    fn handle_response(&mut self, response: Response, now: Instant) {
+        let Some((cseq, _, _, _)) = &self.outstanding else {
+            return
+        };
+        // Responses to requests given up on are ignored, a missing CSeq is blamed on the server.
+        if response.cseq().is_some_and(|value| value != *cseq) {
+            return
+        }
+        let Some((_, request, pending, retried)) = self.outstanding.take() else {
+            return
+        };
+        let method = request.method.clone();
+        // Server of older version either rejects the request or answers in its own version.
+        let downgrade = response.status == Response::VERSION_NOT_SUPPORTED && request.version > RtspVersion::V1_0;
+        if downgrade || response.version < self.version {
+            self.version = RtspVersion::V1_0;
+        }
+        let result = match response.status {
+            Response::VERSION_NOT_SUPPORTED if downgrade => Ok(true),
+            Response::UNAUTHORIZED if !retried => self.authenticate(&response),
+            _ => Ok(false),
+        };
+        let result = match result {
+            Ok(true) => {
+                self.queue.push_front((request, pending));
+                // Repeating in another version does not use up the retry with credentials.
+                self.retrying = !downgrade || retried;
+                Ok(())
+            },
+            Ok(false) => self.process_response(&response, pending),
+            Err(error) => Err(error),
+        };
+        if let Err(error) = result {
+            self.queue.clear();
+            self.planned = self.state;
+            self.events.push_back(ClientEvent::Failed { method, error });
+        }
+        if self.session.is_some() {
+            self.keepalive = Some(now + self.keepalive_interval());
+        }
+        self.flush();
+    }
+
+    // Answers challenges of 401 response, true if the request should be repeated with credentials.
+    fn authenticate(&mut self, response: &Response) -> Result<bool, ClientError> {
+        let Some((username, password)) = &self.credentials else {
+            return Ok(false)
+        };
+        let challenges: Vec<Challenge> = response.headers.get_all(Headers::WWW_AUTHENTICATE)
+            .flat_map(Challenge::parse_list)
+            .collect();
+        self.authenticator = Some(Authenticator::new(username, password, &challenges)?);
+        Ok(true)
+    }
+
+    fn update_media_properties(&mut self, response: &Response) {
+        if let Some(properties) = response.headers.get(Headers::MEDIA_PROPERTIES) {
+            self.media_properties = Some(properties.to_string());
+        }
+        if let Some(ranges) = response.headers.get(Headers::ACCEPT_RANGES) {
+            self.accept_ranges = ranges.split(',').map(str::trim).filter(|range| !range.is_empty()).map(String::from).collect();
+        }
+    }
+
+    fn process_response(&mut self, response: &Response, pending: Pending) -> Result<(), ClientError> {
+        if let Some(error) = ClientError::from_response(response) {
+            return Err(error)
+        }
+        match pending {
+            Pending::Options => {
+                self.public = response.headers.get(Headers::PUBLIC).map(parse_public).unwrap_or_default();
+                self.events.push_back(ClientEvent::Options(self.public.clone()));
+            },
+            Pending::Describe => {
+                let description = String::from_utf8_lossy(&response.body).parse::<SessionDescription>()?;
+                self.base = response.headers.get(Headers::CONTENT_BASE)
+                    .or(response.headers.get(Headers::CONTENT_LOCATION))
+                    .map(String::from);
+                self.description = Some(description.clone());
+                self.events.push_back(ClientEvent::Described(Box::new(description)));
+            },
+            Pending::Announce => self.events.push_back(ClientEvent::Announced),
+            Pending::Setup(stream) => {
+                let value = response.headers.get(Headers::SESSION);
+                let session = match (value, &self.session) {
+                    (Some(value), _) => value.parse::<SessionHeader>()?,
+                    (None, Some(session)) => session.clone(),
+                    (None, None) => return Err(ClientError::MissingHeader(Headers::SESSION)),
+                };
+                let value = response.headers.get(Headers::TRANSPORT).ok_or(ClientError::MissingHeader(Headers::TRANSPORT))?;
+                let transport = TransportSpec::parse_list(value)?.into_iter().next()
+                    .ok_or(ClientError::MissingHeader(Headers::TRANSPORT))?;
+                if let Some((rtp, rtcp)) = transport.interleaved() {
+                    self.channels.insert(stream, rtp, rtcp);
+                }
+                self.transports.retain(|(id, _)| *id != stream);
+                self.transports.push((stream, transport.clone()));
+                self.session = Some(session);
+                self.update_media_properties(response);
+                if self.state == ClientState::Init {
+                    self.state = ClientState::Ready;
+                }
+                self.events.push_back(ClientEvent::SetUp { stream, transport });
+            },
+            Pending::Play => {
+                self.state = ClientState::Playing;
+                self.update_media_properties(response);
+                self.events.push_back(ClientEvent::Playing {
+                    range: response.headers.get(Headers::RANGE).map(String::from),
+                    rtp_info: response.headers.get(Headers::RTP_INFO).map(String::from),
+                });
+            },
+            Pending::Pause => {
+                self.state = ClientState::Ready;
+                self.events.push_back(ClientEvent::Paused);
+            },
+            Pending::Record => {
+                self.state = ClientState::Recording;
+                self.events.push_back(ClientEvent::Recording);
+            },
+            Pending::Teardown => {
+                self.state = ClientState::Init;
+                self.session = None;
+                self.keepalive = None;
+                self.transports.clear();
+                self.channels = ChannelMap::new();
+                self.events.push_back(ClientEvent::TornDown);
+            },
+            Pending::KeepAlive => {},
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::UNIX_EPOCH;
+
+    use super::*;
+    use crate::headers::Range;
+    use crate::onvif::{backchannel_streams, BackchannelSender, BACKCHANNEL, REPLAY};
+
+    const SDP: &str = "v=0\r\n\
+        o=- 0 0 IN IP4 192.168.1.64\r\n\
+        s=Media Presentation\r\n\
+        t=0 0\r\n\
+        a=control:*\r\n\
+        m=video 0 RTP/AVP 96\r\n\
+        a=rtpmap:96 H264/90000\r\n\
+        a=control:trackID=1\r\n\
+        m=audio 0 RTP/AVP 0\r\n\
+        a=control:trackID=2\r\n";
+
+    fn transmit(client: &mut RtspClient) -> Request {
+        let data = client.poll_transmit().unwrap();
+        let mut parser = RtspParser::new();
+        parser.push(&data);
+        let Some(RtspItem::Message(Message::Request(request))) = parser.poll_item().unwrap() else { panic!() };
+        request
+    }
+
+    fn respond(client: &mut RtspClient, request: &Request, status: u16, headers: &[(&str, &str)], body: &[u8], now: Instant) {
+        let mut response = Response::new(status, request.cseq());
+        response.version = request.version;
+        for (name, value) in headers {
+            response.headers.append(name, value);
+        }
+        response.body = body.to_vec();
+        client.handle_input(&response.to_vec(), now).unwrap();
+    }
+
+    #[test]
+    fn play_over_tcp() {
+        let now = Instant::now();
+        let mut client = RtspClient::new("rtsp://192.168.1.64/Streaming/Channels/101");
+        client.options();
+        client.describe();
+        let request = transmit(&mut client);
+        assert_eq!(Method::Options, request.method);
+        assert_eq!(Some(1), request.cseq());
+        assert!(client.poll_transmit().is_none());
+        respond(&mut client, &request, 200, &[("Public", "OPTIONS, DESCRIBE, SETUP, PLAY, TEARDOWN, GET_PARAMETER")], b"", now);
+        assert!(matches!(client.poll_event(), Some(ClientEvent::Options(methods)) if methods.contains(&Method::GetParameter)));
+
+        let request = transmit(&mut client);
+        assert_eq!(Method::Describe, request.method);
+        assert_eq!(Some("application/sdp"), request.headers.get(Headers::ACCEPT));
+        respond(&mut client, &request, 200, &[("Content-Base", "rtsp://192.168.1.64/Streaming/Channels/101/")], SDP.as_bytes(), now);
+        assert!(matches!(client.poll_event(), Some(ClientEvent::Described(description)) if description.media.len() == 2));
+
+        client.setup(0, TransportSpec::tcp(0)).unwrap();
+        client.setup(1, TransportSpec::tcp(2)).unwrap();
+        client.play(Some("npt=0.000-")).unwrap();
+        let request = transmit(&mut client);
+        assert_eq!("rtsp://192.168.1.64/Streaming/Channels/101/trackID=1", request.uri);
+        assert_eq!(Some("RTP/AVP/TCP;unicast;interleaved=0-1"), request.headers.get(Headers::TRANSPORT));
+        assert!(request.headers.get(Headers::SESSION).is_none());
+        respond(&mut client, &request, 200, &[("Session", "12345678;timeout=30"), ("Transport", "RTP/AVP/TCP;unicast;interleaved=0-1;ssrc=1A2B3C4D")], b"", now);
+        assert!(matches!(client.poll_event(), Some(ClientEvent::SetUp { stream: 0, .. })));
+        assert_eq!(ClientState::Ready, client.state());
+
+        let request = transmit(&mut client);
+        assert_eq!("rtsp://192.168.1.64/Streaming/Channels/101/trackID=2", request.uri);
+        assert_eq!(Some("12345678"), request.headers.get(Headers::SESSION));
+        respond(&mut client, &request, 200, &[("Session", "12345678"), ("Transport", "RTP/AVP/TCP;unicast;interleaved=2-3")], b"", now);
+        assert!(matches!(client.poll_event(), Some(ClientEvent::SetUp { stream: 1, .. })));
+        assert_eq!(Some(0x1a2b3c4d), client.transport(0).unwrap().ssrc());
+
+        let request = transmit(&mut client);
+        assert_eq!(Method::Play, request.method);
+        assert_eq!("rtsp://192.168.1.64/Streaming/Channels/101/", request.uri);
+        assert_eq!(Some("npt=0.000-"), request.headers.get(Headers::RANGE));
+        respond(&mut client, &request, 200, &[("RTP-Info", "url=trackID=1;seq=1;rtptime=0")], b"", now);
+        assert!(matches!(client.poll_event(), Some(ClientEvent::Playing { rtp_info: Some(_), .. })));
+        assert_eq!(ClientState::Playing, client.state());
+
+        let frame = InterleavedFrame::new(2, vec![0x80, 0x00, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1]).unwrap();
+        let mut data = frame.to_vec();
+        data.extend_from_slice(&InterleavedFrame::new(9, vec![0; 4]).unwrap().to_vec());
+        client.handle_input(&data, now).unwrap();
+        assert!(matches!(client.poll_event(), Some(ClientEvent::Frame { stream: 1, kind: PacketKind::Rtp, .. })));
+        assert!(client.poll_event().is_none());
+
+        client.send_frame(0, PacketKind::Rtcp, vec![0x80, 0xc9, 0, 1, 0, 0, 0, 1]).unwrap();
+        assert_eq!(b'$', client.poll_transmit().unwrap()[0]);
+        assert!(matches!(client.send_frame(5, PacketKind::Rtp, Vec::new()).unwrap_err(), ClientError::UnknownStream(5)));
+    }
+
+    #[test]
+    fn send_keepalive_and_teardown() {
+        let now = Instant::now();
+        let mut client = RtspClient::new("rtsp://camera/live");
+        client.description = Some(SDP.parse().unwrap());
+        client.setup(0, TransportSpec::udp(5000)).unwrap();
+        let request = transmit(&mut client);
+        respond(&mut client, &request, 200, &[("Session", "abc;timeout=20"), ("Transport", "RTP/AVP;unicast;client_port=5000-5001;server_port=6000-6001")], b"", now);
+        assert_eq!(Some((6000, Some(6001))), client.transport(0).unwrap().server_port());
+        assert_eq!(Some(now + Duration::from_secs(10)), client.poll_timeout());
+
+        client.handle_timeout(now + Duration::from_secs(9));
+        assert!(client.poll_transmit().is_none());
+        client.handle_timeout(now + Duration::from_secs(10));
+        let request = transmit(&mut client);
+        assert_eq!(Method::Options, request.method);
+        assert_eq!(Some("abc"), request.headers.get(Headers::SESSION));
+        respond(&mut client, &request, 200, &[], b"", now + Duration::from_secs(11));
+        assert_eq!(Some(now + Duration::from_secs(21)), client.poll_timeout());
+        assert!(matches!(client.poll_event(), Some(ClientEvent::SetUp { stream: 0, .. })));
+        assert!(client.poll_event().is_none());
+
+        client.teardown().unwrap();
+        let request = transmit(&mut client);
+        assert_eq!(Method::Teardown, request.method);
+        respond(&mut client, &request, 200, &[], b"", now);
+        assert!(matches!(client.poll_event(), Some(ClientEvent::TornDown)));
+        assert_eq!(ClientState::Init, client.state());
+        assert!(client.poll_timeout().is_none());
+    }
+
+    #[test]
+    fn negotiate_rtsp_2_0() {
+        let now = Instant::now();
+        let mut client = RtspClient::new("rtsp://camera/live");
+        client.set_version(RtspVersion::V2_0);
+        client.description = Some(SDP.parse().unwrap());
+        assert!(matches!(client.record(None), Err(ClientError::UnsupportedMethod(Method::Record, RtspVersion::V2_0))));
+        client.setup(0, TransportSpec::tcp(0)).unwrap();
+        let request = transmit(&mut client);
+        assert_eq!(RtspVersion::V2_0, request.version);
+        // Session of RTSP 2.0 SETUP response has no timeout, keepalive follows the default of 60 seconds.
+        respond(&mut client, &request, 200, &[("Session", "abc"), ("Transport", "RTP/AVP/TCP;unicast;interleaved=0-1"),
+            ("Media-Properties", "No-Seeking, Time-Progressing, Time-Duration=0.0"), ("Accept-Ranges", "npt, clock")], b"", now);
+        assert_eq!(Some(now + Duration::from_secs(30)), client.poll_timeout());
+        assert_eq!(Some("No-Seeking, Time-Progressing, Time-Duration=0.0"), client.media_properties());
+        assert_eq!(&["npt".to_string(), "clock".to_string()], client.accept_ranges());
+        client.play(None).unwrap();
+        let request = transmit(&mut client);
+        respond(&mut client, &request, 200, &[], b"", now);
+
+        let mut notify = Request::new(Method::PlayNotify, "rtsp://camera/live", 854);
+        notify.version = RtspVersion::V2_0;
+        notify.headers.set(Headers::NOTIFY_REASON, "end-of-stream");
+        notify.headers.set(Headers::RANGE, "npt=-145");
+        notify.headers.set(Headers::SESSION, "abc");
+        client.handle_input(&notify.to_vec(), now).unwrap();
+        let mut parser = RtspParser::new();
+        parser.push(&client.poll_transmit().unwrap());
+        let Some(RtspItem::Message(Message::Response(response))) = parser.poll_item().unwrap() else { panic!() };
+        assert_eq!((RtspVersion::V2_0, 200, Some(854)), (response.version, response.status, response.cseq()));
+        assert_eq!(Some("abc"), response.headers.get(Headers::SESSION));
+        let events = std::iter::from_fn(|| client.poll_event()).collect::<Vec<_>>();
+        assert!(matches!(&events[..], [ClientEvent::SetUp { .. }, ClientEvent::Playing { .. }, ClientEvent::PlayNotify { reason, range: Some(range) }]
+            if reason == "end-of-stream" && range == "npt=-145"));
+        assert_eq!(RtspVersion::V2_0, client.version());
+    }
+
+    #[test]
+    fn replay_recording() {
+        let now = Instant::now();
+        let mut client = RtspClient::new("rtsp://nvr/recording/1");
+        client.add_require(REPLAY);
+        client.description = Some(SDP.parse().unwrap());
+        client.setup(0, TransportSpec::tcp(0)).unwrap();
+        let request = transmit(&mut client);
+        assert_eq!(Some("onvif-replay"), request.headers.get(Headers::REQUIRE));
+        respond(&mut client, &request, 200, &[("Session", "abc"), ("Transport", "RTP/AVP/TCP;unicast;interleaved=0-1")], b"", now);
+
+        let start = UNIX_EPOCH + Duration::from_secs(1_245_066_600);
+        let options = ReplayOptions {
+            range: Some(Range::Clock { start, end: Some(start - Duration::from_secs(60)) }),
+            scale: Some(-1.0),
+            rate_control: false,
+            ..Default::default()
+        };
+        client.replay(&options).unwrap();
+        let request = transmit(&mut client);
+        assert_eq!(Method::Play, request.method);
+        assert_eq!(Some("onvif-replay"), request.headers.get(Headers::REQUIRE));
+        assert_eq!(Some("abc"), request.headers.get(Headers::SESSION));
+        assert_eq!(Some("clock=20090615T115000Z-20090615T114900Z"), request.headers.get(Headers::RANGE));
+        assert_eq!(Some("-1"), request.headers.get(Headers::SCALE));
+        assert_eq!(Some("no"), request.headers.get(Headers::RATE_CONTROL));
+        assert!(request.headers.get(Headers::IMMEDIATE).is_none());
+        respond(&mut client, &request, 200, &[("Range", "clock=20090615T115000Z-20090615T114900Z")], b"", now);
+        assert_eq!(ClientState::Playing, client.state());
+    }
+
+    #[test]
+    fn fall_back_to_rtsp_1_0() {
+        let now = Instant::now();
+        let mut client = RtspClient::new("rtsp://camera/live");
+        client.set_version(RtspVersion::V2_0);
+        client.options();
+        let request = transmit(&mut client);
+        let mut response = Response::new(Response::VERSION_NOT_SUPPORTED, request.cseq());
+        response.version = RtspVersion::V2_0;
+        client.handle_input(&response.to_vec(), now).unwrap();
+        let request = transmit(&mut client);
+        assert_eq!((Method::Options, RtspVersion::V1_0), (request.method.clone(), request.version));
+        assert_eq!(RtspVersion::V1_0, client.version());
+        respond(&mut client, &request, 200, &[("Public", "OPTIONS, DESCRIBE")], b"", now);
+        assert!(matches!(client.poll_event(), Some(ClientEvent::Options(_))));
+
+        // Server answering in 1.0 keeps the client at 1.0 as well.
+        let mut client = RtspClient::new("rtsp://camera/live");
+        client.set_version(RtspVersion::V2_0);
+        client.describe();
+        let request = transmit(&mut client);
+        client.handle_input(&Response::new(Response::NOT_FOUND, request.cseq()).to_vec(), now).unwrap();
+        assert_eq!(RtspVersion::V1_0, client.version());
+        assert!(matches!(client.poll_event(), Some(ClientEvent::Failed { method: Method::Describe, error: ClientError::NotFound })));
+    }
+
+    #[test]
+    fn send_backchannel_audio() {
+        let now = Instant::now();
+        let sdp = format!("{}m=audio 0 RTP/AVP 0\r\na=sendonly\r\na=control:trackID=3\r\n", SDP);
+        let mut client = RtspClient::new("rtsp://admin:1234@[2001:db8::1]:554/onvif");
+        client.add_require(BACKCHANNEL);
+        client.describe();
+        let request = transmit(&mut client);
+        assert_eq!(Some("www.onvif.org/ver20/backchannel"), request.headers.get(Headers::REQUIRE));
+        respond(&mut client, &request, 200, &[("Content-Base", "rtsp://[2001:db8::1]/onvif/")], sdp.as_bytes(), now);
+        let Some(ClientEvent::Described(description)) = client.poll_event() else { panic!() };
+        let streams = backchannel_streams(&description);
+        assert_eq!(vec![2], streams);
+
+        client.setup(0, TransportSpec::tcp(0)).unwrap();
+        let request = transmit(&mut client);
+        assert_eq!(Some("www.onvif.org/ver20/backchannel"), request.headers.get(Headers::REQUIRE));
+        respond(&mut client, &request, 200, &[("Session", "abc"), ("Transport", "RTP/AVP/TCP;unicast;interleaved=0-1")], b"", now);
+        client.setup(2, TransportSpec::tcp(2)).unwrap();
+        let request = transmit(&mut client);
+        assert_eq!("rtsp://[2001:db8::1]/onvif/trackID=3", request.uri);
+        assert_eq!(Some("RTP/AVP/TCP;unicast;interleaved=2-3"), request.headers.get(Headers::TRANSPORT));
+        respond(&mut client, &request, 200, &[("Session", "abc"), ("Transport", "RTP/AVP/TCP;unicast;interleaved=2-3")], b"", now);
+        assert_eq!(Some((2, Some(3))), client.transport(2).and_then(TransportSpec::interleaved));
+        assert_eq!(None, client.udp_destination(2, PacketKind::Rtp));
+
+        let codecs = description.media[2].codecs().unwrap();
+        let mut sender = BackchannelSender::new(&codecs, 1400, 0x1234).unwrap();
+        let packets = sender.packetize(&[0xff; 160]).unwrap();
+        assert_eq!(1, packets.len());
+        assert_eq!(0, RtpPacket::from_slice(&packets[0]).unwrap().payload_type());
+        // The backchannel has its own channel pair next to that of the video.
+        client.send_frame(2, PacketKind::Rtp, packets[0].clone()).unwrap();
+        let frame = client.poll_transmit().unwrap();
+        assert_eq!(&[b'$', 2, 0, 172], &frame[..4]);
+        assert_eq!(&packets[0][..], &frame[4..]);
+        client.send_frame(2, PacketKind::Rtcp, vec![0x80, 0xc8, 0, 0]).unwrap();
+        assert_eq!(&[b'$', 3, 0, 4], &client.poll_transmit().unwrap()[..4]);
+    }
+
+    #[test]
+    fn send_backchannel_over_udp() {
+        let now = Instant::now();
+        let sdp = format!("{}m=audio 0 RTP/AVP 0\r\na=sendonly\r\na=control:trackID=3\r\n", SDP);
+        let mut client = RtspClient::new("rtsp://admin:1234@[2001:db8::1]:554/onvif");
+        client.description = Some(sdp.parse().unwrap());
+        client.setup(2, TransportSpec::udp(5000)).unwrap();
+        let request = transmit(&mut client);
+        respond(&mut client, &request, 200, &[("Session", "abc"), ("Transport", "RTP/AVP;unicast;client_port=5000-5001;server_port=6000-6001")], b"", now);
+        assert_eq!(Some(("2001:db8::1".to_string(), 6000)), client.udp_destination(2, PacketKind::Rtp));
+        assert_eq!(Some(("2001:db8::1".to_string(), 6001)), client.udp_destination(2, PacketKind::Rtcp));
+        assert_eq!(None, client.udp_destination(0, PacketKind::Rtp));
+    }
+
+    #[test]
+    fn report_unsupported_option() {
+        let now = Instant::now();
+        let mut client = RtspClient::new("rtsp://camera/live");
+        client.add_require(BACKCHANNEL);
+        client.describe();
+        let request = transmit(&mut client);
+        respond(&mut client, &request, 551, &[("Unsupported", "www.onvif.org/ver20/backchannel")], b"", now);
+        let Some(ClientEvent::Failed { error: ClientError::OptionNotSupported(tags), .. }) = client.poll_event() else { panic!() };
+        assert_eq!(vec![BACKCHANNEL.to_string()], tags);
+    }
+
+    #[test]
+    fn report_errors() {
+        let now = Instant::now();
+        let mut client = RtspClient::new("rtsp://camera/live");
+        assert!(matches!(client.play(None).unwrap_err(), ClientError::InvalidState(ClientState::Init)));
+        assert!(matches!(client.setup(0, TransportSpec::udp(5000)).unwrap_err(), ClientError::UnknownStream(0)));
+
+        client.describe();
+        client.options();
+        let request = transmit(&mut client);
+        respond(&mut client, &request, 401, &[("WWW-Authenticate", "Basic realm=\"camera\"")], b"", now);
+        let Some(ClientEvent::Failed { method: Method::Describe, error: ClientError::Unauthorized(challenges) }) = client.poll_event() else { panic!() };
+        assert_eq!(vec!["Basic realm=\"camera\"".to_string()], challenges);
+        assert!(client.poll_transmit().is_none());
+
+        client.description = Some(SDP.parse().unwrap());
+        client.setup(0, TransportSpec::multicast()).unwrap();
+        client.play(None).unwrap();
+        let request = transmit(&mut client);
+        respond(&mut client, &request, 461, &[], b"", now);
+        assert!(matches!(client.poll_event(), Some(ClientEvent::Failed { method: Method::Setup, error: ClientError::UnsupportedTransport })));
+        assert!(client.poll_transmit().is_none());
+        assert!(matches!(client.play(None).unwrap_err(), ClientError::InvalidState(ClientState::Init)));
+
+        client.setup(0, TransportSpec::multicast()).unwrap();
+        let request = transmit(&mut client);
+        respond(&mut client, &request, 200, &[("Transport", "RTP/AVP;multicast;destination=232.0.0.1;port=5000-5001")], b"", now);
+        assert!(matches!(client.poll_event(), Some(ClientEvent::Failed { error: ClientError::MissingHeader("Session"), .. })));
+
+        client.setup(0, TransportSpec::udp(5000)).unwrap();
+        let request = transmit(&mut client);
+        respond(&mut client, &request, 200, &[("Session", "1"), ("Transport", "RTP/AVP;unicast;client_port=5000-5001")], b"", now);
+        client.poll_event();
+        client.play(None).unwrap();
+        let request = transmit(&mut client);
+        respond(&mut client, &request, 454, &[], b"", now);
+        assert!(matches!(client.poll_event(), Some(ClientEvent::Failed { method: Method::Play, error: ClientError::SessionNotFound })));
+        let request = Response::new(555, Some(9));
+        let error = ClientError::from_response(&request).unwrap();
+        assert!(matches!(error, ClientError::Status(555, _)));
+    }
+
+    #[test]
+    fn retry_with_credentials() {
+        let now = Instant::now();
+        let mut client = RtspClient::new("rtsp://camera/live");
+        client.set_credentials("admin", "secret");
+        client.describe();
+        client.options();
+        let request = transmit(&mut client);
+        assert!(request.headers.get(Headers::AUTHORIZATION).is_none());
+        respond(&mut client, &request, 401, &[("WWW-Authenticate", "Digest realm=\"camera\", nonce=\"abc\", qop=\"auth\""),
+            ("WWW-Authenticate", "Basic realm=\"camera\"")], b"", now);
+        assert!(client.poll_event().is_none());
+
+        let request = transmit(&mut client);
+        assert_eq!(Method::Describe, request.method);
+        assert_eq!(Some(2), request.cseq());
+        let authorization = request.headers.get(Headers::AUTHORIZATION).unwrap();
+        assert!(authorization.starts_with("Digest username=\"admin\", realm=\"camera\", nonce=\"abc\", uri=\"rtsp://camera/live\""));
+        respond(&mut client, &request, 200, &[], SDP.as_bytes(), now);
+        assert!(matches!(client.poll_event(), Some(ClientEvent::Described(_))));
+
+        let request = transmit(&mut client);
+        assert_eq!(Method::Options, request.method);
+        assert!(request.headers.get(Headers::AUTHORIZATION).unwrap().contains("nc=00000002"));
+        respond(&mut client, &request, 401, &[("WWW-Authenticate", "Digest realm=\"camera\", nonce=\"def\", stale=true")], b"", now);
+        let request = transmit(&mut client);
+        assert!(request.headers.get(Headers::AUTHORIZATION).unwrap().contains("nonce=\"def\""));
+        respond(&mut client, &request, 401, &[("WWW-Authenticate", "Digest realm=\"camera\", nonce=\"ghi\"")], b"", now);
+        assert!(matches!(client.poll_event(), Some(ClientEvent::Failed { method: Method::Options, error: ClientError::Unauthorized(_) })));
+        assert!(client.poll_transmit().is_none());
+    }
+}
@@ -0,0 +1,413 @@
+use std::fmt;
+use std::str::FromStr;
+use base64::Engine;
+use base64::engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig};
+use base64::alphabet::STANDARD;
+
+use crate::rtp::RtpPacket;
+use crate::sdp::{Fmtp, MediaDescription, RtpMap};
+
+#[derive(Debug)]
+pub enum CodecError {
+    // Format parameter name and its value that cannot be decoded.
+    InvalidParameter(String, String),
+    // Payload type listed in m= line has neither rtpmap nor a static assignment.
+    UnknownPayloadType(u8),
+}
+
+// Cameras often omit base64 padding in sprop parameters.
+const BASE64: GeneralPurpose = GeneralPurpose::new(&STANDARD, GeneralPurposeConfig::new()
+    .with_decode_padding_mode(DecodePaddingMode::Indifferent));
+
+fn invalid(name: &str, value: &str) -> CodecError {
+    CodecError::InvalidParameter(name.to_string(), value.to_string())
+}
+
+// Decodes comma separated list of base64 encoded NAL units.
+fn decode_nal_units(fmtp: &Fmtp, name: &str) -> Result<Vec<Vec<u8>>, CodecError> {
+    let Some(value) = fmtp.get(name) else {
+        return Ok(Vec::new())
+    };
+    value.split(',')
+        .map(str::trim)
+        .filter(|unit| !unit.is_empty())
+        .map(|unit| BASE64.decode(unit).map_err(|_| invalid(name, value)))
+        .collect()
+}
+
+fn parse_param<T: FromStr>(fmtp: &Fmtp, name: &str) -> Result<Option<T>, CodecError> {
+    fmtp.get(name).map(|value| value.parse().map_err(|_| invalid(name, value))).transpose()
+}
+
+// profile-level-id of H.264 (RFC 6184 section 8.1): profile_idc, constraint flags and level_idc.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ProfileLevelId {
+    pub profile_idc: u8,
+    pub profile_iop: u8,
+    pub level_idc: u8,
+}
+
+impl FromStr for ProfileLevelId {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        if text.len() != 6 {
+            return Err(())
+        }
+        let value = u32::from_str_radix(text, 16).map_err(|_| ())?;
+        Ok(ProfileLevelId { profile_idc: (value >> 16) as u8, profile_iop: (value >> 8) as u8, level_idc: value as u8 })
+    }
+}
+
+impl fmt::Display for ProfileLevelId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{:02x}{:02x}{:02x}", self.profile_idc, self.profile_iop, self.level_idc)
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct H264Parameters {
+    pub profile_level_id: Option<ProfileLevelId>,
+    pub packetization_mode: u8,
+    pub sps: Vec<Vec<u8>>,
+    pub pps: Vec<Vec<u8>>,
+}
+
+impl H264Parameters {
+    const NAL_SPS: u8 = 7;
+    const NAL_PPS: u8 = 8;
+
+    pub fn from_fmtp(fmtp: &Fmtp) -> Result<H264Parameters, CodecError> {
+        let profile_level_id = match fmtp.get("profile-level-id") {
+            Some(value) => Some(value.parse().map_err(|_| invalid("profile-level-id", value))?),
+            None => None,
+        };
+        let mut parameters = H264Parameters {
+            profile_level_id,
+            packetization_mode: parse_param(fmtp, "packetization-mode")?.unwrap_or(0),
+            ..Default::default()
+        };
+        // Parameter sets come in one list, other NAL units sometimes found there are dropped.
+        for unit in decode_nal_units(fmtp, "sprop-parameter-sets")? {
+            match unit.first().map(|header| header & 0x1f) {
+                Some(H264Parameters::NAL_SPS) => parameters.sps.push(unit),
+                Some(H264Parameters::NAL_PPS) => parameters.pps.push(unit),
+                _ => {},
+            }
+        }
+        Ok(parameters)
+    }
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct H265Parameters {
+    pub vps: Vec<Vec<u8>>,
+    pub sps: Vec<Vec<u8>>,
+    pub pps: Vec<Vec<u8>>,
+}
+
+impl H265Parameters {
+    pub fn from_fmtp(fmtp: &Fmtp) -> Result<H265Parameters, CodecError> {
+        Ok(H265Parameters {
+            vps: decode_nal_units(fmtp, "sprop-vps")?,
+            sps: decode_nal_units(fmtp, "sprop-sps")?,
+            pps: decode_nal_units(fmtp, "sprop-pps")?,
+        })
+    }
+}
+
+// AudioSpecificConfig (ISO/IEC 14496-3 section 1.6.2.1), only the leading fields are decoded:
+//
+//   audioObjectType          5 bits, 31 escapes to 32 + 6 bits
+//   samplingFrequencyIndex   4 bits, 15 escapes to explicit 24 bit frequency
+//   channelConfiguration     4 bits
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AudioSpecificConfig {
+    object_type: u8,
+    sampling_frequency: u32,
+    channel_configuration: u8,
+    data: Vec<u8>,
+}
+
+impl AudioSpecificConfig {
+    const FREQUENCIES: [u32; 13] = [96000, 88200, 64000, 48000, 44100, 32000, 24000, 22050, 16000, 12000, 11025, 8000, 7350];
+
+    pub fn from_slice(slice: &[u8]) -> Option<AudioSpecificConfig> {
+        let mut bits = 0u64;
+        let mut available = 0usize;
+        let mut input = slice.iter();
+        let mut read = |count: usize| -> Option<u32> {
+            while available < count {
+                bits = (bits << 8) | *input.next()? as u64;
+                available += 8;
+            }
+            available -= count;
+            Some(((bits >> available) & ((1 << count) - 1)) as u32)
+        };
+        let mut object_type = read(5)? as u8;
+        if object_type == 31 {
+            object_type = 32 + read(6)? as u8;
+        }
+        let index = read(4)? as usize;
+        let sampling_frequency = match index {
+            15 => read(24)?,
+            index => *AudioSpecificConfig::FREQUENCIES.get(index)?,
+        };
+        let channel_configuration = read(4)? as u8;
+        Some(AudioSpecificConfig { object_type, sampling_frequency, channel_configuration, data: slice.to_vec() })
+    }
+
+    pub fn object_type(&self) -> u8 {
+        self.object_type
+    }
+
+    pub fn sampling_frequency(&self) -> u32 {
+        self.sampling_frequency
+    }
+
+    pub fn channel_configuration(&self) -> u8 {
+        self.channel_configuration
+    }
+
+    // Complete config as carried in SDP, decoders usually need it verbatim.
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+}
+
+// MPEG4-GENERIC parameters (RFC 3640 section 4.1), sizes of AU header fields are in bits.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AacParameters {
+    pub config: AudioSpecificConfig,
+    pub mode: String,
+    pub size_length: u8,
+    pub index_length: u8,
+    pub index_delta_length: u8,
+}
+
+impl AacParameters {
+    pub fn from_fmtp(fmtp: &Fmtp) -> Result<AacParameters, CodecError> {
+        let value = fmtp.get("config").ok_or_else(|| invalid("config", ""))?;
+        if !value.is_ascii() || value.len() % 2 != 0 {
+            return Err(invalid("config", value))
+        }
+        let data = (0..value.len()).step_by(2)
+            .map(|off| u8::from_str_radix(&value[off..off + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| invalid("config", value))?;
+        Ok(AacParameters {
+            config: AudioSpecificConfig::from_slice(&data).ok_or_else(|| invalid("config", value))?,
+            mode: fmtp.get("mode").unwrap_or_default().to_string(),
+            size_length: parse_param(fmtp, "sizelength")?.unwrap_or(0),
+            index_length: parse_param(fmtp, "indexlength")?.unwrap_or(0),
+            index_delta_length: parse_param(fmtp, "indexdeltalength")?.unwrap_or(0),
+        })
+    }
+}
+
+// Opus parameters (RFC 7587 section 6.1), receiver preferences for the stream sent to it.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OpusParameters {
+    pub stereo: bool,
+    pub max_playback_rate: u32,
+}
+
+impl OpusParameters {
+    pub fn from_fmtp(fmtp: Option<&Fmtp>) -> Result<OpusParameters, CodecError> {
+        let mut parameters = OpusParameters { stereo: false, max_playback_rate: 48000 };
+        if let Some(fmtp) = fmtp {
+            parameters.stereo = parse_param::<u8>(fmtp, "stereo")? == Some(1);
+            parameters.max_playback_rate = parse_param(fmtp, "maxplaybackrate")?.unwrap_or(48000);
+        }
+        Ok(parameters)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CodecParameters {
+    H264(H264Parameters),
+    H265(H265Parameters),
+    Aac(AacParameters),
+    Opus(OpusParameters),
+    // Codec without format parameters this crate understands.
+    Other,
+}
+
+// Codec is a payload format of a media section, bound to an RTP payload type.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Codec {
+    payload_type: u8,
+    encoding: String,
+    clock_rate: u32,
+    channels: Option<u16>,
+    parameters: CodecParameters,
+}
+
+impl Codec {
+    // Static payload types of RFC 3551 section 6 that are still in use.
+    fn from_static(payload_type: u8) -> Option<RtpMap> {
+        let (encoding, clock_rate, channels) = match payload_type {
+            0 => ("PCMU", 8000, Some(1)),
+            3 => ("GSM", 8000, Some(1)),
+            4 => ("G723", 8000, Some(1)),
+            8 => ("PCMA", 8000, Some(1)),
+            9 => ("G722", 8000, Some(1)),
+            10 => ("L16", 44100, Some(2)),
+            11 => ("L16", 44100, Some(1)),
+            14 => ("MPA", 90000, None),
+            18 => ("G729", 8000, Some(1)),
+            26 => ("JPEG", 90000, None),
+            32 => ("MPV", 90000, None),
+            33 => ("MP2T", 90000, None),
+            _ => return None,
+        };
+        Some(RtpMap { payload_type, encoding: encoding.to_string(), clock_rate, channels })
+    }
+
+    pub fn from_media(media: &MediaDescription, payload_type: u8) -> Result<Codec, CodecError> {
+        let rtpmap = media.rtpmap(payload_type)
+            .or_else(|| Codec::from_static(payload_type))
+            .ok_or(CodecError::UnknownPayloadType(payload_type))?;
+        let fmtp = media.fmtp(payload_type);
+        let parameters = match (rtpmap.encoding.to_ascii_uppercase().as_str(), &fmtp) {
+            ("H264", Some(fmtp)) => CodecParameters::H264(H264Parameters::from_fmtp(fmtp)?),
+            ("H264", None) => CodecParameters::H264(H264Parameters::default()),
+            ("H265", Some(fmtp)) => CodecParameters::H265(H265Parameters::from_fmtp(fmtp)?),
+            ("H265", None) => CodecParameters::H265(H265Parameters::default()),
+            ("MPEG4-GENERIC", Some(fmtp)) => CodecParameters::Aac(AacParameters::from_fmtp(fmtp)?),
+            ("OPUS", fmtp) => CodecParameters::Opus(OpusParameters::from_fmtp(fmtp.as_ref())?),
+            _ => CodecParameters::Other,
+        };
+        Ok(Codec {
+            payload_type,
+            encoding: rtpmap.encoding,
+            clock_rate: rtpmap.clock_rate,
+            channels: rtpmap.channels,
+            parameters,
+        })
+    }
+
+    pub fn payload_type(&self) -> u8 {
+        self.payload_type
+    }
+
+    pub fn encoding(&self) -> &str {
+        &self.encoding
+    }
+
+    pub fn clock_rate(&self) -> u32 {
+        self.clock_rate
+    }
+
+    pub fn channels(&self) -> Option<u16> {
+        self.channels
+    }
+
+    pub fn parameters(&self) -> &CodecParameters {
+        &self.parameters
+    }
+
+    pub fn matches(&self, packet: &RtpPacket) -> bool {
+        packet.payload_type() == self.payload_type
+    }
+}
+
+impl MediaDescription {
+    // Codecs of all payload types listed in m= line, in order of preference.
+    pub fn codecs(&self) -> Result<Vec<Codec>, CodecError> {
+        self.payload_types().into_iter().map(|payload_type| Codec::from_media(self, payload_type)).collect()
+    }
+
+    // Codec of the received packet, None if its payload type does not belong to this media.
+    pub fn codec_for(&self, packet: &RtpPacket) -> Result<Option<Codec>, CodecError> {
+        if !self.payload_types().contains(&packet.payload_type()) {
+            return Ok(None)
+        }
+        Codec::from_media(self, packet.payload_type()).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sdp::SessionDescription;
+
+    const SDP: &str = "v=0\r\n\
+        o=- 0 0 IN IP4 127.0.0.1\r\n\
+        s=Session\r\n\
+        t=0 0\r\n\
+        m=video 0 RTP/AVP 96\r\n\
+        a=rtpmap:96 H264/90000\r\n\
+        a=fmtp:96 packetization-mode=1;profile-level-id=64001F;sprop-parameter-sets=Z2QAH6zZQFAFuhAAAAMAEAAAAwPI8YMZYA,aOvjyyLA\r\n\
+        m=video 0 RTP/AVP 97\r\n\
+        a=rtpmap:97 H265/90000\r\n\
+        a=fmtp:97 sprop-vps=QAEMAf//AWAAAAMAkAAAAwAAAwBdlZgJ; sprop-sps=QgEBAWAAAAMAkAAAAwAAAwBdoAKAgC0WWVmkkyuAQAAA+kAAF3AC; sprop-pps=RAHBcrRiQA==\r\n\
+        m=audio 0 RTP/AVP 98 0\r\n\
+        a=rtpmap:98 mpeg4-generic/44100/2\r\n\
+        a=fmtp:98 streamtype=5;profile-level-id=15;mode=AAC-hbr;config=1210;sizelength=13;indexlength=3;indexdeltalength=3\r\n\
+        m=audio 0 RTP/SAVPF 111\r\n\
+        a=rtpmap:111 opus/48000/2\r\n\
+        a=fmtp:111 minptime=10;useinbandfec=1;stereo=1;maxplaybackrate=24000\r\n";
+
+    #[test]
+    fn extract_video_parameters() {
+        let session = SDP.parse::<SessionDescription>().unwrap();
+        let codecs = session.media[0].codecs().unwrap();
+        assert_eq!(96, codecs[0].payload_type());
+        assert_eq!(90000, codecs[0].clock_rate());
+        let CodecParameters::H264(h264) = codecs[0].parameters() else { panic!() };
+        assert_eq!(Some(ProfileLevelId { profile_idc: 0x64, profile_iop: 0x00, level_idc: 0x1f }), h264.profile_level_id);
+        assert_eq!(1, h264.packetization_mode);
+        assert_eq!(1, h264.sps.len());
+        assert_eq!(&[0x67, 0x64, 0x00, 0x1f], &h264.sps[0][..4]);
+        assert_eq!(vec![vec![0x68, 0xeb, 0xe3, 0xcb, 0x22, 0xc0]], h264.pps);
+
+        let codecs = session.media[1].codecs().unwrap();
+        let CodecParameters::H265(h265) = codecs[0].parameters() else { panic!() };
+        assert_eq!(0x40, h265.vps[0][0]);
+        assert_eq!(0x42, h265.sps[0][0]);
+        assert_eq!(vec![vec![0x44, 0x01, 0xc1, 0x72, 0xb4, 0x62, 0x40]], h265.pps);
+    }
+
+    #[test]
+    fn extract_audio_parameters() {
+        let session = SDP.parse::<SessionDescription>().unwrap();
+        let codecs = session.media[2].codecs().unwrap();
+        assert_eq!(2, codecs.len());
+        let CodecParameters::Aac(aac) = codecs[0].parameters() else { panic!() };
+        assert_eq!(2, aac.config.object_type());
+        assert_eq!(44100, aac.config.sampling_frequency());
+        assert_eq!(2, aac.config.channel_configuration());
+        assert_eq!(&[0x12, 0x10], aac.config.data());
+        assert_eq!("AAC-hbr", aac.mode);
+        assert_eq!((13, 3, 3), (aac.size_length, aac.index_length, aac.index_delta_length));
+        assert_eq!("PCMU", codecs[1].encoding());
+        assert_eq!(8000, codecs[1].clock_rate());
+        assert_eq!(CodecParameters::Other, *codecs[1].parameters());
+
+        let codecs = session.media[3].codecs().unwrap();
+        assert_eq!(Some(2), codecs[0].channels());
+        assert_eq!(CodecParameters::Opus(OpusParameters { stereo: true, max_playback_rate: 24000 }), *codecs[0].parameters());
+    }
+
+    #[test]
+    fn map_packet_to_codec() {
+        let session = SDP.parse::<SessionDescription>().unwrap();
+        let packet = RtpPacket::new(false, 0, 1, 160, 0x1234, &[0xff; 160]);
+        assert!(session.media[0].codec_for(&packet).unwrap().is_none());
+        let codec = session.media[2].codec_for(&packet).unwrap().unwrap();
+        assert!(codec.matches(&packet));
+        assert_eq!("PCMU", codec.encoding());
+    }
+
+    #[test]
+    fn reject_invalid_parameters() {
+        let session = "m=video 0 RTP/AVP 96 100\r\na=rtpmap:96 H264/90000\r\na=fmtp:96 profile-level-id=42e0\r\n"
+            .parse::<SessionDescription>().unwrap();
+        let error = Codec::from_media(&session.media[0], 96).unwrap_err();
+        assert!(matches!(error, CodecError::InvalidParameter(name, value) if name == "profile-level-id" && value == "42e0"));
+        assert!(matches!(Codec::from_media(&session.media[0], 100).unwrap_err(), CodecError::UnknownPayloadType(100)));
+        let config = AudioSpecificConfig::from_slice(&[0xf8, 0x1e, 0x0f, 0x42, 0x40, 0x20]).unwrap();
+        assert_eq!((32, 500000, 1), (config.object_type(), config.sampling_frequency(), config.channel_configuration()));
+    }
+}
@@ -0,0 +1,286 @@
+use std::fmt;
+use std::str::FromStr;
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use rand::RngCore;
+
+use crate::srtp::{SrtpContext, SrtpError, SrtpProfile};
+
+#[derive(Debug)]
+pub enum CryptoError {
+    InvalidFormat(String),
+    InvalidTag(String),
+    UnsupportedSuite(String),
+    InvalidKeyLen(usize),
+    InvalidLifetime(String),
+    InvalidMki(String),
+    Srtp(SrtpError),
+}
+
+impl From<SrtpError> for CryptoError {
+    fn from(error: SrtpError) -> Self {
+        CryptoError::Srtp(error)
+    }
+}
+
+impl SrtpProfile {
+    // Crypto suite names used in SDP security descriptions (RFC 4568, RFC 7714).
+    pub fn sdes_name(&self) -> &'static str {
+        match self {
+            SrtpProfile::Aes128CmHmacSha1_80 => "AES_CM_128_HMAC_SHA1_80",
+            SrtpProfile::Aes128CmHmacSha1_32 => "AES_CM_128_HMAC_SHA1_32",
+            SrtpProfile::AeadAes128Gcm => "AEAD_AES_128_GCM",
+            SrtpProfile::AeadAes256Gcm => "AEAD_AES_256_GCM",
+        }
+    }
+
+    pub fn from_sdes_name(name: &str) -> Option<SrtpProfile> {
+        [
+            SrtpProfile::Aes128CmHmacSha1_80,
+            SrtpProfile::Aes128CmHmacSha1_32,
+            SrtpProfile::AeadAes128Gcm,
+            SrtpProfile::AeadAes256Gcm,
+        ].into_iter().find(|profile| profile.sdes_name() == name)
+    }
+}
+
+// CryptoKey is a single inline key parameter: master key and salt, optional lifetime in packets and optional
+// master key identifier with its length in bytes.
+#[derive(Clone, Eq, PartialEq)]
+pub struct CryptoKey {
+    key: Vec<u8>,
+    salt: Vec<u8>,
+    lifetime: Option<u64>,
+    mki: Option<(u32, u8)>,
+}
+
+impl CryptoKey {
+    pub fn new(key: Vec<u8>, salt: Vec<u8>, lifetime: Option<u64>, mki: Option<(u32, u8)>) -> CryptoKey {
+        CryptoKey { key, salt, lifetime, mki }
+    }
+
+    pub fn key(&self) -> &[u8] {
+        &self.key
+    }
+
+    pub fn salt(&self) -> &[u8] {
+        &self.salt
+    }
+
+    pub fn lifetime(&self) -> Option<u64> {
+        self.lifetime
+    }
+
+    // MKI value and length of MKI field in SRTP packets.
+    pub fn mki(&self) -> Option<(u32, u8)> {
+        self.mki
+    }
+
+    // Parses "inline:<key||salt>[|lifetime][|MKI:length]".
+    fn parse(text: &str, profile: SrtpProfile) -> Result<CryptoKey, CryptoError> {
+        let inline = text.strip_prefix("inline:").ok_or_else(|| CryptoError::InvalidFormat(text.to_string()))?;
+        let mut fields = inline.split('|');
+        let material = fields.next().unwrap_or_default();
+        let material = STANDARD.decode(material).map_err(|_| CryptoError::InvalidFormat(material.to_string()))?;
+        if material.len() != profile.key_len() + profile.salt_len() {
+            return Err(CryptoError::InvalidKeyLen(material.len()))
+        }
+        let (key, salt) = material.split_at(profile.key_len());
+        let mut lifetime = None;
+        let mut mki = None;
+        for field in fields {
+            // Lifetime may be omitted while MKI is present, MKI is recognized by the colon.
+            if let Some((value, len)) = field.split_once(':') {
+                let value = value.parse::<u32>().map_err(|_| CryptoError::InvalidMki(field.to_string()))?;
+                let len = len.parse::<u8>().ok().filter(|len| (1..=128).contains(len))
+                    .ok_or_else(|| CryptoError::InvalidMki(field.to_string()))?;
+                mki = Some((value, len));
+            } else if let Some(exp) = field.strip_prefix("2^") {
+                let exp = exp.parse::<u32>().ok().filter(|exp| *exp < 64)
+                    .ok_or_else(|| CryptoError::InvalidLifetime(field.to_string()))?;
+                lifetime = Some(1u64 << exp);
+            } else {
+                lifetime = Some(field.parse::<u64>().map_err(|_| CryptoError::InvalidLifetime(field.to_string()))?);
+            }
+        }
+        Ok(CryptoKey { key: key.to_vec(), salt: salt.to_vec(), lifetime, mki })
+    }
+}
+
+impl fmt::Display for CryptoKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "inline:{}", STANDARD.encode([&self.key[..], &self.salt].concat()))?;
+        match self.lifetime {
+            Some(lifetime) if lifetime.is_power_of_two() => write!(f, "|2^{}", lifetime.trailing_zeros())?,
+            Some(lifetime) => write!(f, "|{}", lifetime)?,
+            None => {},
+        }
+        if let Some((value, len)) = self.mki {
+            write!(f, "|{}:{}", value, len)?;
+        }
+        Ok(())
+    }
+}
+
+// Key material must not leak into logs.
+impl fmt::Debug for CryptoKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        f.debug_struct("CryptoKey")
+            .field("key_len", &self.key.len())
+            .field("salt_len", &self.salt.len())
+            .field("lifetime", &self.lifetime)
+            .field("mki", &self.mki)
+            .finish()
+    }
+}
+
+// CryptoAttribute is the value of SDP a=crypto attribute (RFC 4568 section 9.1):
+//
+//   a=crypto:<tag> <crypto-suite> <key-params> [<session-params>]
+//
+// Key parameters are separated by semicolons, session parameters by spaces and are kept as is.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CryptoAttribute {
+    tag: u32,
+    profile: SrtpProfile,
+    keys: Vec<CryptoKey>,
+    params: Vec<String>,
+}
+
+impl CryptoAttribute {
+    pub fn new(tag: u32, profile: SrtpProfile, keys: Vec<CryptoKey>, params: Vec<String>) -> CryptoAttribute {
+        CryptoAttribute { tag, profile, keys, params }
+    }
+
+    // Creates attribute with a fresh random master key and salt.
+    pub fn generate(tag: u32, profile: SrtpProfile) -> CryptoAttribute {
+        let mut rng = rand::thread_rng();
+        let mut key = vec![0u8; profile.key_len()];
+        let mut salt = vec![0u8; profile.salt_len()];
+        rng.fill_bytes(&mut key);
+        rng.fill_bytes(&mut salt);
+        CryptoAttribute::new(tag, profile, vec![CryptoKey::new(key, salt, None, None)], Vec::new())
+    }
+
+    pub fn tag(&self) -> u32 {
+        self.tag
+    }
+
+    pub fn profile(&self) -> SrtpProfile {
+        self.profile
+    }
+
+    pub fn keys(&self) -> &[CryptoKey] {
+        &self.keys
+    }
+
+    pub fn params(&self) -> &[String] {
+        &self.params
+    }
+
+    // SRTP context keyed with the first master key.
+    pub fn context(&self) -> Result<SrtpContext, CryptoError> {
+        let key = self.keys.first().ok_or_else(|| CryptoError::InvalidFormat(String::new()))?;
+        Ok(SrtpContext::new(self.profile, &key.key, &key.salt)?)
+    }
+}
+
+impl FromStr for CryptoAttribute {
+    type Err = CryptoError;
+
+    // Accepts attribute value with or without "a=crypto:" prefix.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let value = text.trim();
+        let value = value.strip_prefix("a=").unwrap_or(value);
+        let value = value.strip_prefix("crypto:").unwrap_or(value);
+        let mut fields = value.split_ascii_whitespace();
+        let (tag, suite, keys) = match (fields.next(), fields.next(), fields.next()) {
+            (Some(tag), Some(suite), Some(keys)) => (tag, suite, keys),
+            _ => return Err(CryptoError::InvalidFormat(text.to_string())),
+        };
+        // Tag is 1 to 9 digits.
+        let tag = Some(tag).filter(|tag| tag.len() <= 9).and_then(|tag| tag.parse::<u32>().ok())
+            .ok_or_else(|| CryptoError::InvalidTag(tag.to_string()))?;
+        let profile = SrtpProfile::from_sdes_name(suite).ok_or_else(|| CryptoError::UnsupportedSuite(suite.to_string()))?;
+        let keys = keys.split(';').map(|key| CryptoKey::parse(key, profile)).collect::<Result<Vec<_>, _>>()?;
+        let params = fields.map(String::from).collect();
+        Ok(CryptoAttribute { tag, profile, keys, params })
+    }
+}
+
+impl fmt::Display for CryptoAttribute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{} {} ", self.tag, self.profile.sdes_name())?;
+        for (index, key) in self.keys.iter().enumerate() {
+            if index > 0 {
+                write!(f, ";")?;
+            }
+            write!(f, "{}", key)?;
+        }
+        for param in &self.params {
+            write!(f, " {}", param)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_crypto_attribute() {
+        // RFC 4568 section 4.
+        let text = "a=crypto:1 AES_CM_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR|2^20|1:4";
+        let attr = text.parse::<CryptoAttribute>().unwrap();
+        assert_eq!(1, attr.tag());
+        assert_eq!(SrtpProfile::Aes128CmHmacSha1_80, attr.profile());
+        assert_eq!(1, attr.keys().len());
+        let key = &attr.keys()[0];
+        assert_eq!(16, key.key().len());
+        assert_eq!(&[0x3d, 0x2d, 0x6e, 0x40][..], &key.key()[..4]);
+        assert_eq!(14, key.salt().len());
+        assert_eq!(Some(1 << 20), key.lifetime());
+        assert_eq!(Some((1, 4)), key.mki());
+        assert_eq!(&text[9..], attr.to_string());
+        assert!(attr.context().is_ok());
+    }
+
+    #[test]
+    fn parse_multiple_keys_and_session_params() {
+        let text = "2 AES_CM_128_HMAC_SHA1_32 inline:NzB4d1BINUAvLEw6UzF3WSJ+PSdFcGdUJShpX1Zj|2^20|1:32;\
+            inline:NzB4d1BINUAvLEw6UzF3WSJ+PSdFcGdUJShpX1Zj|1000|2:32 FEC_ORDER=FEC_SRTP UNENCRYPTED_SRTCP";
+        let attr = text.parse::<CryptoAttribute>().unwrap();
+        assert_eq!(2, attr.keys().len());
+        assert_eq!(Some(1000), attr.keys()[1].lifetime());
+        assert_eq!(Some((2, 32)), attr.keys()[1].mki());
+        assert_eq!(&["FEC_ORDER=FEC_SRTP".to_string(), "UNENCRYPTED_SRTCP".to_string()][..], attr.params());
+        assert_eq!(text, attr.to_string());
+    }
+
+    #[test]
+    fn reject_invalid_attributes() {
+        let error = "1 F8_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR".parse::<CryptoAttribute>().unwrap_err();
+        assert!(matches!(error, CryptoError::UnsupportedSuite(suite) if suite == "F8_128_HMAC_SHA1_80"));
+        let error = "1 AEAD_AES_128_GCM inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR".parse::<CryptoAttribute>().unwrap_err();
+        assert!(matches!(error, CryptoError::InvalidKeyLen(30)));
+        let error = "1 AES_CM_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR|2^x".parse::<CryptoAttribute>().unwrap_err();
+        assert!(matches!(error, CryptoError::InvalidLifetime(_)));
+        let error = "x AES_CM_128_HMAC_SHA1_80 inline:PS1uQCVeeCFCanVmcjkpPywjNWhcYD0mXXtxaVBR".parse::<CryptoAttribute>().unwrap_err();
+        assert!(matches!(error, CryptoError::InvalidTag(_)));
+    }
+
+    #[test]
+    fn generate_and_key_srtp() {
+        let offer = CryptoAttribute::generate(1, SrtpProfile::AeadAes128Gcm);
+        let answer = offer.to_string().parse::<CryptoAttribute>().unwrap();
+        assert_eq!(offer, answer);
+        let mut sender = offer.context().unwrap();
+        let mut receiver = answer.context().unwrap();
+        let plain = vec![0x80, 0x60, 0x00, 0x01, 0x00, 0x00, 0x00, 0x10, 0x12, 0x34, 0x56, 0x78, 0xde, 0xad];
+        let mut buf = plain.clone();
+        sender.protect_rtp(&mut buf).unwrap();
+        receiver.unprotect_rtp(&mut buf).unwrap();
+        assert_eq!(plain, buf);
+    }
+}
@@ -0,0 +1,269 @@
+use crate::srtp::{SrtpContext, SrtpError, SrtpProfile};
+
+#[derive(Debug)]
+pub enum DtlsError {
+    InvalidLen(usize),
+    // Negotiated protection profile is not supported by this crate.
+    UnsupportedProfile(u16),
+    // Handshake completed without use_srtp extension.
+    NoProfile,
+    // Keying material exporter failed, the message comes from the DTLS implementation.
+    Export(String),
+    Srtp(SrtpError),
+}
+
+impl From<SrtpError> for DtlsError {
+    fn from(error: SrtpError) -> Self {
+        DtlsError::Srtp(error)
+    }
+}
+
+// Role of this side in the DTLS handshake, client keys protect packets sent by the DTLS client.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DtlsRole {
+    Client,
+    Server,
+}
+
+// KeyingMaterialExporter is implemented on top of a DTLS connection once its handshake completes.  The crate
+// does not run DTLS itself, any implementation able to export keying material (RFC 5705) and report the
+// protection profile negotiated with use_srtp extension can key SRTP.
+pub trait KeyingMaterialExporter {
+    fn export_keying_material(&self, label: &str, context: Option<&[u8]>, len: usize) -> Result<Vec<u8>, DtlsError>;
+    // Protection profile identifier selected by the server, None if use_srtp was not negotiated.
+    fn selected_srtp_profile(&self) -> Option<u16>;
+}
+
+impl SrtpProfile {
+    // Protection profile identifiers registered for use_srtp extension (RFC 5764, RFC 7714).
+    pub const SRTP_AES128_CM_HMAC_SHA1_80: u16 = 0x0001;
+    pub const SRTP_AES128_CM_HMAC_SHA1_32: u16 = 0x0002;
+    pub const SRTP_AEAD_AES_128_GCM: u16 = 0x0007;
+    pub const SRTP_AEAD_AES_256_GCM: u16 = 0x0008;
+
+    pub fn from_use_srtp_id(id: u16) -> Option<SrtpProfile> {
+        match id {
+            SrtpProfile::SRTP_AES128_CM_HMAC_SHA1_80 => Some(SrtpProfile::Aes128CmHmacSha1_80),
+            SrtpProfile::SRTP_AES128_CM_HMAC_SHA1_32 => Some(SrtpProfile::Aes128CmHmacSha1_32),
+            SrtpProfile::SRTP_AEAD_AES_128_GCM => Some(SrtpProfile::AeadAes128Gcm),
+            SrtpProfile::SRTP_AEAD_AES_256_GCM => Some(SrtpProfile::AeadAes256Gcm),
+            _ => None,
+        }
+    }
+
+    pub fn use_srtp_id(&self) -> u16 {
+        match self {
+            SrtpProfile::Aes128CmHmacSha1_80 => SrtpProfile::SRTP_AES128_CM_HMAC_SHA1_80,
+            SrtpProfile::Aes128CmHmacSha1_32 => SrtpProfile::SRTP_AES128_CM_HMAC_SHA1_32,
+            SrtpProfile::AeadAes128Gcm => SrtpProfile::SRTP_AEAD_AES_128_GCM,
+            SrtpProfile::AeadAes256Gcm => SrtpProfile::SRTP_AEAD_AES_256_GCM,
+        }
+    }
+}
+
+// Data of use_srtp extension (RFC 5764 section 4.1.1) for DTLS implementations that need to build or
+// inspect it:
+//
+//   uint16 SRTPProtectionProfile[2];
+//   SRTPProtectionProfile SRTPProtectionProfiles<2..2^16-1>;
+//   opaque srtp_mki<0..255>;
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UseSrtp {
+    profiles: Vec<u16>,
+    mki: Vec<u8>,
+}
+
+impl UseSrtp {
+    pub fn new(profiles: Vec<u16>, mki: Vec<u8>) -> UseSrtp {
+        UseSrtp { profiles, mki }
+    }
+
+    pub fn from_slice(slice: &[u8]) -> Result<UseSrtp, DtlsError> {
+        if slice.len() < 2 {
+            return Err(DtlsError::InvalidLen(slice.len()))
+        }
+        let list_len = u16::from_be_bytes([slice[0], slice[1]]) as usize;
+        if !list_len.is_multiple_of(2) || slice.len() < 2 + list_len + 1 {
+            return Err(DtlsError::InvalidLen(slice.len()))
+        }
+        let profiles = slice[2..2 + list_len].chunks_exact(2).map(|id| u16::from_be_bytes([id[0], id[1]])).collect();
+        let mki_len = slice[2 + list_len] as usize;
+        let mki = slice.get(3 + list_len..3 + list_len + mki_len).ok_or(DtlsError::InvalidLen(slice.len()))?;
+        Ok(UseSrtp { profiles, mki: mki.to_vec() })
+    }
+
+    pub fn profiles(&self) -> &[u16] {
+        &self.profiles
+    }
+
+    pub fn mki(&self) -> &[u8] {
+        &self.mki
+    }
+
+    // Picks the first of offered profiles that is supported, as a server would.
+    pub fn select(&self) -> Option<SrtpProfile> {
+        self.profiles.iter().find_map(|id| SrtpProfile::from_use_srtp_id(*id))
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(3 + self.profiles.len() * 2 + self.mki.len());
+        buf.extend_from_slice(&((self.profiles.len() * 2) as u16).to_be_bytes());
+        for id in &self.profiles {
+            buf.extend_from_slice(&id.to_be_bytes());
+        }
+        buf.push(self.mki.len() as u8);
+        buf.extend_from_slice(&self.mki);
+        buf
+    }
+}
+
+// DtlsSrtpKeys holds master keys and salts of both directions exported from DTLS (RFC 5764 section 4.2).
+pub struct DtlsSrtpKeys {
+    profile: SrtpProfile,
+    client_key: Vec<u8>,
+    server_key: Vec<u8>,
+    client_salt: Vec<u8>,
+    server_salt: Vec<u8>,
+}
+
+impl DtlsSrtpKeys {
+    pub const EXPORTER_LABEL: &'static str = "EXTRACTOR-dtls_srtp";
+
+    pub fn from_exporter(exporter: &impl KeyingMaterialExporter) -> Result<DtlsSrtpKeys, DtlsError> {
+        let id = exporter.selected_srtp_profile().ok_or(DtlsError::NoProfile)?;
+        let profile = SrtpProfile::from_use_srtp_id(id).ok_or(DtlsError::UnsupportedProfile(id))?;
+        let len = 2 * (profile.key_len() + profile.salt_len());
+        let material = exporter.export_keying_material(DtlsSrtpKeys::EXPORTER_LABEL, None, len)?;
+        DtlsSrtpKeys::from_material(profile, &material)
+    }
+
+    // Splits exported material laid out as client key, server key, client salt, server salt.
+    pub fn from_material(profile: SrtpProfile, material: &[u8]) -> Result<DtlsSrtpKeys, DtlsError> {
+        let key_len = profile.key_len();
+        let salt_len = profile.salt_len();
+        if material.len() != 2 * (key_len + salt_len) {
+            return Err(DtlsError::InvalidLen(material.len()))
+        }
+        let (client_key, rest) = material.split_at(key_len);
+        let (server_key, rest) = rest.split_at(key_len);
+        let (client_salt, server_salt) = rest.split_at(salt_len);
+        Ok(DtlsSrtpKeys {
+            profile,
+            client_key: client_key.to_vec(),
+            server_key: server_key.to_vec(),
+            client_salt: client_salt.to_vec(),
+            server_salt: server_salt.to_vec(),
+        })
+    }
+
+    pub fn profile(&self) -> SrtpProfile {
+        self.profile
+    }
+
+    pub fn client_key(&self) -> &[u8] {
+        &self.client_key
+    }
+
+    pub fn server_key(&self) -> &[u8] {
+        &self.server_key
+    }
+
+    pub fn client_salt(&self) -> &[u8] {
+        &self.client_salt
+    }
+
+    pub fn server_salt(&self) -> &[u8] {
+        &self.server_salt
+    }
+
+    // Context protecting packets sent by this side.
+    pub fn outbound(&self, role: DtlsRole) -> Result<SrtpContext, DtlsError> {
+        match role {
+            DtlsRole::Client => Ok(SrtpContext::new(self.profile, &self.client_key, &self.client_salt)?),
+            DtlsRole::Server => Ok(SrtpContext::new(self.profile, &self.server_key, &self.server_salt)?),
+        }
+    }
+
+    // Context unprotecting packets received from the peer.
+    pub fn inbound(&self, role: DtlsRole) -> Result<SrtpContext, DtlsError> {
+        match role {
+            DtlsRole::Client => self.outbound(DtlsRole::Server),
+            DtlsRole::Server => self.outbound(DtlsRole::Client),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedExporter {
+        profile: Option<u16>,
+    }
+
+    impl KeyingMaterialExporter for FixedExporter {
+        fn export_keying_material(&self, label: &str, context: Option<&[u8]>, len: usize) -> Result<Vec<u8>, DtlsError> {
+            assert_eq!("EXTRACTOR-dtls_srtp", label);
+            assert!(context.is_none());
+            Ok((0..len as u8).collect())
+        }
+
+        fn selected_srtp_profile(&self) -> Option<u16> {
+            self.profile
+        }
+    }
+
+    #[test]
+    fn split_keying_material() {
+        let exporter = FixedExporter { profile: Some(0x0001) };
+        let keys = DtlsSrtpKeys::from_exporter(&exporter).unwrap();
+        assert_eq!(SrtpProfile::Aes128CmHmacSha1_80, keys.profile());
+        assert_eq!(&(0..16).collect::<Vec<u8>>()[..], keys.client_key());
+        assert_eq!(&(16..32).collect::<Vec<u8>>()[..], keys.server_key());
+        assert_eq!(&(32..46).collect::<Vec<u8>>()[..], keys.client_salt());
+        assert_eq!(&(46..60).collect::<Vec<u8>>()[..], keys.server_salt());
+
+        let exporter = FixedExporter { profile: Some(0x0008) };
+        let keys = DtlsSrtpKeys::from_exporter(&exporter).unwrap();
+        assert_eq!(&(64..76).collect::<Vec<u8>>()[..], keys.client_salt());
+        assert_eq!(&(76..88).collect::<Vec<u8>>()[..], keys.server_salt());
+    }
+
+    #[test]
+    fn reject_missing_profile() {
+        let error = DtlsSrtpKeys::from_exporter(&FixedExporter { profile: None }).err().unwrap();
+        assert!(matches!(error, DtlsError::NoProfile));
+        let error = DtlsSrtpKeys::from_exporter(&FixedExporter { profile: Some(0x0005) }).err().unwrap();
+        assert!(matches!(error, DtlsError::UnsupportedProfile(5)));
+    }
+
+    #[test]
+    fn key_both_directions() {
+        let exporter = FixedExporter { profile: Some(0x0007) };
+        let keys = DtlsSrtpKeys::from_exporter(&exporter).unwrap();
+        let mut client = keys.outbound(DtlsRole::Client).unwrap();
+        let mut server = keys.inbound(DtlsRole::Server).unwrap();
+        let plain = vec![0x80, 0x60, 0x00, 0x01, 0x00, 0x00, 0x00, 0x10, 0x12, 0x34, 0x56, 0x78, 0xde, 0xad];
+        let mut buf = plain.clone();
+        client.protect_rtp(&mut buf).unwrap();
+        server.unprotect_rtp(&mut buf).unwrap();
+        assert_eq!(plain, buf);
+
+        let mut buf = plain.clone();
+        buf[3] = 2;
+        keys.outbound(DtlsRole::Server).unwrap().protect_rtp(&mut buf).unwrap();
+        assert!(matches!(server.unprotect_rtp(&mut buf).unwrap_err(), SrtpError::AuthFailed));
+    }
+
+    #[test]
+    fn parse_use_srtp_extension() {
+        let data = [0x00, 0x04, 0x00, 0x07, 0x00, 0x01, 0x00];
+        let ext = UseSrtp::from_slice(&data).unwrap();
+        assert_eq!(&[0x0007, 0x0001][..], ext.profiles());
+        assert!(ext.mki().is_empty());
+        assert_eq!(Some(SrtpProfile::AeadAes128Gcm), ext.select());
+        assert_eq!(&data[..], &ext.to_vec()[..]);
+        assert!(matches!(UseSrtp::from_slice(&data[..6]).unwrap_err(), DtlsError::InvalidLen(6)));
+        assert_eq!(0x0002, SrtpProfile::Aes128CmHmacSha1_32.use_srtp_id());
+    }
+}
@@ -0,0 +1,399 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::mux::{MuxError, MuxPacket, PacketKind};
+use crate::ntp::NtpTimestamp;
+use crate::rtcp::{DataBYE, DataRR, DataSDES, DataSR, RtcpMode, RtcpPacket, SdesChunk, SynSource};
+use crate::rtp::{RtpPacket, RtpPacketizer};
+use crate::scheduler::{RtcpScheduler, RtcpTimeout};
+use crate::session::{RtpSession, SessionEvent};
+use crate::stats::ReceptionStats;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum EndpointEvent {
+    NewSource(u32),
+    Bye(u32),
+    Timeout(u32),
+    // Local SSRC was replaced after a collision, subsequent media is sent with the new one.
+    SsrcChanged { old: u32, new: u32 },
+    LoopDetected(SocketAddr),
+    Conflict { ssrc: u32, source: SocketAddr },
+    // Sender report of a remote source, maps its RTP timestamps to wallclock time.
+    SenderReport { ssrc: u32, ntpts: NtpTimestamp, rtpts: u32 },
+    // Report block about local source received from a remote participant.
+    ReportReceived { ssrc: u32, report: SynSource, rtt: Option<Duration> },
+    // Packets with sequence numbers starting at first are missing from the remote source.
+    LossDetected { ssrc: u32, first: u16, count: u16 },
+}
+
+// Endpoint is an I/O free RTP/RTCP participant sending a single media stream.  Received datagrams are fed
+// to handle_incoming(), media frames to send_media().  Datagrams to send are picked up with poll_transmit()
+// and events with poll_event().  RTCP reports are sent when the time returned by poll_timeout() comes and
+// handle_timeout() is called.
+pub struct Endpoint {
+    session: RtpSession,
+    scheduler: RtcpScheduler,
+    packetizer: RtpPacketizer,
+    stats: HashMap<u32, ReceptionStats>,
+    clock_rate: u32,
+    mode: RtcpMode,
+    start: Instant,
+    wallclock: SystemTime,
+    packets_sent: u32,
+    octets_sent: u32,
+    last_rtpts: u32,
+    last_media: Option<Instant>,
+    closed: bool,
+    transmits: VecDeque<(PacketKind, Vec<u8>)>,
+    events: VecDeque<EndpointEvent>,
+}
+
+impl Endpoint {
+    // Report blocks that fit into a single SR or RR.
+    const MAX_REPORTS: usize = 31;
+
+    // Creates endpoint sending media of given payload type and clock rate in a session of given bandwidth
+    // in bits per second.  Wallclock is the time of day at now, sender reports derive their NTP time from it.
+    pub fn new(cname: impl Into<String>, mtu: usize, payload_type: u8, clock_rate: u32, bandwidth: u64, now: Instant,
+        wallclock: SystemTime) -> Self {
+        let mut session = RtpSession::new(cname);
+        let ssrc = session.add_local_ssrc();
+        Endpoint {
+            session,
+            scheduler: RtcpScheduler::new(bandwidth, now),
+            packetizer: RtpPacketizer::new(mtu, payload_type, ssrc),
+            stats: HashMap::new(),
+            clock_rate,
+            mode: RtcpMode::Compound,
+            start: now,
+            wallclock,
+            packets_sent: 0,
+            octets_sent: 0,
+            last_rtpts: 0,
+            last_media: None,
+            closed: false,
+            transmits: VecDeque::new(),
+            events: VecDeque::new(),
+        }
+    }
+
+    pub fn ssrc(&self) -> u32 {
+        self.packetizer.ssrc()
+    }
+
+    pub fn session(&self) -> &RtpSession {
+        &self.session
+    }
+
+    pub fn set_rtcp_mode(&mut self, mode: RtcpMode) {
+        self.mode = mode;
+    }
+
+    pub fn stats(&self, ssrc: u32) -> Option<&ReceptionStats> {
+        self.stats.get(&ssrc)
+    }
+
+    // Wallclock time corresponding to the monotonic instant.
+    fn ntp_time(&self, now: Instant) -> NtpTimestamp {
+        NtpTimestamp::from_system_time(self.wallclock + now.saturating_duration_since(self.start))
+    }
+
+    // Processes a datagram received from given address.  Returns accepted RTP packet for depacketization.
+    pub fn handle_incoming<'a>(&mut self, datagram: &'a [u8], source: SocketAddr, now: Instant) -> Result<Option<RtpPacket<'a>>, MuxError> {
+        if self.closed {
+            return Ok(None)
+        }
+        match MuxPacket::from_slice(datagram, self.mode)? {
+            MuxPacket::Rtp(packet) => {
+                let valid = self.session.receive_rtp(&packet, source, now);
+                self.process_session_events(now);
+                Ok((valid && self.receive_rtp(&packet, now)).then_some(packet))
+            },
+            MuxPacket::Rtcp(packets) => {
+                self.scheduler.received(datagram.len());
+                let valid = self.session.receive_rtcp(&packets, source, now);
+                self.process_session_events(now);
+                if valid {
+                    self.receive_rtcp(&packets, now);
+                }
+                Ok(None)
+            },
+        }
+    }
+
+    fn receive_rtp(&mut self, packet: &RtpPacket, now: Instant) -> bool {
+        let ssrc = packet.ssrc();
+        let stats = self.stats.entry(ssrc)
+            .or_insert_with(|| ReceptionStats::new(packet.seq_number(), self.clock_rate, self.start));
+        match stats.update(packet.seq_number(), packet.timestamp(), now) {
+            None => false,
+            Some(0) => true,
+            Some(count) => {
+                let first = packet.seq_number().wrapping_sub(count);
+                self.events.push_back(EndpointEvent::LossDetected { ssrc, first, count });
+                true
+            },
+        }
+    }
+
+    fn receive_rtcp(&mut self, packets: &[RtcpPacket], now: Instant) {
+        let arrival = self.ntp_time(now);
+        let local = self.ssrc();
+        for packet in packets {
+            let (sender, reports) = match packet.payload_type() {
+                RtcpPacket::SR => match DataSR::from_packet(packet) {
+                    Ok(sr) => {
+                        if let Some(stats) = self.stats.get_mut(&sr.ssrc()) {
+                            stats.sr_received(sr.ntpts(), now);
+                        }
+                        self.events.push_back(EndpointEvent::SenderReport { ssrc: sr.ssrc(), ntpts: sr.ntpts(), rtpts: sr.rtpts() });
+                        (sr.ssrc(), sr.reports().to_vec())
+                    },
+                    Err(_) => continue,
+                },
+                RtcpPacket::RR => match DataRR::from_packet(packet) {
+                    Ok(rr) => (rr.ssrc(), rr.reports().to_vec()),
+                    Err(_) => continue,
+                },
+                RtcpPacket::BYE => {
+                    self.scheduler.bye_received(packet.size());
+                    continue
+                },
+                _ => continue,
+            };
+            for report in reports.into_iter().filter(|report| report.ssrc() == local) {
+                let rtt = report.rtt(arrival);
+                self.events.push_back(EndpointEvent::ReportReceived { ssrc: sender, report, rtt });
+            }
+        }
+    }
+
+    fn process_session_events(&mut self, now: Instant) {
+        while let Some(event) = self.session.poll_event() {
+            let event = match event {
+                SessionEvent::NewMember(ssrc) => EndpointEvent::NewSource(ssrc),
+                SessionEvent::MemberBye(ssrc) => {
+                    self.stats.remove(&ssrc);
+                    EndpointEvent::Bye(ssrc)
+                },
+                SessionEvent::MemberTimeout(ssrc) => {
+                    self.stats.remove(&ssrc);
+                    EndpointEvent::Timeout(ssrc)
+                },
+                SessionEvent::SsrcChanged { old, new } => {
+                    self.packetizer.set_ssrc(new);
+                    self.packets_sent = 0;
+                    self.octets_sent = 0;
+                    EndpointEvent::SsrcChanged { old, new }
+                },
+                SessionEvent::LoopDetected(source) => EndpointEvent::LoopDetected(source),
+                SessionEvent::ThirdPartyConflict { ssrc, source } => EndpointEvent::Conflict { ssrc, source },
+            };
+            self.events.push_back(event);
+        }
+        while let Some(datagram) = self.session.poll_transmit() {
+            self.transmits.push_back((PacketKind::Rtcp, datagram));
+        }
+        self.update_members(now);
+    }
+
+    fn update_members(&mut self, now: Instant) {
+        let we_sent = self.scheduler.we_sent();
+        self.scheduler.update_members(now, self.session.member_count(), self.session.sender_count() + we_sent as usize);
+    }
+
+    // Packetizes a media frame advancing RTP timestamp by given number of clock ticks and queues RTP packets.
+    pub fn send_media(&mut self, frame: &[u8], ticks: u32, now: Instant) {
+        if self.closed {
+            return
+        }
+        for packet in self.packetizer.packetize(frame, ticks) {
+            self.packets_sent = self.packets_sent.wrapping_add(1);
+            self.octets_sent = self.octets_sent.wrapping_add(packet.payload().len() as u32);
+            self.last_rtpts = packet.timestamp();
+            self.transmits.push_back((PacketKind::Rtp, packet.to_vec()));
+        }
+        self.last_media = Some(now);
+        self.scheduler.media_sent(now);
+    }
+
+    // Time handle_timeout() should be called next, None once BYE is sent.
+    pub fn poll_timeout(&self) -> Option<Instant> {
+        (!self.closed).then(|| self.scheduler.poll_timeout())
+    }
+
+    pub fn handle_timeout(&mut self, now: Instant) {
+        if self.closed {
+            return
+        }
+        self.session.handle_timeout(now, self.scheduler.deterministic_interval());
+        self.process_session_events(now);
+        match self.scheduler.handle_timeout(now) {
+            Some(RtcpTimeout::Report) => {
+                let datagram = self.build_report(now);
+                self.scheduler.sent(now, datagram.len());
+                self.transmits.push_back((PacketKind::Rtcp, datagram));
+            },
+            Some(RtcpTimeout::Bye) => self.send_bye(now),
+            None => {},
+        }
+    }
+
+    // Leaves the session sending BYE either immediately or when BYE reconsideration allows.
+    pub fn close(&mut self, now: Instant) {
+        if !self.closed && self.scheduler.leave(now) {
+            self.send_bye(now);
+        }
+    }
+
+    fn send_bye(&mut self, now: Instant) {
+        let mut datagram = self.build_report(now);
+        datagram.extend(DataBYE::new(vec![self.ssrc()], None).to_vec());
+        self.transmits.push_back((PacketKind::Rtcp, datagram));
+        self.closed = true;
+    }
+
+    // Builds compound packet with SR when media was sent since the second previous report or RR otherwise,
+    // followed by SDES CNAME.
+    fn build_report(&mut self, now: Instant) -> Vec<u8> {
+        let ssrc = self.ssrc();
+        let mut reports: Vec<SynSource> = self.stats.iter_mut()
+            .filter(|(_, stats)| stats.is_active())
+            .map(|(source, stats)| stats.report(*source, now))
+            .collect();
+        reports.truncate(Endpoint::MAX_REPORTS);
+        let mut datagram = match self.last_media {
+            Some(last) if self.scheduler.we_sent() => {
+                // Extrapolate RTP timestamp of the last packet to the report time.
+                let elapsed = now.saturating_duration_since(last);
+                let ticks = (elapsed.as_nanos() * self.clock_rate as u128 / 1_000_000_000) as u32;
+                let rtpts = self.last_rtpts.wrapping_add(ticks);
+                DataSR::new(ssrc, self.ntp_time(now), rtpts, self.packets_sent, self.octets_sent, reports).to_vec()
+            },
+            _ => DataRR::new(ssrc, reports).to_vec(),
+        };
+        let cname = self.session.cname().as_bytes();
+        datagram.extend(DataSDES::new(vec![SdesChunk::new(ssrc, vec![(DataSDES::CNAME, cname)])]).to_vec());
+        datagram
+    }
+
+    pub fn poll_transmit(&mut self) -> Option<(PacketKind, Vec<u8>)> {
+        self.transmits.pop_front()
+    }
+
+    pub fn poll_event(&mut self) -> Option<EndpointEvent> {
+        self.events.pop_front()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wallclock() -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(1_700_000_000)
+    }
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([10, 0, 0, 1], port))
+    }
+
+    // Delivers all queued datagrams from one endpoint to another, returning payload sizes of accepted RTP.
+    fn deliver(from: &mut Endpoint, to: &mut Endpoint, now: Instant) -> Vec<usize> {
+        let mut accepted = Vec::new();
+        while let Some((kind, datagram)) = from.poll_transmit() {
+            let port = if kind == PacketKind::Rtp { 5000 } else { 5001 };
+            if let Some(packet) = to.handle_incoming(&datagram, addr(port), now).unwrap() {
+                accepted.push(packet.payload().len());
+            }
+        }
+        accepted
+    }
+
+    fn events(endpoint: &mut Endpoint) -> Vec<EndpointEvent> {
+        std::iter::from_fn(|| endpoint.poll_event()).collect()
+    }
+
+    // Advances the clock until the endpoint sends a report, timer reconsideration may postpone it.
+    fn report(endpoint: &mut Endpoint, not_before: Instant) -> (Instant, Vec<u8>) {
+        loop {
+            let now = endpoint.poll_timeout().unwrap().max(not_before);
+            endpoint.handle_timeout(now);
+            if let Some((kind, datagram)) = endpoint.poll_transmit() {
+                assert_eq!(PacketKind::Rtcp, kind);
+                return (now, datagram)
+            }
+        }
+    }
+
+    #[test]
+    fn exchange_media_and_reports() {
+        let start = Instant::now();
+        let mut camera = Endpoint::new("camera@host", 1200, 96, 90000, 1_000_000, start, wallclock());
+        let mut viewer = Endpoint::new("viewer@host", 1200, 96, 90000, 1_000_000, start, wallclock());
+        camera.send_media(&[0u8; 2000], 3000, start);
+        assert_eq!(vec![1188, 812], deliver(&mut camera, &mut viewer, start));
+        assert_eq!(vec![EndpointEvent::NewSource(camera.ssrc())], events(&mut viewer));
+        assert_eq!(2, viewer.stats(camera.ssrc()).unwrap().received());
+
+        let (now, datagram) = report(&mut camera, start);
+        let packets = RtcpPacket::compound_from_slice(&datagram, RtcpMode::Compound).unwrap();
+        assert_eq!(RtcpPacket::SR, packets[0].payload_type());
+        let sr = DataSR::from_packet(&packets[0]).unwrap();
+        assert_eq!(2, sr.packets());
+        assert_eq!(2000, sr.octets());
+        assert_eq!(NtpTimestamp::from(wallclock() + (now - start)), sr.ntpts());
+        viewer.handle_incoming(&datagram, addr(5001), now).unwrap();
+        assert!(matches!(events(&mut viewer)[..], [EndpointEvent::SenderReport { ssrc, .. }] if ssrc == camera.ssrc()));
+
+        let (later, datagram) = report(&mut viewer, now + Duration::from_millis(250));
+        let packets = RtcpPacket::compound_from_slice(&datagram, RtcpMode::Compound).unwrap();
+        assert_eq!(RtcpPacket::RR, packets[0].payload_type());
+        camera.handle_incoming(&datagram, addr(6001), later).unwrap();
+        let events = events(&mut camera);
+        assert_eq!(EndpointEvent::NewSource(viewer.ssrc()), events[0]);
+        match &events[1] {
+            EndpointEvent::ReportReceived { ssrc, report, rtt } => {
+                assert_eq!(viewer.ssrc(), *ssrc);
+                assert_eq!(0, report.packets_lost());
+                assert!(rtt.is_some());
+            },
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+
+    #[test]
+    fn detect_loss() {
+        let start = Instant::now();
+        let mut camera = Endpoint::new("camera@host", 100, 96, 90000, 1_000_000, start, wallclock());
+        let mut viewer = Endpoint::new("viewer@host", 100, 96, 90000, 1_000_000, start, wallclock());
+        camera.send_media(&[0u8; 300], 3000, start);
+        let datagrams: Vec<_> = std::iter::from_fn(|| camera.poll_transmit()).collect();
+        assert_eq!(4, datagrams.len());
+        let first = RtpPacket::from_slice(&datagrams[0].1).unwrap().seq_number();
+        for (_, datagram) in datagrams.iter().filter(|(_, datagram)| RtpPacket::from_slice(datagram).unwrap().seq_number() != first.wrapping_add(1)) {
+            viewer.handle_incoming(datagram, addr(5000), start).unwrap();
+        }
+        assert_eq!(vec![
+            EndpointEvent::NewSource(camera.ssrc()),
+            EndpointEvent::LossDetected { ssrc: camera.ssrc(), first: first.wrapping_add(1), count: 1 },
+        ], events(&mut viewer));
+        assert_eq!(1, viewer.stats(camera.ssrc()).unwrap().lost());
+    }
+
+    #[test]
+    fn close_sends_bye() {
+        let start = Instant::now();
+        let mut camera = Endpoint::new("camera@host", 1200, 96, 90000, 1_000_000, start, wallclock());
+        let mut viewer = Endpoint::new("viewer@host", 1200, 96, 90000, 1_000_000, start, wallclock());
+        camera.send_media(&[0u8; 100], 3000, start);
+        deliver(&mut camera, &mut viewer, start);
+        events(&mut viewer);
+        camera.close(start);
+        assert_eq!(None, camera.poll_timeout());
+        deliver(&mut camera, &mut viewer, start);
+        assert!(events(&mut viewer).contains(&EndpointEvent::Bye(camera.ssrc())));
+        assert_eq!(None, viewer.stats(camera.ssrc()).map(|stats| stats.received()));
+    }
+}
@@ -0,0 +1,467 @@
+use std::fmt;
+use std::str::FromStr;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::rtsp::{Method, RtspError};
+
+// Parses "a" or "a-b" as used by ports and interleaved channels.
+fn parse_pair<T: FromStr>(value: &str) -> Option<(T, Option<T>)> {
+    match value.split_once('-') {
+        Some((first, second)) => Some((first.trim().parse().ok()?, Some(second.trim().parse().ok()?))),
+        None => Some((value.trim().parse().ok()?, None)),
+    }
+}
+
+fn format_pair<T: fmt::Display>(first: T, second: Option<T>) -> String {
+    match second {
+        Some(second) => format!("{}-{}", first, second),
+        None => first.to_string(),
+    }
+}
+
+// TransportSpec is one transport specification of Transport header (RFC 2326 section 12.39):
+//
+//   RTP/AVP[/TCP|/UDP] *(";" parameter["=" value])
+//
+// Parameters are kept as received for exact serialization, typed accessors decode the common ones.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct TransportSpec {
+    pub protocol: String,
+    pub params: Vec<(String, Option<String>)>,
+}
+
+impl TransportSpec {
+    pub const PROFILE: &'static str = "RTP/AVP";
+
+    pub fn new(protocol: impl Into<String>) -> TransportSpec {
+        TransportSpec { protocol: protocol.into(), params: Vec::new() }
+    }
+
+    // Unicast UDP with RTP on the given port and RTCP on the next one.
+    pub fn udp(rtp_port: u16) -> TransportSpec {
+        let mut spec = TransportSpec::new(TransportSpec::PROFILE);
+        spec.set("unicast", None);
+        spec.set("client_port", Some(format_pair(rtp_port, Some(rtp_port.wrapping_add(1)))));
+        spec
+    }
+
+    // RTP and RTCP interleaved on the RTSP connection.
+    pub fn tcp(rtp_channel: u8) -> TransportSpec {
+        let mut spec = TransportSpec::new(format!("{}/TCP", TransportSpec::PROFILE));
+        spec.set("unicast", None);
+        spec.set("interleaved", Some(format_pair(rtp_channel, Some(rtp_channel.wrapping_add(1)))));
+        spec
+    }
+
+    pub fn multicast() -> TransportSpec {
+        let mut spec = TransportSpec::new(TransportSpec::PROFILE);
+        spec.set("multicast", None);
+        spec
+    }
+
+    // Parses all comma separated specifications of the header value, preferred first.
+    pub fn parse_list(value: &str) -> Result<Vec<TransportSpec>, RtspError> {
+        value.split(',').filter(|spec| !spec.trim().is_empty()).map(str::parse).collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.params.iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_deref().unwrap_or_default())
+    }
+
+    // Adds or replaces the parameter.
+    pub fn set(&mut self, name: &str, value: Option<String>) {
+        match self.params.iter_mut().find(|(key, _)| key.eq_ignore_ascii_case(name)) {
+            Some(param) => param.1 = value,
+            None => self.params.push((name.to_string(), value)),
+        }
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.params.retain(|(key, _)| !key.eq_ignore_ascii_case(name));
+    }
+
+    pub fn is_tcp(&self) -> bool {
+        self.protocol.to_ascii_uppercase().ends_with("/TCP")
+    }
+
+    pub fn is_multicast(&self) -> bool {
+        self.get("multicast").is_some()
+    }
+
+    pub fn destination(&self) -> Option<&str> {
+        self.get("destination").filter(|destination| !destination.is_empty())
+    }
+
+    pub fn source(&self) -> Option<&str> {
+        self.get("source")
+    }
+
+    pub fn interleaved(&self) -> Option<(u8, Option<u8>)> {
+        self.get("interleaved").and_then(parse_pair)
+    }
+
+    pub fn client_port(&self) -> Option<(u16, Option<u16>)> {
+        self.get("client_port").and_then(parse_pair)
+    }
+
+    pub fn server_port(&self) -> Option<(u16, Option<u16>)> {
+        self.get("server_port").and_then(parse_pair)
+    }
+
+    // Multicast ports.
+    pub fn port(&self) -> Option<(u16, Option<u16>)> {
+        self.get("port").and_then(parse_pair)
+    }
+
+    pub fn ttl(&self) -> Option<u8> {
+        self.get("ttl").and_then(|ttl| ttl.trim().parse().ok())
+    }
+
+    pub fn ssrc(&self) -> Option<u32> {
+        self.get("ssrc").and_then(|ssrc| u32::from_str_radix(ssrc.trim(), 16).ok())
+    }
+
+    // Mode without quotes, None when absent, which means PLAY.
+    pub fn mode(&self) -> Option<&str> {
+        self.get("mode").map(|mode| mode.trim_matches('"'))
+    }
+}
+
+impl FromStr for TransportSpec {
+    type Err = RtspError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut fields = text.trim().split(';');
+        let protocol = fields.next().unwrap_or_default().trim();
+        if !protocol.to_ascii_uppercase().starts_with("RTP/") {
+            return Err(RtspError::InvalidHeader(text.to_string()))
+        }
+        let params = fields
+            .map(str::trim)
+            .filter(|param| !param.is_empty())
+            .map(|param| match param.split_once('=') {
+                Some((name, value)) => (name.trim().to_string(), Some(value.trim().to_string())),
+                None => (param.to_string(), None),
+            })
+            .collect();
+        Ok(TransportSpec { protocol: protocol.to_string(), params })
+    }
+}
+
+impl fmt::Display for TransportSpec {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        f.write_str(&self.protocol)?;
+        for (name, value) in &self.params {
+            match value {
+                Some(value) => write!(f, ";{}={}", name, value)?,
+                None => write!(f, ";{}", name)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+// Session header: session identifier with optional timeout in seconds (RFC 2326 section 12.37).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SessionHeader {
+    pub id: String,
+    pub timeout: Option<u64>,
+}
+
+impl SessionHeader {
+    // Timeout assumed when the server does not announce one.
+    pub const DEFAULT_TIMEOUT: u64 = 60;
+}
+
+impl FromStr for SessionHeader {
+    type Err = RtspError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut fields = text.split(';').map(str::trim);
+        let id = fields.next().filter(|id| !id.is_empty()).ok_or_else(|| RtspError::InvalidHeader(text.to_string()))?;
+        let timeout = fields
+            .filter_map(|param| param.split_once('='))
+            .find(|(name, _)| name.trim().eq_ignore_ascii_case("timeout"))
+            .and_then(|(_, value)| value.trim().parse().ok());
+        Ok(SessionHeader { id: id.to_string(), timeout })
+    }
+}
+
+impl fmt::Display for SessionHeader {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        f.write_str(&self.id)?;
+        if let Some(timeout) = self.timeout {
+            write!(f, ";timeout={}", timeout)?;
+        }
+        Ok(())
+    }
+}
+
+// Range header (RFC 2326 section 12.29) in normal play time or absolute time:
+//
+//   npt=<start>-[<end>] | npt=-<end>   seconds or h:mm:ss with optional fraction, or "now" for live streams
+//   clock=<start>-[<end>]              UTC as YYYYMMDDThhmmss[.fraction]Z
+//
+// Recording replay (ONVIF) plays backwards from start when end lies before it.  Parameters after ";", such as
+// time=, are ignored.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Range {
+    // At least one of start and end is present.
+    Npt { start: Option<NptTime>, end: Option<NptTime> },
+    Clock { start: SystemTime, end: Option<SystemTime> },
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum NptTime {
+    Now,
+    Time(Duration),
+}
+
+impl FromStr for NptTime {
+    type Err = RtspError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || RtspError::InvalidHeader(text.to_string());
+        if text.trim() == "now" {
+            return Ok(NptTime::Now)
+        }
+        let mut seconds = 0.0;
+        for field in text.split(':') {
+            seconds = seconds * 60.0 + field.trim().parse::<f64>().ok().filter(|value| *value >= 0.0).ok_or_else(invalid)?;
+        }
+        Duration::try_from_secs_f64(seconds).map(NptTime::Time).map_err(|_| invalid())
+    }
+}
+
+impl fmt::Display for NptTime {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            NptTime::Now => f.write_str("now"),
+            NptTime::Time(time) => match time.subsec_millis() {
+                0 => write!(f, "{}", time.as_secs()),
+                millis => write!(f, "{}.{:03}", time.as_secs(), millis),
+            },
+        }
+    }
+}
+
+impl Range {
+    fn parse_clock(text: &str) -> Option<SystemTime> {
+        let text = text.trim().strip_suffix('Z')?;
+        let (date, time) = text.split_once('T')?;
+        let (time, fraction) = time.split_once('.').unwrap_or((time, ""));
+        if date.len() != 8 || time.len() != 6 || !date.bytes().chain(time.bytes()).chain(fraction.bytes()).all(|byte| byte.is_ascii_digit()) {
+            return None
+        }
+        let number = |text: &str| text.parse::<u64>().ok();
+        let (year, month, day) = (number(&date[..4])?, number(&date[4..6])?, number(&date[6..])?);
+        let (hour, minute, second) = (number(&time[..2])?, number(&time[2..4])?, number(&time[4..])?);
+        if !(1970..=9999).contains(&year) || !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day)
+            || hour > 23 || minute > 59 || second > 60 {
+            return None
+        }
+        let nanos = format!("{:0<9}", &fraction[..fraction.len().min(9)]).parse::<u32>().ok()?;
+        let seconds = days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second;
+        Some(UNIX_EPOCH + Duration::new(seconds, nanos))
+    }
+
+    fn format_clock(time: SystemTime) -> String {
+        let since_unix = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let seconds = since_unix.as_secs();
+        let (year, month, day) = civil_from_days(seconds / 86400);
+        let time = seconds % 86400;
+        let text = format!("{:04}{:02}{:02}T{:02}{:02}{:02}", year, month, day, time / 3600, time / 60 % 60, time % 60);
+        match since_unix.subsec_millis() {
+            0 => format!("{}Z", text),
+            millis => format!("{}.{:03}Z", text, millis),
+        }
+    }
+}
+
+fn days_in_month(year: u64, month: u64) -> u64 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Days since 1970-01-01 of a proleptic Gregorian date (algorithm of H. Hinnant's chrono-compatible date
+// library), years before 1970 are not needed.
+fn days_from_civil(year: u64, month: u64, day: u64) -> u64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: u64) -> (u64, u64, u64) {
+    let days = days + 719468;
+    let era = days / 146097;
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+    let year = year_of_era + era * 400;
+    (if month <= 2 { year + 1 } else { year }, month, day)
+}
+
+impl FromStr for Range {
+    type Err = RtspError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || RtspError::InvalidHeader(text.to_string());
+        let value = text.split(';').next().unwrap_or_default().trim();
+        let (unit, times) = value.split_once('=').ok_or_else(invalid)?;
+        let (start, end) = times.split_once('-').ok_or_else(invalid)?;
+        let (start, end) = (start.trim(), end.trim());
+        match unit.trim() {
+            "npt" => {
+                let time = |text: &str| match text {
+                    "" => Ok(None),
+                    text => text.parse().map(Some),
+                };
+                match (time(start)?, time(end)?) {
+                    (None, None) => Err(invalid()),
+                    (start, end) => Ok(Range::Npt { start, end }),
+                }
+            },
+            "clock" => {
+                let start = Range::parse_clock(start).ok_or_else(invalid)?;
+                let end = match end {
+                    "" => None,
+                    end => Some(Range::parse_clock(end).ok_or_else(invalid)?),
+                };
+                Ok(Range::Clock { start, end })
+            },
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self {
+            Range::Npt { start, end } => {
+                f.write_str("npt=")?;
+                if let Some(start) = start {
+                    write!(f, "{}", start)?;
+                }
+                f.write_str("-")?;
+                if let Some(end) = end {
+                    write!(f, "{}", end)?;
+                }
+            },
+            Range::Clock { start, end } => {
+                write!(f, "clock={}-", Range::format_clock(*start))?;
+                if let Some(end) = end {
+                    f.write_str(&Range::format_clock(*end))?;
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+// Methods listed in Public header of OPTIONS response.
+pub fn parse_public(value: &str) -> Vec<Method> {
+    value.split(',').filter_map(|method| method.trim().parse().ok()).collect()
+}
+
+// Resolves control URL of SDP against base URL (RFC 2326 section C.1.1).  Relative controls are appended to
+// the base the way most servers expect, "*" refers to the base itself.
+pub fn resolve_control(base: &str, control: Option<&str>) -> String {
+    match control.map(str::trim) {
+        None | Some("") | Some("*") => base.to_string(),
+        Some(control) if control.to_ascii_lowercase().starts_with("rtsp://")
+            || control.to_ascii_lowercase().starts_with("rtsps://") => control.to_string(),
+        Some(control) if base.ends_with('/') => format!("{}{}", base, control.trim_start_matches('/')),
+        Some(control) => format!("{}/{}", base, control.trim_start_matches('/')),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_transport_specs() {
+        let value = "RTP/AVP/TCP;unicast;interleaved=0-1, RTP/AVP;unicast;client_port=5000-5001;server_port=6970-6971;ssrc=0A1B2C3D;mode=\"PLAY\"";
+        let specs = TransportSpec::parse_list(value).unwrap();
+        assert_eq!(2, specs.len());
+        assert!(specs[0].is_tcp());
+        assert_eq!(Some((0, Some(1))), specs[0].interleaved());
+        assert!(!specs[1].is_tcp());
+        assert_eq!(Some((5000, Some(5001))), specs[1].client_port());
+        assert_eq!(Some((6970, Some(6971))), specs[1].server_port());
+        assert_eq!(Some(0x0a1b2c3d), specs[1].ssrc());
+        assert_eq!(Some("PLAY"), specs[1].mode());
+        assert_eq!("RTP/AVP;unicast;client_port=5000-5001;server_port=6970-6971;ssrc=0A1B2C3D;mode=\"PLAY\"", specs[1].to_string());
+
+        let spec = "RTP/AVP;multicast;destination=232.0.0.1;port=5000-5001;ttl=16".parse::<TransportSpec>().unwrap();
+        assert!(spec.is_multicast());
+        assert_eq!(Some("232.0.0.1"), spec.destination());
+        assert_eq!(Some((5000, Some(5001))), spec.port());
+        assert_eq!(Some(16), spec.ttl());
+        assert!("MP2T/H2221/UDP;unicast".parse::<TransportSpec>().is_err());
+
+        assert_eq!("RTP/AVP/TCP;unicast;interleaved=2-3", TransportSpec::tcp(2).to_string());
+        assert_eq!("RTP/AVP;unicast;client_port=5000-5001", TransportSpec::udp(5000).to_string());
+    }
+
+    #[test]
+    fn parse_session_and_public() {
+        assert_eq!(SessionHeader { id: "47112344".to_string(), timeout: Some(30) }, "47112344; timeout=30".parse().unwrap());
+        assert_eq!(SessionHeader { id: "abc".to_string(), timeout: None }, "abc".parse().unwrap());
+        assert!("".parse::<SessionHeader>().is_err());
+        assert_eq!(vec![Method::Options, Method::Describe, Method::GetParameter], parse_public("OPTIONS, DESCRIBE,GET_PARAMETER"));
+    }
+
+    #[test]
+    fn parse_ranges() {
+        let range = "clock=20090615T114900.440Z-20090615T115000Z".parse::<Range>().unwrap();
+        let start = UNIX_EPOCH + Duration::from_millis(1_245_066_540_440);
+        assert_eq!(Range::Clock { start, end: Some(UNIX_EPOCH + Duration::from_secs(1_245_066_600)) }, range);
+        assert_eq!("clock=20090615T114900.440Z-20090615T115000Z", range.to_string());
+        let range = "clock=20240229T235959Z-".parse::<Range>().unwrap();
+        assert_eq!(Range::Clock { start: UNIX_EPOCH + Duration::from_secs(1_709_251_199), end: None }, range);
+        assert_eq!("clock=20240229T235959Z-", range.to_string());
+
+        assert_eq!(Range::Npt { start: Some(NptTime::Now), end: None }, "npt=now-".parse().unwrap());
+        let range = "npt=0:01:02.5-125;time=19970123T143720Z".parse::<Range>().unwrap();
+        let (start, end) = (NptTime::Time(Duration::from_millis(62_500)), NptTime::Time(Duration::from_secs(125)));
+        assert_eq!(Range::Npt { start: Some(start), end: Some(end) }, range);
+        assert_eq!("npt=62.500-125", range.to_string());
+        let range = "npt=-145".parse::<Range>().unwrap();
+        assert_eq!(Range::Npt { start: None, end: Some(NptTime::Time(Duration::from_secs(145))) }, range);
+        assert_eq!("npt=-145", range.to_string());
+
+        assert!("clock=20090615T114900-".parse::<Range>().is_err());
+        assert!("clock=20091315T114900Z-".parse::<Range>().is_err());
+        assert!("clock=20260231T000000Z-".parse::<Range>().is_err());
+        assert!("clock=20230229T000000Z-".parse::<Range>().is_err());
+        assert!("clock=21000229T000000Z-".parse::<Range>().is_err());
+        assert!("clock=20000229T000000Z-".parse::<Range>().is_ok());
+        assert!("clock=20260430T000000Z-".parse::<Range>().is_ok());
+        assert!("npt=-".parse::<Range>().is_err());
+        assert!("npt=1:x-".parse::<Range>().is_err());
+        assert!("smpte=0:10:20-".parse::<Range>().is_err());
+    }
+
+    #[test]
+    fn resolve_control_urls() {
+        let base = "rtsp://192.168.1.64/Streaming/Channels/101/";
+        assert_eq!("rtsp://192.168.1.64/Streaming/Channels/101/trackID=1", resolve_control(base, Some("trackID=1")));
+        assert_eq!(base, resolve_control(base, Some("*")));
+        assert_eq!(base, resolve_control(base, None));
+        assert_eq!("rtsp://host/live/track1", resolve_control("rtsp://host/live", Some("track1")));
+        assert_eq!("rtsp://other/track", resolve_control(base, Some("rtsp://other/track")));
+    }
+}
@@ -0,0 +1,164 @@
+use crate::mux::PacketKind;
+use crate::rtcp::{RtcpError, RtcpMode, RtcpPacket};
+use crate::rtp::{RtpError, RtpPacket};
+use crate::rtsp::RtspError;
+
+// InterleavedFrame is binary data sent on the RTSP connection (RFC 2326 section 10.12):
+//
+//   0                   1                   2                   3
+//   0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//  |  '$' (0x24)   |    channel    |             length            |
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//  :                     data (one RTP or RTCP packet)             :
+//  +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct InterleavedFrame {
+    channel: u8,
+    data: Vec<u8>,
+}
+
+impl InterleavedFrame {
+    pub const MAGIC: u8 = b'$';
+    pub const HEADER_SIZE: usize = 4;
+
+    pub fn new(channel: u8, data: Vec<u8>) -> Result<InterleavedFrame, RtspError> {
+        if data.len() > u16::MAX as usize {
+            return Err(RtspError::FrameTooLarge(data.len()))
+        }
+        Ok(InterleavedFrame { channel, data })
+    }
+
+    pub fn channel(&self) -> u8 {
+        self.channel
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn rtp(&self) -> Result<RtpPacket<'_>, RtpError> {
+        RtpPacket::from_slice(&self.data)
+    }
+
+    pub fn rtcp(&self, mode: RtcpMode) -> Result<Vec<RtcpPacket<'_>>, RtcpError> {
+        RtcpPacket::compound_from_slice(&self.data, mode)
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(InterleavedFrame::HEADER_SIZE + self.data.len());
+        buf.push(InterleavedFrame::MAGIC);
+        buf.push(self.channel);
+        buf.extend_from_slice(&(self.data.len() as u16).to_be_bytes());
+        buf.extend_from_slice(&self.data);
+        buf
+    }
+}
+
+// ChannelMap assigns interleaved channels to streams as negotiated in Transport header, e.g.
+// "RTP/AVP/TCP;interleaved=0-1" maps RTP of the stream to channel 0 and RTCP to channel 1.
+#[derive(Clone, Debug, Default)]
+pub struct ChannelMap {
+    // Stream identifier, RTP channel and RTCP channel.
+    entries: Vec<(usize, u8, Option<u8>)>,
+}
+
+impl ChannelMap {
+    pub fn new() -> ChannelMap {
+        ChannelMap::default()
+    }
+
+    // Adds or replaces channels of a stream, RTCP channel may be absent when only RTP is interleaved.
+    pub fn insert(&mut self, stream: usize, rtp: u8, rtcp: Option<u8>) {
+        self.entries.retain(|(id, _, _)| *id != stream);
+        self.entries.push((stream, rtp, rtcp));
+    }
+
+    pub fn remove(&mut self, stream: usize) {
+        self.entries.retain(|(id, _, _)| *id != stream);
+    }
+
+    pub fn channel(&self, stream: usize, kind: PacketKind) -> Option<u8> {
+        let (_, rtp, rtcp) = self.entries.iter().find(|(id, _, _)| *id == stream)?;
+        match kind {
+            PacketKind::Rtp => Some(*rtp),
+            PacketKind::Rtcp => *rtcp,
+        }
+    }
+
+    // Stream and packet kind the frame belongs to, None for channels not set up, e.g. garbage that happened
+    // to start with '$'.
+    pub fn route(&self, frame: &InterleavedFrame) -> Option<(usize, PacketKind)> {
+        self.entries.iter().find_map(|(stream, rtp, rtcp)| {
+            if *rtp == frame.channel {
+                Some((*stream, PacketKind::Rtp))
+            } else if *rtcp == Some(frame.channel) {
+                Some((*stream, PacketKind::Rtcp))
+            } else {
+                None
+            }
+        })
+    }
+
+    // Builds the frame carrying serialized packet of the stream.
+    pub fn frame(&self, stream: usize, kind: PacketKind, data: Vec<u8>) -> Result<Option<InterleavedFrame>, RtspError> {
+        self.channel(stream, kind).map(|channel| InterleavedFrame::new(channel, data)).transpose()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtcp::DataRR;
+    use crate::rtsp::{Message, RtspItem, RtspParser};
+
+    #[test]
+    fn route_frames_to_streams() {
+        let mut map = ChannelMap::new();
+        map.insert(0, 0, Some(1));
+        map.insert(1, 2, Some(3));
+        let packet = RtpPacket::new(true, 96, 1, 3000, 0x1234, &[1, 2, 3]);
+        let frame = map.frame(1, PacketKind::Rtp, packet.to_vec()).unwrap().unwrap();
+        let report = map.frame(0, PacketKind::Rtcp, DataRR::new(0x1234, Vec::new()).to_vec()).unwrap().unwrap();
+        assert_eq!(&[b'$', 2, 0, 15], &frame.to_vec()[..4]);
+
+        let mut parser = RtspParser::new();
+        parser.push(&frame.to_vec());
+        parser.push(&report.to_vec());
+        let Some(RtspItem::Interleaved(frame)) = parser.poll_item().unwrap() else { panic!() };
+        assert_eq!(Some((1, PacketKind::Rtp)), map.route(&frame));
+        assert_eq!(&[1, 2, 3], frame.rtp().unwrap().payload());
+        let Some(RtspItem::Interleaved(frame)) = parser.poll_item().unwrap() else { panic!() };
+        assert_eq!(Some((0, PacketKind::Rtcp)), map.route(&frame));
+        assert_eq!(RtcpPacket::RR, frame.rtcp(RtcpMode::Compound).unwrap()[0].payload_type());
+
+        map.remove(1);
+        assert_eq!(None, map.route(&InterleavedFrame::new(2, Vec::new()).unwrap()));
+        assert!(map.frame(1, PacketKind::Rtp, Vec::new()).unwrap().is_none());
+        assert!(matches!(InterleavedFrame::new(0, vec![0; 65536]).unwrap_err(), RtspError::FrameTooLarge(65536)));
+    }
+
+    #[test]
+    fn skip_garbage_between_frames() {
+        let frame = InterleavedFrame::new(0, vec![0x80, 0x60, 0, 1, 0, 0, 0, 0, 0, 0, 0, 1]).unwrap();
+        let mut data = vec![0x00, 0xff, 0x13, b'x'];
+        data.extend_from_slice(&frame.to_vec());
+        data.extend_from_slice(b"\x00\x00garbage\r\n");
+        data.extend_from_slice(b"RTSP/1.0 200 OK\r\nCSeq: 4\r\n\r\n");
+        data.extend_from_slice(&frame.to_vec());
+
+        let mut parser = RtspParser::new();
+        let mut items = Vec::new();
+        for chunk in data.chunks(5) {
+            parser.push(chunk);
+            while let Some(item) = parser.poll_item().unwrap() {
+                items.push(item);
+            }
+        }
+        assert_eq!(3, items.len());
+        assert_eq!(RtspItem::Interleaved(frame.clone()), items[0]);
+        assert!(matches!(&items[1], RtspItem::Message(Message::Response(response)) if response.cseq() == Some(4)));
+        assert_eq!(RtspItem::Interleaved(frame), items[2]);
+        assert_eq!(4 + 9, parser.discarded());
+    }
+}
@@ -2,4 +2,78 @@ pub(crate) mod rtp;
 pub use crate::rtp::*;
 
 pub(crate) mod rtcp;
-pub use crate::rtcp::*;
\ No newline at end of file
+pub use crate::rtcp::*;
+
+pub(crate) mod twcc;
+pub use crate::twcc::*;
+
+pub(crate) mod afb;
+pub use crate::afb::*;
+
+pub(crate) mod xr;
+pub use crate::xr::*;
+
+pub(crate) mod mux;
+pub use crate::mux::*;
+
+pub(crate) mod ntp;
+pub use crate::ntp::*;
+
+pub(crate) mod scheduler;
+pub use crate::scheduler::*;
+
+pub(crate) mod session;
+pub use crate::session::*;
+
+pub(crate) mod stats;
+pub use crate::stats::*;
+
+pub(crate) mod endpoint;
+pub use crate::endpoint::*;
+
+#[cfg(feature = "tokio")]
+pub(crate) mod transport;
+#[cfg(feature = "tokio")]
+pub use crate::transport::*;
+
+pub(crate) mod srtp;
+pub use crate::srtp::*;
+
+pub(crate) mod dtls;
+pub use crate::dtls::*;
+
+pub(crate) mod crypto;
+pub use crate::crypto::*;
+
+pub(crate) mod sdp;
+pub use crate::sdp::*;
+
+pub(crate) mod codec;
+pub use crate::codec::*;
+
+pub(crate) mod negotiation;
+pub use crate::negotiation::*;
+
+pub(crate) mod rtsp;
+pub use crate::rtsp::*;
+
+pub(crate) mod interleaved;
+pub use crate::interleaved::*;
+
+pub(crate) mod headers;
+pub use crate::headers::*;
+
+pub(crate) mod client;
+pub use crate::client::*;
+
+pub(crate) mod auth;
+pub use crate::auth::*;
+
+pub(crate) mod server;
+pub use crate::server::*;
+
+pub(crate) mod tunnel;
+pub use crate::tunnel::*;
+
+pub(crate) mod onvif;
+pub use crate::onvif::*;
\ No newline at end of file
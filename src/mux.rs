@@ -0,0 +1,101 @@
+use crate::rtcp::{RtcpError, RtcpMode, RtcpPacket};
+use crate::rtp::{RtpError, RtpPacket};
+
+#[derive(Debug)]
+pub enum MuxError {
+    InvalidLen(usize),
+    // First byte does not belong to RTP/RTCP range (RFC 7983), e.g. STUN or DTLS on the same port.
+    NotRtp(u8),
+    Rtp(RtpError),
+    Rtcp(RtcpError),
+}
+
+impl From<RtpError> for MuxError {
+    fn from(error: RtpError) -> Self {
+        MuxError::Rtp(error)
+    }
+}
+
+impl From<RtcpError> for MuxError {
+    fn from(error: RtcpError) -> Self {
+        MuxError::Rtcp(error)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PacketKind {
+    Rtp,
+    Rtcp,
+}
+
+impl PacketKind {
+    // Classifies a datagram received on a port shared by RTP and RTCP (RFC 5761 section 4).  RTCP packet
+    // types 192-223 map onto RTP payload types 64-95 with the marker bit set, so those payload types must
+    // not be used for RTP when multiplexing.
+    pub fn classify(slice: &[u8]) -> Result<PacketKind, MuxError> {
+        if slice.len() < 2 {
+            return Err(MuxError::InvalidLen(slice.len()))
+        }
+        if !(128..=191).contains(&slice[0]) {
+            return Err(MuxError::NotRtp(slice[0]))
+        }
+        if (64..=95).contains(&(slice[1] & 0x7F)) {
+            Ok(PacketKind::Rtcp)
+        } else {
+            Ok(PacketKind::Rtp)
+        }
+    }
+}
+
+// MuxPacket is a parsed datagram received on a port shared by RTP and RTCP.
+#[derive(Debug)]
+pub enum MuxPacket<'a> {
+    Rtp(RtpPacket<'a>),
+    Rtcp(Vec<RtcpPacket<'a>>),
+}
+
+impl<'a> MuxPacket<'a> {
+    pub fn from_slice(slice: &'a [u8], mode: RtcpMode) -> Result<MuxPacket<'a>, MuxError> {
+        match PacketKind::classify(slice)? {
+            PacketKind::Rtp => Ok(MuxPacket::Rtp(RtpPacket::from_slice(slice)?)),
+            PacketKind::Rtcp => Ok(MuxPacket::Rtcp(RtcpPacket::compound_from_slice(slice, mode)?)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classify_packets() {
+        assert_eq!(PacketKind::Rtp, PacketKind::classify(&[0x80, 0x60]).unwrap());
+        assert_eq!(PacketKind::Rtp, PacketKind::classify(&[0x80, 0xe0]).unwrap());
+        assert_eq!(PacketKind::Rtcp, PacketKind::classify(&[0x80, 0xc8]).unwrap());
+        assert_eq!(PacketKind::Rtcp, PacketKind::classify(&[0x81, 0xcf]).unwrap());
+        assert!(matches!(PacketKind::classify(&[0x16, 0xfe]).unwrap_err(), MuxError::NotRtp(0x16)));
+        assert!(matches!(PacketKind::classify(&[0x80]).unwrap_err(), MuxError::InvalidLen(1)));
+    }
+
+    #[test]
+    fn dispatch_rtp_packet() {
+        let data: [u8; 13] = [
+            0x80, 0xe0, 0x69, 0x8f, 0xd9, 0xc2, 0x93, 0xda, 0x1c, 0x64,
+            0x27, 0x82, 0x01,
+        ];
+        let packet = MuxPacket::from_slice(&data, RtcpMode::Compound).unwrap();
+        assert!(matches!(packet, MuxPacket::Rtp(packet) if packet.seq_number() == 27023));
+    }
+
+    #[test]
+    fn dispatch_reduced_size_rtcp_packet() {
+        let data: [u8; 12] = [
+            0x81, 0xcd, 0x00, 0x02, 0x12, 0x34, 0x56, 0x78, 0x00, 0x00,
+            0x00, 0x01,
+        ];
+        let error = MuxPacket::from_slice(&data, RtcpMode::Compound).unwrap_err();
+        assert!(matches!(error, MuxError::Rtcp(RtcpError::InvalidCompound(205))));
+        let packet = MuxPacket::from_slice(&data, RtcpMode::ReducedSize).unwrap();
+        assert!(matches!(packet, MuxPacket::Rtcp(packets) if packets.len() == 1));
+    }
+}
@@ -0,0 +1,494 @@
+use std::net::IpAddr;
+use rand::Rng;
+
+use crate::codec::{Codec, ProfileLevelId};
+use crate::rtp::RtpPacketizer;
+use crate::sdp::{
+    Attribute, Attributes, Connection, Direction, ExtMap, Fmtp, MediaDescription, Origin, RtcpFb, RtpMap,
+    SessionDescription, Timing,
+};
+
+// MediaCapability describes what this side accepts for one kind of media.  Codecs are listed as they would
+// appear in own SDP, their payload types are only local preferences as the answer keeps offered ones.
+#[derive(Clone, Debug)]
+pub struct MediaCapability {
+    media: String,
+    port: u16,
+    direction: Direction,
+    codecs: Vec<(RtpMap, Option<Fmtp>)>,
+    // Feedback without payload type applies to all codecs.
+    rtcp_fbs: Vec<RtcpFb>,
+    extensions: Vec<String>,
+}
+
+impl MediaCapability {
+    pub fn new(media: impl Into<String>, port: u16, direction: Direction) -> MediaCapability {
+        MediaCapability {
+            media: media.into(),
+            port,
+            direction,
+            codecs: Vec::new(),
+            rtcp_fbs: Vec::new(),
+            extensions: Vec::new(),
+        }
+    }
+
+    pub fn add_codec(&mut self, rtpmap: RtpMap, fmtp: Option<Fmtp>) {
+        self.codecs.push((rtpmap, fmtp));
+    }
+
+    pub fn add_rtcp_fb(&mut self, rtcp_fb: RtcpFb) {
+        self.rtcp_fbs.push(rtcp_fb);
+    }
+
+    // Header extension URI (RFC 8285) accepted by this side.
+    pub fn add_extension(&mut self, uri: impl Into<String>) {
+        self.extensions.push(uri.into());
+    }
+
+    pub fn media(&self) -> &str {
+        &self.media
+    }
+
+    fn supports_rtcp_fb(&self, local: &RtpMap, offered: &RtcpFb) -> bool {
+        self.rtcp_fbs.iter().any(|rtcp_fb| {
+            rtcp_fb.payload_type.is_none_or(|payload_type| payload_type == local.payload_type)
+                && rtcp_fb.kind == offered.kind
+                && rtcp_fb.param == offered.param
+        })
+    }
+}
+
+// NegotiatedMedia is the outcome of one m= line, codecs carry payload types both sides use on the wire.
+#[derive(Clone, Debug)]
+pub struct NegotiatedMedia {
+    mid: Option<String>,
+    media: String,
+    direction: Direction,
+    codecs: Vec<Codec>,
+    rtcp_fbs: Vec<RtcpFb>,
+    extmaps: Vec<ExtMap>,
+    rejected: bool,
+}
+
+impl NegotiatedMedia {
+    pub fn mid(&self) -> Option<&str> {
+        self.mid.as_deref()
+    }
+
+    pub fn media(&self) -> &str {
+        &self.media
+    }
+
+    // Direction from the point of view of this side.
+    pub fn direction(&self) -> Direction {
+        self.direction
+    }
+
+    // Accepted codecs, the first one is preferred for sending.
+    pub fn codecs(&self) -> &[Codec] {
+        &self.codecs
+    }
+
+    pub fn rtcp_fbs(&self) -> &[RtcpFb] {
+        &self.rtcp_fbs
+    }
+
+    pub fn extmaps(&self) -> &[ExtMap] {
+        &self.extmaps
+    }
+
+    pub fn is_rejected(&self) -> bool {
+        self.rejected
+    }
+
+    // Packetizer for the preferred codec, None if the media is rejected or this side must not send.
+    pub fn packetizer(&self, mtu: usize, ssrc: u32) -> Option<RtpPacketizer> {
+        if self.rejected || !self.direction.sends() {
+            return None
+        }
+        self.codecs.first().map(|codec| RtpPacketizer::new(mtu, codec.payload_type(), ssrc))
+    }
+}
+
+// NegotiatedSession holds the answer to send back and the negotiated media in order of m= lines.
+#[derive(Clone, Debug)]
+pub struct NegotiatedSession {
+    answer: SessionDescription,
+    media: Vec<NegotiatedMedia>,
+    bundle: Vec<String>,
+}
+
+impl NegotiatedSession {
+    pub fn answer(&self) -> &SessionDescription {
+        &self.answer
+    }
+
+    pub fn media(&self) -> &[NegotiatedMedia] {
+        &self.media
+    }
+
+    // Identifiers of media sharing one transport, empty if BUNDLE was not negotiated.
+    pub fn bundle(&self) -> &[String] {
+        &self.bundle
+    }
+}
+
+// Answerer generates answers to SDP offers (RFC 3264 section 6).  Every offered m= line gets an m= line in
+// the answer, media without matching capability or common codec is rejected with port zero.
+pub struct Answerer {
+    address: IpAddr,
+    session_id: u64,
+    version: u64,
+    capabilities: Vec<MediaCapability>,
+    bundle: bool,
+}
+
+impl Answerer {
+    pub fn new(address: IpAddr) -> Answerer {
+        Answerer {
+            address,
+            session_id: rand::thread_rng().gen::<u32>() as u64,
+            version: 0,
+            capabilities: Vec::new(),
+            bundle: true,
+        }
+    }
+
+    pub fn add_media(&mut self, capability: MediaCapability) {
+        self.capabilities.push(capability);
+    }
+
+    // Enables BUNDLE (RFC 8843) when offered, on by default.
+    pub fn set_bundle(&mut self, bundle: bool) {
+        self.bundle = bundle;
+    }
+
+    pub fn answer(&mut self, offer: &SessionDescription) -> NegotiatedSession {
+        self.version += 1;
+        let addr_type = if self.address.is_ipv4() { "IP4" } else { "IP6" };
+        let mut answer = SessionDescription {
+            origin: Some(Origin {
+                username: "-".to_string(),
+                session_id: self.session_id.to_string(),
+                session_version: self.version.to_string(),
+                net_type: "IN".to_string(),
+                addr_type: addr_type.to_string(),
+                address: self.address.to_string(),
+            }),
+            name: Some("-".to_string()),
+            connection: Some(Connection {
+                net_type: "IN".to_string(),
+                addr_type: addr_type.to_string(),
+                address: self.address.to_string(),
+            }),
+            // Time description of the answer must equal the offer.
+            timings: if offer.timings.is_empty() { vec![Timing::default()] } else { offer.timings.clone() },
+            ..Default::default()
+        };
+        let mut media = Vec::with_capacity(offer.media.len());
+        for offered in &offer.media {
+            let (description, negotiated) = self.answer_media(offer, offered);
+            answer.media.push(description);
+            media.push(negotiated);
+        }
+        let bundle = self.bundle_media(offer, &mut answer, &media);
+        NegotiatedSession { answer, media, bundle }
+    }
+
+    fn answer_media(&self, offer: &SessionDescription, offered: &MediaDescription) -> (MediaDescription, NegotiatedMedia) {
+        let mid = offered.attribute("mid").map(String::from);
+        let mut description = MediaDescription::new(offered.media.clone(), 0, offered.proto.clone(), offered.formats.clone());
+        if let Some(mid) = &mid {
+            description.attributes.push(Attribute::value("mid", mid));
+        }
+        let mut negotiated = NegotiatedMedia {
+            mid,
+            media: offered.media.clone(),
+            direction: Direction::Inactive,
+            codecs: Vec::new(),
+            rtcp_fbs: Vec::new(),
+            extmaps: Vec::new(),
+            rejected: true,
+        };
+        let capability = self.capabilities.iter().find(|capability| capability.media == offered.media);
+        let Some(capability) = capability.filter(|_| !offered.is_rejected()) else {
+            return (description, negotiated)
+        };
+
+        let mut formats = Vec::new();
+        let mut codec_attributes = Vec::new();
+        for payload_type in offered.payload_types() {
+            let Ok(offered_codec) = Codec::from_media(offered, payload_type) else {
+                continue
+            };
+            let offered_fmtp = offered.fmtp(payload_type);
+            let Some((local, fmtp)) = capability.codecs.iter()
+                .find_map(|(local, fmtp)| match_codec(&offered_codec, offered_fmtp.as_ref(), local, fmtp.as_ref())
+                    .map(|fmtp| (local, fmtp))) else {
+                continue
+            };
+            formats.push(payload_type.to_string());
+            let rtpmap = RtpMap {
+                payload_type,
+                encoding: offered_codec.encoding().to_string(),
+                clock_rate: offered_codec.clock_rate(),
+                channels: offered_codec.channels(),
+            };
+            codec_attributes.push(Attribute::value("rtpmap", rtpmap));
+            if let Some(params) = fmtp {
+                codec_attributes.push(Attribute::value("fmtp", Fmtp { payload_type, params }));
+            }
+            for rtcp_fb in offered.rtcp_fbs() {
+                if rtcp_fb.payload_type.is_none_or(|offered| offered == payload_type) && capability.supports_rtcp_fb(local, &rtcp_fb) {
+                    let rtcp_fb = RtcpFb { payload_type: Some(payload_type), ..rtcp_fb };
+                    codec_attributes.push(Attribute::value("rtcp-fb", &rtcp_fb));
+                    negotiated.rtcp_fbs.push(rtcp_fb);
+                }
+            }
+        }
+        if formats.is_empty() {
+            return (description, negotiated)
+        }
+
+        let remote = offer.media_direction(offered).reverse();
+        negotiated.direction = match (capability.direction.sends() && remote.sends(), capability.direction.receives() && remote.receives()) {
+            (true, true) => Direction::SendRecv,
+            (true, false) => Direction::SendOnly,
+            (false, true) => Direction::RecvOnly,
+            (false, false) => Direction::Inactive,
+        };
+        description.port = capability.port;
+        description.formats = formats;
+        description.attributes.push(Attribute::flag(negotiated.direction.as_str()));
+        if offered.has_attribute("rtcp-mux") {
+            description.attributes.push(Attribute::flag("rtcp-mux"));
+        }
+        for extmap in offered.extmaps() {
+            if capability.extensions.contains(&extmap.uri) {
+                let extmap = ExtMap { direction: extmap.direction.map(|direction| direction.reverse()), ..extmap };
+                description.attributes.push(Attribute::value("extmap", &extmap));
+                negotiated.extmaps.push(extmap);
+            }
+        }
+        description.attributes.extend(codec_attributes);
+        negotiated.codecs = description.payload_types().into_iter()
+            .filter_map(|payload_type| Codec::from_media(&description, payload_type).ok())
+            .collect();
+        negotiated.rejected = false;
+        (description, negotiated)
+    }
+
+    // Keeps accepted media of the offered BUNDLE group on the transport of the first of them.
+    fn bundle_media(&self, offer: &SessionDescription, answer: &mut SessionDescription, media: &[NegotiatedMedia]) -> Vec<String> {
+        let offered = offer.attributes("group").find_map(|group| group.strip_prefix("BUNDLE "));
+        let Some(offered) = offered.filter(|_| self.bundle) else {
+            return Vec::new()
+        };
+        let mut bundle = Vec::new();
+        let mut port = None;
+        for mid in offered.split_ascii_whitespace() {
+            let Some(index) = media.iter().position(|media| media.mid() == Some(mid) && !media.is_rejected()) else {
+                continue
+            };
+            let port = *port.get_or_insert(answer.media[index].port);
+            answer.media[index].port = port;
+            bundle.push(mid.to_string());
+        }
+        if !bundle.is_empty() {
+            answer.attributes.push(Attribute::value("group", format!("BUNDLE {}", bundle.join(" "))));
+        }
+        bundle
+    }
+}
+
+// Returns format parameters of the answer if local codec can receive and send the offered one.
+fn match_codec(offered: &Codec, offered_fmtp: Option<&Fmtp>, local: &RtpMap, local_fmtp: Option<&Fmtp>) -> Option<Option<String>> {
+    if !offered.encoding().eq_ignore_ascii_case(&local.encoding)
+        || offered.clock_rate() != local.clock_rate
+        || offered.channels().unwrap_or(1) != local.channels.unwrap_or(1) {
+        return None
+    }
+    if offered.encoding().eq_ignore_ascii_case("H264") {
+        return match_h264(offered_fmtp, local_fmtp)
+    }
+    Some(local_fmtp.map(|fmtp| fmtp.params.clone()))
+}
+
+// H.264 payload formats are compatible if packetization modes and profiles match (RFC 6184 section 8.2.2).
+// The answer keeps offered profile with the lower of both levels, or own level if both allow asymmetry.
+fn match_h264(offered: Option<&Fmtp>, local: Option<&Fmtp>) -> Option<Option<String>> {
+    let get = |fmtp: Option<&Fmtp>, key: &str| fmtp.and_then(|fmtp| fmtp.get(key)).map(String::from);
+    if get(offered, "packetization-mode").unwrap_or("0".to_string()) != get(local, "packetization-mode").unwrap_or("0".to_string()) {
+        return None
+    }
+    // Constrained baseline 42e01f is assumed when profile-level-id is missing.
+    let profile = |fmtp: Option<&Fmtp>| get(fmtp, "profile-level-id").unwrap_or("42e01f".to_string()).parse::<ProfileLevelId>().ok();
+    let (offered_profile, local_profile) = (profile(offered)?, profile(local)?);
+    if !same_h264_profile(&offered_profile, &local_profile) {
+        return None
+    }
+    let asymmetry = get(offered, "level-asymmetry-allowed").as_deref() == Some("1")
+        && get(local, "level-asymmetry-allowed").as_deref() == Some("1");
+    let level_idc = if asymmetry { local_profile.level_idc } else { offered_profile.level_idc.min(local_profile.level_idc) };
+    let negotiated = ProfileLevelId { level_idc, ..offered_profile };
+
+    let mut params = vec![format!("profile-level-id={}", negotiated)];
+    if let Some(fmtp) = local {
+        params.extend(fmtp.pairs()
+            .filter(|(key, _)| !key.eq_ignore_ascii_case("profile-level-id"))
+            .map(|(key, value)| if value.is_empty() { key.to_string() } else { format!("{}={}", key, value) }));
+    }
+    Some(Some(params.join(";")))
+}
+
+fn same_h264_profile(left: &ProfileLevelId, right: &ProfileLevelId) -> bool {
+    const BASELINE: u8 = 66;
+    const CONSTRAINT_SET1: u8 = 0x40;
+    if left.profile_idc != right.profile_idc {
+        return false
+    }
+    // Baseline with constraint_set1_flag is constrained baseline, a distinct profile.
+    left.profile_idc != BASELINE || (left.profile_iop & CONSTRAINT_SET1) == (right.profile_iop & CONSTRAINT_SET1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+    use crate::codec::CodecParameters;
+
+    const OFFER: &str = "v=0\r\n\
+        o=- 4611731400430051336 2 IN IP4 127.0.0.1\r\n\
+        s=-\r\n\
+        t=0 0\r\n\
+        a=group:BUNDLE 0 1 2\r\n\
+        m=audio 9 UDP/TLS/RTP/SAVPF 111 0\r\n\
+        c=IN IP4 0.0.0.0\r\n\
+        a=mid:0\r\n\
+        a=sendrecv\r\n\
+        a=rtcp-mux\r\n\
+        a=extmap:1 urn:ietf:params:rtp-hdrext:ssrc-audio-level\r\n\
+        a=extmap:3 http://www.ietf.org/id/draft-holmer-rmcat-transport-wide-cc-extensions-01\r\n\
+        a=rtpmap:111 opus/48000/2\r\n\
+        a=rtcp-fb:111 transport-cc\r\n\
+        a=fmtp:111 minptime=10;useinbandfec=1\r\n\
+        a=rtpmap:0 PCMU/8000\r\n\
+        m=video 9 UDP/TLS/RTP/SAVPF 96 97 98\r\n\
+        c=IN IP4 0.0.0.0\r\n\
+        a=mid:1\r\n\
+        a=sendonly\r\n\
+        a=rtcp-mux\r\n\
+        a=extmap:3 http://www.ietf.org/id/draft-holmer-rmcat-transport-wide-cc-extensions-01\r\n\
+        a=rtpmap:96 VP8/90000\r\n\
+        a=rtcp-fb:* nack\r\n\
+        a=rtcp-fb:* nack pli\r\n\
+        a=rtcp-fb:* goog-remb\r\n\
+        a=rtpmap:97 H264/90000\r\n\
+        a=fmtp:97 level-asymmetry-allowed=1;packetization-mode=0;profile-level-id=42e02a\r\n\
+        a=rtpmap:98 H264/90000\r\n\
+        a=fmtp:98 level-asymmetry-allowed=1;packetization-mode=1;profile-level-id=42e02a\r\n\
+        m=application 9 UDP/DTLS/SCTP webrtc-datachannel\r\n\
+        c=IN IP4 0.0.0.0\r\n\
+        a=mid:2\r\n";
+
+    fn answerer() -> Answerer {
+        let mut answerer = Answerer::new(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)));
+        let mut audio = MediaCapability::new("audio", 50000, Direction::SendRecv);
+        audio.add_codec(RtpMap { payload_type: 0, encoding: "PCMU".to_string(), clock_rate: 8000, channels: None }, None);
+        audio.add_extension("http://www.ietf.org/id/draft-holmer-rmcat-transport-wide-cc-extensions-01");
+        answerer.add_media(audio);
+        let mut video = MediaCapability::new("video", 50002, Direction::SendRecv);
+        let fmtp = Fmtp { payload_type: 102, params: "packetization-mode=1;profile-level-id=42e01f".to_string() };
+        video.add_codec(RtpMap { payload_type: 102, encoding: "H264".to_string(), clock_rate: 90000, channels: None }, Some(fmtp));
+        video.add_rtcp_fb(RtcpFb { payload_type: None, kind: "nack".to_string(), param: Some("pli".to_string()) });
+        answerer.add_media(video);
+        answerer
+    }
+
+    #[test]
+    fn answer_webrtc_offer() {
+        let offer = OFFER.parse::<SessionDescription>().unwrap();
+        let session = answerer().answer(&offer);
+        let media = session.media();
+        assert_eq!(3, media.len());
+
+        assert_eq!(Some("0"), media[0].mid());
+        assert_eq!(Direction::SendRecv, media[0].direction());
+        assert_eq!(vec![0], media[0].codecs().iter().map(Codec::payload_type).collect::<Vec<_>>());
+        assert_eq!(vec![3], media[0].extmaps().iter().map(|extmap| extmap.id).collect::<Vec<_>>());
+        assert!(media[0].rtcp_fbs().is_empty());
+        assert_eq!(0, media[0].packetizer(1200, 0x1234).unwrap().payload_type());
+
+        assert_eq!(Direction::RecvOnly, media[1].direction());
+        assert_eq!(98, media[1].codecs()[0].payload_type());
+        let CodecParameters::H264(h264) = media[1].codecs()[0].parameters() else { panic!() };
+        assert_eq!(0x1f, h264.profile_level_id.unwrap().level_idc);
+        assert_eq!(vec![RtcpFb { payload_type: Some(98), kind: "nack".to_string(), param: Some("pli".to_string()) }], media[1].rtcp_fbs());
+        assert!(media[1].packetizer(1200, 0x1234).is_none());
+
+        assert!(media[2].is_rejected());
+        assert_eq!(&["0".to_string(), "1".to_string()], session.bundle());
+    }
+
+    #[test]
+    fn serialize_answer() {
+        let offer = OFFER.parse::<SessionDescription>().unwrap();
+        let mut answerer = answerer();
+        answerer.session_id = 1;
+        let answer = answerer.answer(&offer).answer().to_string();
+        assert_eq!("v=0\r\n\
+            o=- 1 1 IN IP4 192.0.2.1\r\n\
+            s=-\r\n\
+            c=IN IP4 192.0.2.1\r\n\
+            t=0 0\r\n\
+            a=group:BUNDLE 0 1\r\n\
+            m=audio 50000 UDP/TLS/RTP/SAVPF 0\r\n\
+            a=mid:0\r\n\
+            a=sendrecv\r\n\
+            a=rtcp-mux\r\n\
+            a=extmap:3 http://www.ietf.org/id/draft-holmer-rmcat-transport-wide-cc-extensions-01\r\n\
+            a=rtpmap:0 PCMU/8000\r\n\
+            m=video 50000 UDP/TLS/RTP/SAVPF 98\r\n\
+            a=mid:1\r\n\
+            a=recvonly\r\n\
+            a=rtcp-mux\r\n\
+            a=rtpmap:98 H264/90000\r\n\
+            a=fmtp:98 profile-level-id=42e01f;packetization-mode=1\r\n\
+            a=rtcp-fb:98 nack pli\r\n\
+            m=application 0 UDP/DTLS/SCTP webrtc-datachannel\r\n\
+            a=mid:2\r\n", answer);
+    }
+
+    #[test]
+    fn negotiate_h264_profiles() {
+        let fmtp = |params: &str| Fmtp { payload_type: 96, params: params.to_string() };
+        let local = fmtp("packetization-mode=1;profile-level-id=42e01f");
+        assert_eq!(Some(Some("profile-level-id=42e00d;packetization-mode=1".to_string())),
+            match_h264(Some(&fmtp("packetization-mode=1;profile-level-id=42e00d")), Some(&local)));
+        // Baseline is not constrained baseline, high profile is different.
+        assert_eq!(None, match_h264(Some(&fmtp("packetization-mode=1;profile-level-id=42001f")), Some(&local)));
+        assert_eq!(None, match_h264(Some(&fmtp("packetization-mode=1;profile-level-id=64001f")), Some(&local)));
+        assert_eq!(None, match_h264(Some(&fmtp("profile-level-id=42e01f")), Some(&local)));
+        assert_eq!(Some(Some("profile-level-id=42e01f".to_string())), match_h264(None, None));
+    }
+
+    #[test]
+    fn reject_without_capability() {
+        let offer = "v=0\r\nt=0 0\r\nm=audio 5004 RTP/AVP 8\r\na=sendonly\r\n".parse::<SessionDescription>().unwrap();
+        let mut answerer = Answerer::new(IpAddr::V4(Ipv4Addr::LOCALHOST));
+        let session = answerer.answer(&offer);
+        assert!(session.media()[0].is_rejected());
+        assert_eq!(0, session.answer().media[0].port);
+        assert_eq!(vec!["8".to_string()], session.answer().media[0].formats);
+
+        let mut audio = MediaCapability::new("audio", 5004, Direction::SendOnly);
+        audio.add_codec(RtpMap { payload_type: 8, encoding: "PCMA".to_string(), clock_rate: 8000, channels: None }, None);
+        answerer.add_media(audio);
+        let session = answerer.answer(&offer);
+        assert_eq!(Direction::Inactive, session.media()[0].direction());
+        assert!(!session.media()[0].is_rejected());
+        assert!(session.bundle().is_empty());
+        assert_eq!("2", session.answer().origin.as_ref().unwrap().session_version);
+    }
+}
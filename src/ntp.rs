@@ -0,0 +1,175 @@
+use std::fmt;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// NtpTimestamp is a 64 bit fixed point NTP timestamp: seconds since 1900-01-01 00:00:00 UTC in the upper
+// 32 bits and fraction of a second in the lower 32 bits.  Seconds wrap around every 136 years, the first
+// wraparound (era 1) happens on 2036-02-07 06:28:16 UTC.
+#[derive(Clone, Copy, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct NtpTimestamp(u64);
+
+impl NtpTimestamp {
+    // Seconds between NTP epoch (1900) and Unix epoch (1970).
+    const UNIX_OFFSET: u64 = 2_208_988_800;
+    // Seconds between Unix epoch and the start of NTP era 1 (2036-02-07 06:28:16 UTC).
+    const ERA1_UNIX: u64 = (1 << 32) - NtpTimestamp::UNIX_OFFSET;
+
+    pub fn new(value: u64) -> NtpTimestamp {
+        NtpTimestamp(value)
+    }
+
+    pub fn now() -> NtpTimestamp {
+        NtpTimestamp::from_system_time(SystemTime::now())
+    }
+
+    pub fn from_system_time(time: SystemTime) -> NtpTimestamp {
+        let since_unix = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        let secs = since_unix.as_secs().wrapping_add(NtpTimestamp::UNIX_OFFSET) & 0xFFFF_FFFF;
+        let frac = ((since_unix.subsec_nanos() as u64) << 32) / 1_000_000_000;
+        NtpTimestamp((secs << 32) | frac)
+    }
+
+    // Converts to system time assuming the timestamp falls into 1968-2104 range (RFC 4330 section 3): when
+    // the most significant bit is clear, the time belongs to era 1.
+    pub fn to_system_time(&self) -> SystemTime {
+        let secs = self.seconds() as u64;
+        let nanos = (((self.fraction() as u64) * 1_000_000_000) >> 32) as u32;
+        let since_unix = if secs & 0x8000_0000 != 0 {
+            Duration::new(secs - NtpTimestamp::UNIX_OFFSET, nanos)
+        } else {
+            Duration::new(secs + NtpTimestamp::ERA1_UNIX, nanos)
+        };
+        UNIX_EPOCH + since_unix
+    }
+
+    pub fn as_u64(&self) -> u64 {
+        self.0
+    }
+
+    pub fn seconds(&self) -> u32 {
+        (self.0 >> 32) as u32
+    }
+
+    pub fn fraction(&self) -> u32 {
+        self.0 as u32
+    }
+
+    // Middle 32 bits of the timestamp used in LSR field of report blocks and in XR reference time reports.
+    pub fn compact(&self) -> u32 {
+        (self.0 >> 16) as u32
+    }
+
+    // Converts duration into units of 1/65536 seconds used in DLSR and DLRR fields, saturating on overflow.
+    pub fn compact_from_duration(duration: Duration) -> u32 {
+        let units = (duration.as_nanos() << 16) / 1_000_000_000;
+        u32::try_from(units).unwrap_or(u32::MAX)
+    }
+
+    pub fn compact_to_duration(compact: u32) -> Duration {
+        Duration::from_nanos(((compact as u64) * 1_000_000_000) >> 16)
+    }
+
+    // Computes round-trip time at arrival of a report block per RFC 3550 section 6.4.1 from the last SR
+    // timestamp and the delay since last SR echoed by the receiver.  This timestamp is the arrival time.
+    // Compact timestamps are compared modulo 2^32 so the result stays correct across era wraparound.
+    // Returns None when the receiver has not seen an SR yet or clocks are skewed so that RTT is negative.
+    pub fn rtt(&self, last_sr: u32, delay: u32) -> Option<Duration> {
+        if last_sr == 0 {
+            return None
+        }
+        let rtt = self.compact().wrapping_sub(last_sr).wrapping_sub(delay);
+        if rtt & 0x8000_0000 != 0 {
+            return None
+        }
+        Some(NtpTimestamp::compact_to_duration(rtt))
+    }
+}
+
+impl From<u64> for NtpTimestamp {
+    fn from(value: u64) -> Self {
+        NtpTimestamp(value)
+    }
+}
+
+impl From<NtpTimestamp> for u64 {
+    fn from(value: NtpTimestamp) -> Self {
+        value.0
+    }
+}
+
+impl From<SystemTime> for NtpTimestamp {
+    fn from(time: SystemTime) -> Self {
+        NtpTimestamp::from_system_time(time)
+    }
+}
+
+impl From<NtpTimestamp> for SystemTime {
+    fn from(value: NtpTimestamp) -> Self {
+        value.to_system_time()
+    }
+}
+
+impl fmt::Debug for NtpTimestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "NtpTimestamp({}.{:08x})", self.seconds(), self.fraction())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn convert_unix_epoch() {
+        let ntpts = NtpTimestamp::from_system_time(UNIX_EPOCH);
+        assert_eq!(2_208_988_800, ntpts.seconds());
+        assert_eq!(0, ntpts.fraction());
+        assert_eq!(UNIX_EPOCH, ntpts.to_system_time());
+    }
+
+    #[test]
+    fn convert_fraction() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_700_000_000_500);
+        let ntpts = NtpTimestamp::from(time);
+        assert_eq!(0x8000_0000, ntpts.fraction());
+        assert_eq!(time, SystemTime::from(ntpts));
+    }
+
+    #[test]
+    fn convert_era_wraparound() {
+        let era1 = UNIX_EPOCH + Duration::from_secs(2_085_978_496);
+        let ntpts = NtpTimestamp::from_system_time(era1);
+        assert_eq!(0, ntpts.as_u64());
+        assert_eq!(era1, ntpts.to_system_time());
+
+        let before = NtpTimestamp::from_system_time(era1 - Duration::from_secs(1));
+        assert_eq!(0xFFFF_FFFF, before.seconds());
+        let after = NtpTimestamp::from_system_time(era1 + Duration::from_secs(1));
+        assert_eq!(1, after.seconds());
+        assert_eq!(era1 + Duration::from_secs(1), after.to_system_time());
+    }
+
+    #[test]
+    fn compact_middle_bits() {
+        let ntpts = NtpTimestamp::new(0xe535_2f10_8000_1234);
+        assert_eq!(0x2f10_8000, ntpts.compact());
+        assert_eq!(0x0001_8000, NtpTimestamp::compact_from_duration(Duration::from_millis(1500)));
+        assert_eq!(Duration::from_millis(1500), NtpTimestamp::compact_to_duration(0x0001_8000));
+    }
+
+    #[test]
+    fn compute_rtt() {
+        // Example from RFC 3550 section 6.4.1.
+        let arrival = NtpTimestamp::new(0x0000_b710_8000_0000);
+        assert_eq!(Some(Duration::from_millis(6125)), arrival.rtt(0xb705_2000, 0x0005_4000));
+        assert_eq!(None, arrival.rtt(0, 0x0005_4000));
+        assert_eq!(None, arrival.rtt(0xb710_8000, 0x0005_4000));
+    }
+
+    #[test]
+    fn compute_rtt_across_era_wraparound() {
+        let sent = NtpTimestamp::new(0xFFFF_FFFF_0000_0000);
+        let arrival = NtpTimestamp::new(0x0000_0001_8000_0000);
+        let delay = NtpTimestamp::compact_from_duration(Duration::from_secs(2));
+        assert_eq!(Some(Duration::from_millis(500)), arrival.rtt(sent.compact(), delay));
+    }
+}
@@ -0,0 +1,342 @@
+use std::time::SystemTime;
+
+use crate::codec::{Codec, CodecError, CodecParameters};
+use crate::headers::Range;
+use crate::ntp::NtpTimestamp;
+use crate::rtp::{RtpExtension, RtpPacket, RtpPacketizer};
+use crate::rtsp::Headers;
+use crate::sdp::{Attributes, Direction, SessionDescription};
+
+#[derive(Debug)]
+pub enum OnvifError {
+    Codec(CodecError),
+    // Backchannel media offers no payload format the sender can produce.
+    UnsupportedCodec(String),
+    // AAC access unit longer than the 13 bit AU-size field can describe.
+    FrameTooLarge(usize),
+}
+
+impl From<CodecError> for OnvifError {
+    fn from(error: CodecError) -> Self {
+        OnvifError::Codec(error)
+    }
+}
+
+// Feature tag of Require header asking the device to describe its audio outputs (ONVIF Streaming
+// Specification section 5.3).
+pub const BACKCHANNEL: &str = "www.onvif.org/ver20/backchannel";
+
+// Feature tag of Require header for playback of recordings (ONVIF Streaming Specification section 6).
+pub const REPLAY: &str = "onvif-replay";
+
+// Indices of backchannel media.  The device describes them from the client point of view, a=sendonly means
+// the client sends and the device receives.
+pub fn backchannel_streams(description: &SessionDescription) -> Vec<usize> {
+    description.media.iter().enumerate()
+        .filter(|(_, media)| media.direction().or(description.direction()) == Some(Direction::SendOnly))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+// BackchannelSender turns audio frames into RTP packets for a backchannel stream.  G.711 (PCMU, PCMA) is sent
+// in packets of at most MTU size with one timestamp tick per sample.  AAC is sent in RFC 3640 AAC-hbr mode,
+// one access unit per packet behind its AU header, longer units are fragmented with the marker bit on the
+// last fragment.
+pub struct BackchannelSender {
+    codec: Codec,
+    packetizer: RtpPacketizer,
+    mtu: usize,
+    // Samples per AAC access unit, None for G.711.
+    aac_frame: Option<u32>,
+    started: bool,
+}
+
+impl BackchannelSender {
+    // Size of AU-headers-length and one 16 bit AU header.
+    const AU_HEADER_SIZE: usize = 4;
+    // Largest AAC access unit, limited by sizelength=13.
+    pub const MAX_AAC_FRAME: usize = 8191;
+
+    // Creates a sender for the first supported codec of the media.
+    pub fn new(codecs: &[Codec], mtu: usize, ssrc: u32) -> Result<BackchannelSender, OnvifError> {
+        let codec = codecs.iter()
+            .find(|codec| BackchannelSender::supports(codec))
+            .ok_or_else(|| OnvifError::UnsupportedCodec(codecs.iter().map(Codec::encoding).collect::<Vec<_>>().join(",")))?
+            .clone();
+        let aac_frame = matches!(codec.parameters(), CodecParameters::Aac(_)).then_some(1024);
+        // Chunks are cut to fit, so the packetizer makes exactly one packet of each.
+        let mtu = mtu.max(RtpPacket::HEADER_SIZE + BackchannelSender::AU_HEADER_SIZE + 1);
+        Ok(BackchannelSender {
+            packetizer: RtpPacketizer::new(mtu, codec.payload_type(), ssrc),
+            codec,
+            mtu,
+            aac_frame,
+            started: false,
+        })
+    }
+
+    fn supports(codec: &Codec) -> bool {
+        match codec.parameters() {
+            CodecParameters::Aac(aac) => aac.size_length == 13 && aac.index_length == 3,
+            _ => ["PCMU", "PCMA"].iter().any(|name| codec.encoding().eq_ignore_ascii_case(name)),
+        }
+    }
+
+    pub fn codec(&self) -> &Codec {
+        &self.codec
+    }
+
+    pub fn ssrc(&self) -> u32 {
+        self.packetizer.ssrc()
+    }
+
+    // Serialized RTP packets of one G.711 buffer or AAC access unit of at most MAX_AAC_FRAME bytes.
+    pub fn packetize(&mut self, frame: &[u8]) -> Result<Vec<Vec<u8>>, OnvifError> {
+        let mut packets = Vec::new();
+        match self.aac_frame {
+            None => {
+                for chunk in frame.chunks(self.mtu - RtpPacket::HEADER_SIZE) {
+                    let mut data = self.packetizer.packetize(chunk, chunk.len() as u32).remove(0).to_vec();
+                    // Marker starts a talkspurt (RFC 3551 section 4.1), which the first packet does.
+                    if std::mem::replace(&mut self.started, true) {
+                        data[1] &= 0x7f;
+                    }
+                    packets.push(data);
+                }
+            },
+            Some(samples) => {
+                if frame.len() > BackchannelSender::MAX_AAC_FRAME {
+                    return Err(OnvifError::FrameTooLarge(frame.len()))
+                }
+                let size = frame.len() as u16;
+                let chunks = frame.chunks(self.mtu - RtpPacket::HEADER_SIZE - BackchannelSender::AU_HEADER_SIZE).collect::<Vec<_>>();
+                for (index, chunk) in chunks.iter().enumerate() {
+                    // AU-headers-length in bits, then 13 bits AU-size and 3 bits AU-Index of zero.
+                    let mut payload = vec![0x00, 0x10, (size >> 5) as u8, (size << 3) as u8];
+                    payload.extend_from_slice(chunk);
+                    let ticks = if index == 0 { samples } else { 0 };
+                    let mut data = self.packetizer.packetize(&payload, ticks).remove(0).to_vec();
+                    if index + 1 < chunks.len() {
+                        data[1] &= 0x7f;
+                    }
+                    packets.push(data);
+                }
+            },
+        }
+        Ok(packets)
+    }
+}
+
+// ReplayOptions are the PLAY request headers of recording playback.  Range is usually absolute time, an end
+// before start together with a negative scale plays backwards.  Without rate control the device sends as fast
+// as the client reads, immediate drops data queued for an earlier PLAY request.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplayOptions {
+    pub range: Option<Range>,
+    pub scale: Option<f64>,
+    pub rate_control: bool,
+    pub immediate: bool,
+}
+
+impl Default for ReplayOptions {
+    fn default() -> Self {
+        ReplayOptions { range: None, scale: None, rate_control: true, immediate: false }
+    }
+}
+
+impl ReplayOptions {
+    pub fn apply(&self, headers: &mut Headers) {
+        if let Some(range) = &self.range {
+            headers.set(Headers::RANGE, range);
+        }
+        if let Some(scale) = self.scale {
+            headers.set(Headers::SCALE, scale);
+        }
+        if !self.rate_control {
+            headers.set(Headers::RATE_CONTROL, "no");
+        }
+        if self.immediate {
+            headers.set(Headers::IMMEDIATE, "yes");
+        }
+    }
+}
+
+// RTP header extension of replayed packets (ONVIF Streaming Specification section 6.3):
+//
+//    0                   1                   2                   3
+//    0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//   |            0xABAC             |           length=3            |
+//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//   |                         NTP timestamp                         |
+//   |                                                               |
+//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//   |C|E|D|T|  mbz  |     CSeq      |            padding            |
+//   +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// Time is the wall-clock time of the recorded frame, present on its first packet.  C marks a clean point
+// (key frame), E the end of a contiguous section of the recording, D a discontinuity to the previous packet,
+// T the terminal packet of the requested range.  CSeq is the low byte of the PLAY request the packet answers.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ReplayExtension {
+    pub time: NtpTimestamp,
+    pub clean_point: bool,
+    pub end: bool,
+    pub discontinuity: bool,
+    pub terminal: bool,
+    pub cseq: u8,
+}
+
+impl ReplayExtension {
+    pub const PROFILE: u16 = 0xABAC;
+    pub const SIZE: usize = 12;
+
+    // Reads the extension, None for other profiles.  Extensions with additional data after the replay
+    // fields are accepted.
+    pub fn from_extension(extension: &RtpExtension) -> Option<ReplayExtension> {
+        let data = extension.data();
+        if extension.head() != ReplayExtension::PROFILE || data.len() < ReplayExtension::SIZE {
+            return None
+        }
+        let flags = data[8];
+        Some(ReplayExtension {
+            time: NtpTimestamp::new(u64::from_be_bytes(data[..8].try_into().ok()?)),
+            clean_point: flags & 0x80 != 0,
+            end: flags & 0x40 != 0,
+            discontinuity: flags & 0x20 != 0,
+            terminal: flags & 0x10 != 0,
+            cseq: data[9],
+        })
+    }
+
+    pub fn from_packet(packet: &RtpPacket) -> Option<ReplayExtension> {
+        packet.extension().and_then(ReplayExtension::from_extension)
+    }
+
+    // Extension data without the profile and length words, see RtpExtension::new.
+    pub fn to_bytes(&self) -> [u8; ReplayExtension::SIZE] {
+        let mut data = [0u8; ReplayExtension::SIZE];
+        data[..8].copy_from_slice(&self.time.as_u64().to_be_bytes());
+        data[8] = (self.clean_point as u8) << 7 | (self.end as u8) << 6 | (self.discontinuity as u8) << 5 | (self.terminal as u8) << 4;
+        data[9] = self.cseq;
+        data
+    }
+
+    pub fn wallclock(&self) -> SystemTime {
+        self.time.to_system_time()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::*;
+
+    // Describe response of a camera with Require: www.onvif.org/ver20/backchannel.
+    const SDP: &str = "v=0\r\n\
+        o=- 0 0 IN IP4 192.168.1.64\r\n\
+        s=Media Presentation\r\n\
+        t=0 0\r\n\
+        m=video 0 RTP/AVP 96\r\n\
+        a=rtpmap:96 H264/90000\r\n\
+        a=recvonly\r\n\
+        a=control:trackID=1\r\n\
+        m=audio 0 RTP/AVP 0\r\n\
+        a=recvonly\r\n\
+        a=control:trackID=2\r\n\
+        m=audio 0 RTP/AVP 97 0 8\r\n\
+        a=rtpmap:97 MPEG4-GENERIC/16000/1\r\n\
+        a=fmtp:97 streamtype=5;profile-level-id=15;mode=AAC-hbr;config=1408;sizelength=13;indexlength=3;indexdeltalength=3\r\n\
+        a=sendonly\r\n\
+        a=control:trackID=3\r\n";
+
+    #[test]
+    fn find_backchannel() {
+        let description = SDP.parse::<SessionDescription>().unwrap();
+        assert_eq!(vec![2], backchannel_streams(&description));
+        let codecs = description.media[2].codecs().unwrap();
+        let sender = BackchannelSender::new(&codecs, 1400, 0x1234).unwrap();
+        assert_eq!("MPEG4-GENERIC", sender.codec().encoding());
+        let sender = BackchannelSender::new(&codecs[1..], 1400, 0x1234).unwrap();
+        assert_eq!("PCMU", sender.codec().encoding());
+        let codecs = description.media[0].codecs().unwrap();
+        assert!(matches!(BackchannelSender::new(&codecs, 1400, 0x1234), Err(OnvifError::UnsupportedCodec(name)) if name == "H264"));
+    }
+
+    #[test]
+    fn packetize_g711() {
+        let description = SDP.parse::<SessionDescription>().unwrap();
+        let codecs = description.media[2].codecs().unwrap();
+        let mut sender = BackchannelSender::new(&codecs[2..], 172, 0x1234).unwrap();
+        let packets = sender.packetize(&[0xd5; 400]).unwrap();
+        assert_eq!(3, packets.len());
+        let packets = packets.iter().map(|data| RtpPacket::from_slice(data).unwrap()).collect::<Vec<_>>();
+        assert_eq!(vec![160, 160, 80], packets.iter().map(|packet| packet.payload().len()).collect::<Vec<_>>());
+        assert_eq!(vec![true, false, false], packets.iter().map(RtpPacket::mark).collect::<Vec<_>>());
+        assert_eq!(8, packets[0].payload_type());
+        assert_eq!(0x1234, packets[0].ssrc());
+        assert_eq!(packets[0].timestamp().wrapping_add(160), packets[1].timestamp());
+        assert_eq!(packets[0].seq_number().wrapping_add(2), packets[2].seq_number());
+    }
+
+    #[test]
+    fn parse_replay_extension() {
+        let time = UNIX_EPOCH + Duration::from_millis(1_245_066_540_500);
+        let extension = ReplayExtension { time: NtpTimestamp::from(time), clean_point: true, end: true, cseq: 4, ..Default::default() };
+        let data = extension.to_bytes();
+        assert_eq!(&[0xc0, 0x04, 0x00, 0x00], &data[8..]);
+        let mut packet = RtpPacket::new(true, 96, 1, 0, 0x1234, &[0x65, 0x88]);
+        packet.set_extension(Some(RtpExtension::new(ReplayExtension::PROFILE, &data)));
+        let serialized = packet.to_vec();
+        let packet = RtpPacket::from_slice(&serialized).unwrap();
+        let parsed = ReplayExtension::from_packet(&packet).unwrap();
+        assert_eq!(extension, parsed);
+        assert_eq!(time, parsed.wallclock());
+        assert!(!parsed.discontinuity && !parsed.terminal);
+        assert_eq!(&[0x65, 0x88], packet.payload());
+        assert_eq!(None, ReplayExtension::from_extension(&RtpExtension::new(RtpExtension::ONE_BYTE, &data)));
+        assert_eq!(None, ReplayExtension::from_extension(&RtpExtension::new(ReplayExtension::PROFILE, &data[..8])));
+    }
+
+    #[test]
+    fn apply_replay_options() {
+        let start = UNIX_EPOCH + Duration::from_secs(1_245_066_600);
+        let options = ReplayOptions {
+            range: Some(Range::Clock { start, end: Some(start - Duration::from_secs(60)) }),
+            scale: Some(-2.0),
+            rate_control: false,
+            immediate: true,
+        };
+        let mut headers = Headers::new();
+        options.apply(&mut headers);
+        assert_eq!(Some("clock=20090615T115000Z-20090615T114900Z"), headers.get("Range"));
+        assert_eq!(Some("-2"), headers.get("Scale"));
+        assert_eq!(Some("no"), headers.get("Rate-Control"));
+        assert_eq!(Some("yes"), headers.get("Immediate"));
+        let mut headers = Headers::new();
+        ReplayOptions::default().apply(&mut headers);
+        assert_eq!(0, headers.iter().count());
+    }
+
+    #[test]
+    fn packetize_aac() {
+        let description = SDP.parse::<SessionDescription>().unwrap();
+        let codecs = description.media[2].codecs().unwrap();
+        let mut sender = BackchannelSender::new(&codecs, 116, 0x1234).unwrap();
+        let packets = sender.packetize(&[0x21; 300]).unwrap();
+        let packets = packets.iter().map(|data| RtpPacket::from_slice(data).unwrap()).collect::<Vec<_>>();
+        assert_eq!(3, packets.len());
+        // 300 bytes: AU-size 300 << 3.
+        assert_eq!(&[0x00, 0x10, 0x09, 0x60], &packets[0].payload()[..4]);
+        assert_eq!(&[0x00, 0x10, 0x09, 0x60], &packets[2].payload()[..4]);
+        assert_eq!(vec![false, false, true], packets.iter().map(RtpPacket::mark).collect::<Vec<_>>());
+        assert!(packets.iter().all(|packet| packet.timestamp() == packets[0].timestamp()));
+        let packet = sender.packetize(&[0x21; 10]).unwrap().remove(0);
+        let packet = RtpPacket::from_slice(&packet).unwrap();
+        assert_eq!(packets[0].timestamp().wrapping_add(1024), packet.timestamp());
+        assert!(packet.mark());
+        assert_eq!(82, sender.packetize(&[0x21; 8191]).unwrap().len());
+        assert!(matches!(sender.packetize(&[0x21; 8192]), Err(OnvifError::FrameTooLarge(8192))));
+    }
+}
@@ -1,4 +1,8 @@
 pub(crate) use std::fmt;
+use std::borrow::Cow;
+use std::time::Duration;
+
+use crate::ntp::NtpTimestamp;
 
 #[derive(Debug)]
 pub enum RtcpError {
@@ -6,6 +10,10 @@ pub enum RtcpError {
     InvalidVersion(u8),
     InvalidPadding(usize),
     PacketTooShort(u8),
+    InvalidPayloadType(u8),
+    InvalidFormat(u8),
+    InvalidName([u8; 4]),
+    InvalidCompound(u8),
 }
 
 // TODO: Different RTCP packets lend themselves into implementation through enum.
@@ -42,7 +50,7 @@ pub struct RtcpPacket<'a> {
 // +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
 // |                   delay since last SR (DLSR)                  |
 // +=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+
-#[derive(Clone, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct SynSource {
 	ssrc:     u32,  // SSRC identifier
 	f_lost:   u8,   // Fraction Lost
@@ -89,14 +97,14 @@ pub struct SynSource {
 // +=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+
 // |                  profile-specific extensions                  |
 // +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
-#[derive(Clone, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct DataSR<'a> {
-	ssrc:    u32,             // SSRC of sender
-	ntpts:   u64,             // NTP timestamp
-	rtpts:   u32,             // RTP timestamp
-	packets: u32,             // sender's packet count
-	octets:  u32,             // sender's octet count
-	reports: &'a [SynSource], // Sender Reports
+	ssrc:    u32,                   // SSRC of sender
+	ntpts:   NtpTimestamp,          // NTP timestamp
+	rtpts:   u32,                   // RTP timestamp
+	packets: u32,                   // sender's packet count
+	octets:  u32,                   // sender's octet count
+	reports: Cow<'a, [SynSource]>,  // Sender Reports
 }
 
 // DataRR encapsulates data for Receiver Report packet.
@@ -125,10 +133,10 @@ pub struct DataSR<'a> {
 // +=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+
 // |                  profile-specific extensions                  |
 // +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
-#[derive(Clone, Eq, PartialEq)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct DataRR<'a> {
-	ssrc:    u32,             // SSRC of sender
-	reports: &'a [SynSource], // Sender Reports
+	ssrc:    u32,                   // SSRC of sender
+	reports: Cow<'a, [SynSource]>,  // Sender Reports
 }
 
 // DataSDES encapsulates data for Source Description packet.
@@ -147,6 +155,22 @@ pub struct DataRR<'a> {
 // |                           SDES items                          |
 // |                              ...                              |
 // +=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DataSDES<'a> {
+	chunks: Vec<SdesChunk<'a>>, // Chunks, one per source
+}
+
+// SdesChunk encapsulates SSRC/CSRC and its SDES items.  Item list is terminated by a null octet and padded.
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |    CNAME=1    |     length    | user and domain name        ...
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SdesChunk<'a> {
+	ssrc:  u32,               // SSRC/CSRC
+	items: Vec<(u8, &'a [u8])>, // Item type and text
+}
 
 // DataBYE encapsulates data for Goodbye packet.
 //  0                   1                   2                   3
@@ -160,6 +184,11 @@ pub struct DataRR<'a> {
 // +=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+
 // |     length    |               reason for leaving            ... (opt)
 // +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DataBYE<'a> {
+	sources: Vec<u32>,        // SSRC/CSRC leaving the session
+	reason:  Option<&'a [u8]>, // Reason for leaving
+}
 
 // DataAPP encapsulates data for Application-Defined packet.
 //  0                   1                   2                   3
@@ -179,24 +208,43 @@ pub enum RtcpPayload<'a> {
     RR(DataRR<'a>),
 }
 
+// RtcpMode controls validation of RTCP datagrams.  Compound mode enforces RFC 3550 rules where the first
+// packet must be SR or RR.  Reduced size mode (RFC 5506) accepts any packet type first, including a single
+// feedback packet.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RtcpMode {
+    Compound,
+    ReducedSize,
+}
+
 
 impl<'a> RtcpPacket<'a> {
-    const HEADER_SIZE: usize = 4;
-    const VERSION: u8 = 2 << 6;
+    pub(crate) const HEADER_SIZE: usize = 4;
+    const VERSION: u8 = 2;
+
+    // Packet types.
+    pub const SR: u8 = 200;
+    pub const RR: u8 = 201;
+    pub const SDES: u8 = 202;
+    pub const BYE: u8 = 203;
+    pub const APP: u8 = 204;
+    pub const RTPFB: u8 = 205;
+    pub const PSFB: u8 = 206;
+    pub const XR: u8 = 207;
 
     pub fn new(
         payload_type: u8,
         payload: &'a [u8],
-    ) -> RtcpPacket<'_> {
+    ) -> RtcpPacket<'a> {
         RtcpPacket { 
             cc: 0u8, 
-            payload_type: payload_type,
-            length: payload.len() as u16, 
-            payload: payload, 
+            payload_type,
+            length: (payload.len() / 4) as u16, 
+            payload, 
         }
     }
 
-    pub fn from_slice(slice: &'a [u8]) -> Result<RtcpPacket<'_>, RtcpError> {
+    pub fn from_slice(slice: &'a [u8]) -> Result<RtcpPacket<'a>, RtcpError> {
         let slice_len = slice.len();
         if slice_len < RtcpPacket::HEADER_SIZE {
             return Err(RtcpError::InvalidLen(slice_len))
@@ -205,25 +253,422 @@ impl<'a> RtcpPacket<'a> {
         if version != RtcpPacket::VERSION {
             return Err(RtcpError::InvalidVersion(version))
         }
-        let cc = slice[0] & 0x0F;
+        let cc = slice[0] & 0x1F;
         let pad_flag = (slice[0] & 0x20) >> 5;  // 0 or 1
-        let off = RtcpPacket::HEADER_SIZE + (cc as usize) * 24 + 24;
+        let length = u16::from_be_bytes([slice[2], slice[3]]);
+        let size = (length as usize + 1) * 4;
 
-        if off > slice_len {
-            return Err(RtcpError::PacketTooShort(cc))
+        if size > slice_len {
+            return Err(RtcpError::InvalidLen(size))
         }
-        let pad_len = (slice[slice_len - 1] * pad_flag) as usize;
-        if (off + pad_len) > slice_len {
+        let pad_len = (slice[size - 1] * pad_flag) as usize;
+        if (RtcpPacket::HEADER_SIZE + pad_len) > size {
             return Err(RtcpError::InvalidPadding(pad_len))
         }
 
         Ok(RtcpPacket { 
-            cc: cc, 
-            payload_type: slice[1] & 0x7F, 
-            length: u16::from_be_bytes([slice[2], slice[3]]), 
-            payload: &slice[off..(slice_len - pad_len)], 
+            cc, 
+            payload_type: slice[1], 
+            length, 
+            payload: &slice[RtcpPacket::HEADER_SIZE..(size - pad_len)], 
+        })
+    }
+
+    // Count of report blocks or sources, or the subtype/feedback message type depending on packet type.
+    pub fn count(&self) -> u8 {
+        self.cc
+    }
+
+    pub fn payload_type(&self) -> u8 {
+        self.payload_type
+    }
+
+    pub fn payload(&self) -> &'a [u8] {
+        self.payload
+    }
+
+    // Total size of the packet in bytes, including header and padding.
+    pub fn size(&self) -> usize {
+        (self.length as usize + 1) * 4
+    }
+
+    // Splits a datagram into individual RTCP packets.  Lengths of the packets must add up to the length of
+    // the datagram and only the last packet may be padded.
+    pub fn compound_from_slice(slice: &'a [u8], mode: RtcpMode) -> Result<Vec<RtcpPacket<'a>>, RtcpError> {
+        let mut packets = Vec::<RtcpPacket>::new();
+        let mut off = 0;
+        while off < slice.len() {
+            let packet = RtcpPacket::from_slice(&slice[off..])?;
+            off += packet.size();
+            if (slice[off - packet.size()] & 0x20) != 0 && off != slice.len() {
+                return Err(RtcpError::InvalidPadding(off))
+            }
+            packets.push(packet);
+        }
+        match packets.first() {
+            None => Err(RtcpError::InvalidLen(0)),
+            Some(first) if mode == RtcpMode::Compound && first.payload_type != RtcpPacket::SR && first.payload_type != RtcpPacket::RR => {
+                Err(RtcpError::InvalidCompound(first.payload_type))
+            },
+            Some(_) => Ok(packets),
+        }
+    }
+
+    // Writes common RTCP header for the packet of given total size in bytes.  The size must be a multiple of 4.
+    pub(crate) fn write_header(buf: &mut Vec<u8>, count: u8, payload_type: u8, size: usize) {
+        buf.push((RtcpPacket::VERSION << 6) | (count & 0x1F));
+        buf.push(payload_type);
+        buf.extend_from_slice(&((size / 4 - 1) as u16).to_be_bytes());
+    }
+}
+
+impl SynSource {
+    const SIZE: usize = 24;
+
+    pub fn new(
+        ssrc: u32,
+        f_lost: u8,
+        p_lost: u32,
+        seqnum: u32,
+        jitter: u32,
+        last_sr: u32,
+        delay: u32,
+    ) -> SynSource {
+        SynSource { ssrc, f_lost, p_lost, seqnum, jitter, last_sr, delay }
+    }
+
+    pub fn from_slice(slice: &[u8]) -> Result<SynSource, RtcpError> {
+        if slice.len() < SynSource::SIZE {
+            return Err(RtcpError::InvalidLen(slice.len()))
+        }
+        let u32_at = |off: usize| u32::from_be_bytes([slice[off], slice[off + 1], slice[off + 2], slice[off + 3]]);
+        Ok(SynSource {
+            ssrc: u32_at(0),
+            f_lost: slice[4],
+            p_lost: u32_at(4) & 0x00FF_FFFF,
+            seqnum: u32_at(8),
+            jitter: u32_at(12),
+            last_sr: u32_at(16),
+            delay: u32_at(20),
+        })
+    }
+
+    pub fn ssrc(&self) -> u32 {
+        self.ssrc
+    }
+
+    pub fn fraction_lost(&self) -> u8 {
+        self.f_lost
+    }
+
+    pub fn packets_lost(&self) -> u32 {
+        self.p_lost
+    }
+
+    pub fn highest_seq(&self) -> u32 {
+        self.seqnum
+    }
+
+    pub fn jitter(&self) -> u32 {
+        self.jitter
+    }
+
+    pub fn last_sr(&self) -> u32 {
+        self.last_sr
+    }
+
+    pub fn delay(&self) -> u32 {
+        self.delay
+    }
+
+    // Round-trip time between this host and the reporting receiver given the arrival time of the report.
+    pub fn rtt(&self, arrival: NtpTimestamp) -> Option<Duration> {
+        arrival.rtt(self.last_sr, self.delay)
+    }
+
+    fn write_to(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(&self.ssrc.to_be_bytes());
+        buf.extend_from_slice(&(((self.f_lost as u32) << 24) | (self.p_lost & 0x00FF_FFFF)).to_be_bytes());
+        buf.extend_from_slice(&self.seqnum.to_be_bytes());
+        buf.extend_from_slice(&self.jitter.to_be_bytes());
+        buf.extend_from_slice(&self.last_sr.to_be_bytes());
+        buf.extend_from_slice(&self.delay.to_be_bytes());
+    }
+
+    fn from_slice_list(slice: &[u8], count: u8) -> Result<Vec<SynSource>, RtcpError> {
+        if slice.len() < count as usize * SynSource::SIZE {
+            return Err(RtcpError::PacketTooShort(count))
+        }
+        slice.chunks_exact(SynSource::SIZE).take(count as usize).map(SynSource::from_slice).collect()
+    }
+}
+
+impl<'a> DataSR<'a> {
+    const FIXED_SIZE: usize = 24;
+
+    pub fn new(
+        ssrc: u32,
+        ntpts: NtpTimestamp,
+        rtpts: u32,
+        packets: u32,
+        octets: u32,
+        reports: impl Into<Cow<'a, [SynSource]>>,
+    ) -> DataSR<'a> {
+        DataSR { ssrc, ntpts, rtpts, packets, octets, reports: reports.into() }
+    }
+
+    pub fn from_packet(packet: &RtcpPacket) -> Result<DataSR<'a>, RtcpError> {
+        if packet.payload_type() != RtcpPacket::SR {
+            return Err(RtcpError::InvalidPayloadType(packet.payload_type()))
+        }
+        let slice = packet.payload();
+        if slice.len() < DataSR::FIXED_SIZE {
+            return Err(RtcpError::InvalidLen(slice.len()))
+        }
+        let u32_at = |off: usize| u32::from_be_bytes([slice[off], slice[off + 1], slice[off + 2], slice[off + 3]]);
+        Ok(DataSR {
+            ssrc: u32_at(0),
+            ntpts: NtpTimestamp::new(((u32_at(4) as u64) << 32) | u32_at(8) as u64),
+            rtpts: u32_at(12),
+            packets: u32_at(16),
+            octets: u32_at(20),
+            reports: Cow::Owned(SynSource::from_slice_list(&slice[DataSR::FIXED_SIZE..], packet.count())?),
+        })
+    }
+
+    pub fn ssrc(&self) -> u32 {
+        self.ssrc
+    }
+
+    pub fn ntpts(&self) -> NtpTimestamp {
+        self.ntpts
+    }
+
+    pub fn rtpts(&self) -> u32 {
+        self.rtpts
+    }
+
+    pub fn packets(&self) -> u32 {
+        self.packets
+    }
+
+    pub fn octets(&self) -> u32 {
+        self.octets
+    }
+
+    pub fn reports(&self) -> &[SynSource] {
+        &self.reports
+    }
+
+    // Serializes report into a complete RTCP packet.  At most 31 report blocks fit in one packet.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let count = usize::min(self.reports.len(), 31);
+        let size = RtcpPacket::HEADER_SIZE + DataSR::FIXED_SIZE + count * SynSource::SIZE;
+        let mut buf = Vec::<u8>::with_capacity(size);
+        RtcpPacket::write_header(&mut buf, count as u8, RtcpPacket::SR, size);
+        buf.extend_from_slice(&self.ssrc.to_be_bytes());
+        buf.extend_from_slice(&self.ntpts.as_u64().to_be_bytes());
+        buf.extend_from_slice(&self.rtpts.to_be_bytes());
+        buf.extend_from_slice(&self.packets.to_be_bytes());
+        buf.extend_from_slice(&self.octets.to_be_bytes());
+        for report in self.reports.iter().take(count) {
+            report.write_to(&mut buf);
+        }
+        buf
+    }
+}
+
+impl<'a> DataRR<'a> {
+    const FIXED_SIZE: usize = 4;
+
+    pub fn new(ssrc: u32, reports: impl Into<Cow<'a, [SynSource]>>) -> DataRR<'a> {
+        DataRR { ssrc, reports: reports.into() }
+    }
+
+    pub fn from_packet(packet: &RtcpPacket) -> Result<DataRR<'a>, RtcpError> {
+        if packet.payload_type() != RtcpPacket::RR {
+            return Err(RtcpError::InvalidPayloadType(packet.payload_type()))
+        }
+        let slice = packet.payload();
+        if slice.len() < DataRR::FIXED_SIZE {
+            return Err(RtcpError::InvalidLen(slice.len()))
+        }
+        Ok(DataRR {
+            ssrc: u32::from_be_bytes([slice[0], slice[1], slice[2], slice[3]]),
+            reports: Cow::Owned(SynSource::from_slice_list(&slice[DataRR::FIXED_SIZE..], packet.count())?),
         })
     }
+
+    pub fn ssrc(&self) -> u32 {
+        self.ssrc
+    }
+
+    pub fn reports(&self) -> &[SynSource] {
+        &self.reports
+    }
+
+    // Serializes report into a complete RTCP packet.  At most 31 report blocks fit in one packet.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let count = usize::min(self.reports.len(), 31);
+        let size = RtcpPacket::HEADER_SIZE + DataRR::FIXED_SIZE + count * SynSource::SIZE;
+        let mut buf = Vec::<u8>::with_capacity(size);
+        RtcpPacket::write_header(&mut buf, count as u8, RtcpPacket::RR, size);
+        buf.extend_from_slice(&self.ssrc.to_be_bytes());
+        for report in self.reports.iter().take(count) {
+            report.write_to(&mut buf);
+        }
+        buf
+    }
+}
+
+impl<'a> SdesChunk<'a> {
+    pub fn new(ssrc: u32, items: Vec<(u8, &'a [u8])>) -> SdesChunk<'a> {
+        SdesChunk { ssrc, items }
+    }
+
+    pub fn ssrc(&self) -> u32 {
+        self.ssrc
+    }
+
+    pub fn items(&self) -> &[(u8, &'a [u8])] {
+        &self.items
+    }
+
+    // Returns text of the first item of given type.
+    pub fn item(&self, kind: u8) -> Option<&'a [u8]> {
+        self.items.iter().find(|(item_kind, _)| *item_kind == kind).map(|(_, text)| *text)
+    }
+}
+
+impl<'a> DataSDES<'a> {
+    // Item types.
+    pub const CNAME: u8 = 1;
+    pub const NAME: u8 = 2;
+    pub const EMAIL: u8 = 3;
+    pub const PHONE: u8 = 4;
+    pub const LOC: u8 = 5;
+    pub const TOOL: u8 = 6;
+    pub const NOTE: u8 = 7;
+    pub const PRIV: u8 = 8;
+
+    pub fn new(chunks: Vec<SdesChunk<'a>>) -> DataSDES<'a> {
+        DataSDES { chunks }
+    }
+
+    pub fn from_packet(packet: &RtcpPacket<'a>) -> Result<DataSDES<'a>, RtcpError> {
+        if packet.payload_type() != RtcpPacket::SDES {
+            return Err(RtcpError::InvalidPayloadType(packet.payload_type()))
+        }
+        let slice = packet.payload();
+        let mut chunks = Vec::<SdesChunk>::with_capacity(packet.count() as usize);
+        let mut off = 0;
+        for _ in 0..packet.count() {
+            if off + 4 > slice.len() {
+                return Err(RtcpError::PacketTooShort(packet.count()))
+            }
+            let ssrc = u32::from_be_bytes([slice[off], slice[off + 1], slice[off + 2], slice[off + 3]]);
+            off += 4;
+            let mut items = Vec::<(u8, &[u8])>::new();
+            loop {
+                if off >= slice.len() {
+                    return Err(RtcpError::InvalidLen(slice.len()))
+                }
+                let kind = slice[off];
+                if kind == 0 {
+                    // Null item ends the list, the chunk is padded to 32 bit boundary.
+                    off = (off + 4) & !3;
+                    break
+                }
+                if off + 2 > slice.len() || off + 2 + slice[off + 1] as usize > slice.len() {
+                    return Err(RtcpError::InvalidLen(slice.len()))
+                }
+                let len = slice[off + 1] as usize;
+                items.push((kind, &slice[off + 2..off + 2 + len]));
+                off += 2 + len;
+            }
+            chunks.push(SdesChunk { ssrc, items });
+        }
+        Ok(DataSDES { chunks })
+    }
+
+    pub fn chunks(&self) -> &[SdesChunk<'a>] {
+        &self.chunks
+    }
+
+    // Serializes description into a complete RTCP packet.  Item text longer than 255 octets is truncated.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut buf = Vec::<u8>::new();
+        let count = usize::min(self.chunks.len(), 31);
+        RtcpPacket::write_header(&mut buf, count as u8, RtcpPacket::SDES, RtcpPacket::HEADER_SIZE);
+        for chunk in self.chunks.iter().take(count) {
+            buf.extend_from_slice(&chunk.ssrc.to_be_bytes());
+            for (kind, text) in chunk.items.iter() {
+                let len = usize::min(text.len(), u8::MAX as usize);
+                buf.push(*kind);
+                buf.push(len as u8);
+                buf.extend_from_slice(&text[..len]);
+            }
+            buf.push(0);
+            buf.resize(buf.len().div_ceil(4) * 4, 0);
+        }
+        let length = (buf.len() / 4 - 1) as u16;
+        buf[2..4].copy_from_slice(&length.to_be_bytes());
+        buf
+    }
+}
+
+impl<'a> DataBYE<'a> {
+    pub fn new(sources: Vec<u32>, reason: Option<&'a [u8]>) -> DataBYE<'a> {
+        DataBYE { sources, reason }
+    }
+
+    pub fn from_packet(packet: &RtcpPacket<'a>) -> Result<DataBYE<'a>, RtcpError> {
+        if packet.payload_type() != RtcpPacket::BYE {
+            return Err(RtcpError::InvalidPayloadType(packet.payload_type()))
+        }
+        let slice = packet.payload();
+        let off = packet.count() as usize * 4;
+        if off > slice.len() {
+            return Err(RtcpError::PacketTooShort(packet.count()))
+        }
+        let sources = slice[..off]
+            .chunks_exact(4)
+            .map(|c| u32::from_be_bytes([c[0], c[1], c[2], c[3]]))
+            .collect();
+        let reason = match slice.get(off) {
+            Some(&len) if off + 1 + len as usize <= slice.len() => Some(&slice[off + 1..off + 1 + len as usize]),
+            Some(_) => return Err(RtcpError::InvalidLen(slice.len())),
+            None => None,
+        };
+        Ok(DataBYE { sources, reason })
+    }
+
+    pub fn sources(&self) -> &[u32] {
+        &self.sources
+    }
+
+    pub fn reason(&self) -> Option<&'a [u8]> {
+        self.reason
+    }
+
+    // Serializes goodbye into a complete RTCP packet.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut buf = Vec::<u8>::new();
+        let count = usize::min(self.sources.len(), 31);
+        RtcpPacket::write_header(&mut buf, count as u8, RtcpPacket::BYE, RtcpPacket::HEADER_SIZE);
+        for ssrc in self.sources.iter().take(count) {
+            buf.extend_from_slice(&ssrc.to_be_bytes());
+        }
+        if let Some(reason) = self.reason {
+            let len = usize::min(reason.len(), u8::MAX as usize);
+            buf.push(len as u8);
+            buf.extend_from_slice(&reason[..len]);
+            buf.resize(buf.len().div_ceil(4) * 4, 0);
+        }
+        let length = (buf.len() / 4 - 1) as u16;
+        buf[2..4].copy_from_slice(&length.to_be_bytes());
+        buf
+    }
 }
 
 impl<'a> fmt::Debug for RtcpPacket<'a> {
@@ -235,3 +680,171 @@ impl<'a> fmt::Debug for RtcpPacket<'a> {
             .finish()
     }
 }
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_receiver_report_packet() {
+        let data: [u8; 8] = [
+            0x80, 0xc9, 0x00, 0x01, 0x12, 0x34, 0x56, 0x78,
+        ];
+        let packet = RtcpPacket::from_slice(&data).unwrap();
+        assert_eq!(RtcpPacket::RR, packet.payload_type());
+        assert_eq!(0, packet.count());
+        assert_eq!(8, packet.size());
+        assert_eq!(&[0x12u8, 0x34, 0x56, 0x78][..], packet.payload());
+    }
+
+    #[test]
+    fn parse_sender_report_packet() {
+        let data: [u8; 52] = [
+            0x81, 0xc8, 0x00, 0x0c, 0x12, 0x34, 0x56, 0x78, 0xe5, 0x35,
+            0x2f, 0x10, 0x80, 0x00, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00,
+            0x00, 0x00, 0x00, 0x0a, 0x00, 0x00, 0x04, 0x00, 0x0a, 0x0b,
+            0x0c, 0x0d, 0x40, 0xff, 0xff, 0xfe, 0x00, 0x01, 0x00, 0x10,
+            0x00, 0x00, 0x00, 0x20, 0xb7, 0x05, 0x20, 0x00, 0x00, 0x05,
+            0x40, 0x00,
+        ];
+        let packet = RtcpPacket::from_slice(&data).unwrap();
+        let report = DataSR::from_packet(&packet).unwrap();
+        assert_eq!(0x12345678, report.ssrc());
+        assert_eq!(NtpTimestamp::new(0xe5352f1080000000), report.ntpts());
+        assert_eq!(0x10000, report.rtpts());
+        assert_eq!(10, report.packets());
+        assert_eq!(1024, report.octets());
+        assert_eq!(1, report.reports().len());
+        let block = &report.reports()[0];
+        assert_eq!(0x0a0b0c0d, block.ssrc());
+        assert_eq!(0x40, block.fraction_lost());
+        assert_eq!(0xfffffe, block.packets_lost());
+        assert_eq!(0x10010, block.highest_seq());
+        assert_eq!(0x20, block.jitter());
+        let arrival = NtpTimestamp::new(0x0000_b710_8000_0000);
+        assert_eq!(Some(Duration::from_millis(6125)), block.rtt(arrival));
+        assert_eq!(&data[..], &report.to_vec()[..]);
+    }
+
+    #[test]
+    fn parse_short_receiver_report_packet() {
+        let data: [u8; 12] = [
+            0x81, 0xc9, 0x00, 0x02, 0x12, 0x34, 0x56, 0x78, 0x0a, 0x0b,
+            0x0c, 0x0d,
+        ];
+        let packet = RtcpPacket::from_slice(&data).unwrap();
+        let error = DataRR::from_packet(&packet).unwrap_err();
+        assert!(matches!(error, RtcpError::PacketTooShort(1)))
+    }
+
+    #[test]
+    fn serialize_receiver_report() {
+        let reports = [SynSource::new(1, 2, 3, 4, 5, 6, 7), SynSource::new(8, 9, 10, 11, 12, 13, 14)];
+        let report = DataRR::new(0xCAFEBABE, &reports[..]);
+        let data = report.to_vec();
+        assert_eq!(56, data.len());
+        let packet = RtcpPacket::from_slice(&data).unwrap();
+        assert_eq!(2, packet.count());
+        assert_eq!(report, DataRR::from_packet(&packet).unwrap());
+    }
+
+    #[test]
+    fn parse_sdes_packet() {
+        let data: [u8; 28] = [
+            0x82, 0xca, 0x00, 0x06, 0x12, 0x34, 0x56, 0x78, 0x01, 0x05,
+            0x75, 0x40, 0x68, 0x6f, 0x73, 0x06, 0x01, 0x78, 0x00, 0x00,
+            0x0a, 0x0b, 0x0c, 0x0d, 0x01, 0x01, 0x61, 0x00,
+        ];
+        let packet = RtcpPacket::from_slice(&data).unwrap();
+        let sdes = DataSDES::from_packet(&packet).unwrap();
+        assert_eq!(2, sdes.chunks().len());
+        assert_eq!(0x12345678, sdes.chunks()[0].ssrc());
+        assert_eq!(Some(&b"u@host"[..5]), sdes.chunks()[0].item(DataSDES::CNAME));
+        assert_eq!(Some(&b"x"[..]), sdes.chunks()[0].item(DataSDES::TOOL));
+        assert_eq!(Some(&b"a"[..]), sdes.chunks()[1].item(DataSDES::CNAME));
+        assert_eq!(None, sdes.chunks()[1].item(DataSDES::NAME));
+    }
+
+    #[test]
+    fn serialize_sdes_packet() {
+        let sdes = DataSDES::new(vec![
+            SdesChunk::new(1, vec![(DataSDES::CNAME, b"user@example.com")]),
+            SdesChunk::new(2, vec![(DataSDES::CNAME, b"abc"), (DataSDES::NOTE, b"")]),
+        ]);
+        let data = sdes.to_vec();
+        assert_eq!(0, data.len() % 4);
+        let packet = RtcpPacket::from_slice(&data).unwrap();
+        assert_eq!(sdes, DataSDES::from_packet(&packet).unwrap());
+    }
+
+    #[test]
+    fn serialize_bye_packet() {
+        let bye = DataBYE::new(vec![0x12345678, 0x0a0b0c0d], Some(b"camera off"));
+        let data = bye.to_vec();
+        assert_eq!(24, data.len());
+        let packet = RtcpPacket::from_slice(&data).unwrap();
+        assert_eq!(bye, DataBYE::from_packet(&packet).unwrap());
+        let bye = DataBYE::new(vec![1], None);
+        let data = bye.to_vec();
+        assert_eq!(8, data.len());
+        let packet = RtcpPacket::from_slice(&data).unwrap();
+        assert_eq!(bye, DataBYE::from_packet(&packet).unwrap());
+    }
+
+    #[test]
+    fn parse_invalid_version_packet() {
+        let data: [u8; 8] = [
+            0x40, 0xc9, 0x00, 0x01, 0x12, 0x34, 0x56, 0x78,
+        ];
+        let error = RtcpPacket::from_slice(&data).unwrap_err();
+        assert!(matches!(error, RtcpError::InvalidVersion(1)))
+    }
+
+    #[test]
+    fn parse_compound_packet() {
+        let data: [u8; 20] = [
+            0x80, 0xc9, 0x00, 0x01, 0x12, 0x34, 0x56, 0x78, 0xa1, 0xcb,
+            0x00, 0x02, 0x12, 0x34, 0x56, 0x78, 0x00, 0x00, 0x00, 0x04,
+        ];
+        let packets = RtcpPacket::compound_from_slice(&data, RtcpMode::Compound).unwrap();
+        assert_eq!(2, packets.len());
+        assert_eq!(RtcpPacket::RR, packets[0].payload_type());
+        assert_eq!(RtcpPacket::BYE, packets[1].payload_type());
+        assert_eq!(1, packets[1].count());
+        assert_eq!(4, packets[1].payload().len());
+    }
+
+    #[test]
+    fn parse_truncated_compound_packet() {
+        let data: [u8; 16] = [
+            0x80, 0xc9, 0x00, 0x01, 0x12, 0x34, 0x56, 0x78, 0x81, 0xcb,
+            0x00, 0x02, 0x12, 0x34, 0x56, 0x78,
+        ];
+        let error = RtcpPacket::compound_from_slice(&data, RtcpMode::Compound).unwrap_err();
+        assert!(matches!(error, RtcpError::InvalidLen(12)))
+    }
+
+    #[test]
+    fn parse_inner_padding_compound_packet() {
+        let data: [u8; 16] = [
+            0xa0, 0xc9, 0x00, 0x01, 0x00, 0x00, 0x00, 0x04, 0x81, 0xcb,
+            0x00, 0x01, 0x12, 0x34, 0x56, 0x78,
+        ];
+        let error = RtcpPacket::compound_from_slice(&data, RtcpMode::Compound).unwrap_err();
+        assert!(matches!(error, RtcpError::InvalidPadding(8)))
+    }
+
+    #[test]
+    fn parse_reduced_size_packet() {
+        let data: [u8; 12] = [
+            0x81, 0xcd, 0x00, 0x02, 0x12, 0x34, 0x56, 0x78, 0x00, 0x00,
+            0x00, 0x01,
+        ];
+        let error = RtcpPacket::compound_from_slice(&data, RtcpMode::Compound).unwrap_err();
+        assert!(matches!(error, RtcpError::InvalidCompound(RtcpPacket::RTPFB)));
+        let packets = RtcpPacket::compound_from_slice(&data, RtcpMode::ReducedSize).unwrap();
+        assert_eq!(1, packets.len());
+        assert_eq!(RtcpPacket::RTPFB, packets[0].payload_type());
+    }
+}
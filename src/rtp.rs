@@ -32,7 +32,7 @@ pub struct RtpExtension<'a> {
 
 impl<'a> RtpPacket<'a> {
     // The size of the fixed part of the packet, up to and inclding SSRC.
-    const HEADER_SIZE: usize = 12;
+    pub(crate) const HEADER_SIZE: usize = 12;
     // Fixed RTP protocol version.
     const VERSION: u8 = 2;
 
@@ -43,21 +43,21 @@ impl<'a> RtpPacket<'a> {
         timestamp: u32,
         ssrc: u32,
         payload: &'a [u8],
-    ) -> RtpPacket<'_> {
+    ) -> RtpPacket<'a> {
         RtpPacket { 
             cc: 0u8, 
-            payload_type: payload_type, 
-            seq_number: seq_number, 
-            timestamp: timestamp, 
-            ssrc: ssrc, 
+            payload_type, 
+            seq_number, 
+            timestamp, 
+            ssrc, 
             csrc: [0u32; 15], 
             extension: None, 
-            payload: payload, 
-            mark: mark, 
+            payload, 
+            mark, 
         }
     }
 
-    pub fn from_slice(slice: &'a [u8]) -> Result<RtpPacket<'_>, RtpError> {
+    pub fn from_slice(slice: &'a [u8]) -> Result<RtpPacket<'a>, RtpError> {
         let slice_len = slice.len();
         if slice_len < RtpPacket::HEADER_SIZE {
             return Err(RtpError::InvalidLen(slice_len))
@@ -71,15 +71,14 @@ impl<'a> RtpPacket<'a> {
         let pad_flag = (slice[0] & 0x20) >> 5;  // 0 or 1
         let mut off = RtpPacket::HEADER_SIZE + (cc as usize) * 4;
 
-        for index in 0..cc as usize {
-            let csrc_off = off + (cc as usize) * 4;
-            csrc[index] = u32::from_be_bytes([slice[csrc_off], slice[csrc_off + 1], slice[csrc_off + 2], slice[csrc_off + 3]])
-        }
-
         // The following additional validation checks are declared as complex and not always possible in the RFC 1889.
         if off > slice_len {
             return Err(RtpError::InvalidCSRCCount(cc))
         }
+        for (index, item) in csrc.iter_mut().take(cc as usize).enumerate() {
+            let csrc_off = RtpPacket::HEADER_SIZE + index * 4;
+            *item = u32::from_be_bytes([slice[csrc_off], slice[csrc_off + 1], slice[csrc_off + 2], slice[csrc_off + 3]])
+        }
         let mut extension: Option<RtpExtension> = None;
         if (slice[0] & 0x10) != 0 {
             if (off + 4) > slice_len {
@@ -101,17 +100,146 @@ impl<'a> RtpPacket<'a> {
         }
 
         Ok(RtpPacket { 
-            cc: cc, 
+            cc, 
             payload_type: slice[1] & 0x7F, 
             seq_number: u16::from_be_bytes([slice[2], slice[3]]), 
             timestamp: u32::from_be_bytes([slice[4], slice[5], slice[6], slice[7]]), 
             ssrc: u32::from_be_bytes([slice[8], slice[9], slice[10], slice[11]]), 
-            csrc: csrc, 
-            extension: extension, 
+            csrc, 
+            extension, 
             payload: &slice[off..(slice_len - pad_len)], 
             mark: (slice[1] & 0x80) != 0, 
         })
     }
+
+    pub fn mark(&self) -> bool {
+        self.mark
+    }
+
+    pub fn payload_type(&self) -> u8 {
+        self.payload_type
+    }
+
+    pub fn seq_number(&self) -> u16 {
+        self.seq_number
+    }
+
+    pub fn timestamp(&self) -> u32 {
+        self.timestamp
+    }
+
+    pub fn ssrc(&self) -> u32 {
+        self.ssrc
+    }
+
+    pub fn csrc(&self) -> &[u32] {
+        &self.csrc[..self.cc as usize]
+    }
+
+    pub fn extension(&self) -> Option<&RtpExtension<'a>> {
+        self.extension.as_ref()
+    }
+
+    pub fn set_extension(&mut self, extension: Option<RtpExtension<'a>>) {
+        self.extension = extension;
+    }
+
+    pub fn payload(&self) -> &'a [u8] {
+        self.payload
+    }
+
+    // Serializes the packet without padding.  Extension data is zero padded to a multiple of 4 octets.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let ext_len = self.extension.as_ref().map_or(0, |ext| ext.data.len().div_ceil(4) * 4 + 4);
+        let mut buf = Vec::with_capacity(RtpPacket::HEADER_SIZE + (self.cc as usize) * 4 + ext_len + self.payload.len());
+        buf.push((RtpPacket::VERSION << 6) | ((self.extension.is_some() as u8) << 4) | self.cc);
+        buf.push(((self.mark as u8) << 7) | self.payload_type);
+        buf.extend_from_slice(&self.seq_number.to_be_bytes());
+        buf.extend_from_slice(&self.timestamp.to_be_bytes());
+        buf.extend_from_slice(&self.ssrc.to_be_bytes());
+        for csrc in self.csrc() {
+            buf.extend_from_slice(&csrc.to_be_bytes());
+        }
+        if let Some(ext) = &self.extension {
+            buf.extend_from_slice(&ext.head.to_be_bytes());
+            buf.extend_from_slice(&((ext_len / 4 - 1) as u16).to_be_bytes());
+            buf.extend_from_slice(ext.data);
+            buf.resize(buf.len() + (ext_len - 4 - ext.data.len()), 0);
+        }
+        buf.extend_from_slice(self.payload);
+        buf
+    }
+}
+
+impl<'a> RtpExtension<'a> {
+    // Profile marker of the one-byte header extension (RFC 8285).
+    pub const ONE_BYTE: u16 = 0xBEDE;
+    // Profile marker of the two-byte header extension (RFC 8285), lower 4 bits are application specific.
+    pub const TWO_BYTE: u16 = 0x1000;
+
+    pub fn new(head: u16, data: &'a [u8]) -> RtpExtension<'a> {
+        RtpExtension { head, data }
+    }
+
+    pub fn head(&self) -> u16 {
+        self.head
+    }
+
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    // Iterates over individual extension elements as (id, data) pairs.  Yields nothing for the extensions
+    // that do not follow one-byte or two-byte header format.
+    pub fn elements(&self) -> RtpExtensionElements<'a> {
+        let two_byte = (self.head & 0xFFF0) == RtpExtension::TWO_BYTE;
+        let data = if two_byte || self.head == RtpExtension::ONE_BYTE { self.data } else { &self.data[..0] };
+        RtpExtensionElements { data, two_byte }
+    }
+
+    // Returns data of the first extension element with given id.
+    pub fn element(&self, id: u8) -> Option<&'a [u8]> {
+        self.elements().find(|(elem_id, _)| *elem_id == id).map(|(_, data)| data)
+    }
+}
+
+pub struct RtpExtensionElements<'a> {
+    data: &'a [u8],
+    two_byte: bool,
+}
+
+impl<'a> Iterator for RtpExtensionElements<'a> {
+    type Item = (u8, &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // Padding bytes may appear between elements and are always zero.
+            let (&first, rest) = self.data.split_first()?;
+            if first == 0 {
+                self.data = rest;
+                continue
+            }
+            let (id, len, off) = if self.two_byte {
+                let (&len, _) = rest.split_first()?;
+                (first, len as usize, 2)
+            } else {
+                let id = first >> 4;
+                // Identifier 15 is reserved and stops processing of the one-byte header extension.
+                if id == 15 {
+                    self.data = &self.data[..0];
+                    return None
+                }
+                (id, (first & 0x0F) as usize + 1, 1)
+            };
+            if off + len > self.data.len() {
+                self.data = &self.data[..0];
+                return None
+            }
+            let elem = &self.data[off..off + len];
+            self.data = &self.data[off + len..];
+            return Some((id, elem))
+        }
+    }
 }
 
 impl<'a> fmt::Debug for RtpPacket<'a> {
@@ -156,15 +284,28 @@ impl RtpPacketizer {
     ) -> Self {
         let mut rng = rand::thread_rng();
         RtpPacketizer { 
-            mtu: mtu, 
-            payload_type: payload_type, 
+            mtu, 
+            payload_type, 
             seq_number: rng.gen::<u16>(), 
             timestamp: rng.gen::<u32>(), 
-            ssrc: ssrc, 
+            ssrc, 
         }
     }
 
-    pub fn packetize<'a>(&'a mut self, payload: &'a [u8], frames: u32) -> Vec<RtpPacket<'_>> {
+    pub fn payload_type(&self) -> u8 {
+        self.payload_type
+    }
+
+    pub fn ssrc(&self) -> u32 {
+        self.ssrc
+    }
+
+    // Changes SSRC of subsequent packets, e.g. after the session resolved a collision.
+    pub fn set_ssrc(&mut self, ssrc: u32) {
+        self.ssrc = ssrc;
+    }
+
+    pub fn packetize<'a>(&'a mut self, payload: &'a [u8], frames: u32) -> Vec<RtpPacket<'a>> {
         self.timestamp = self.timestamp.wrapping_add(frames);
         // If mtu is too large or too small, give it a reasonable size based on payload size and common sense.
         if self.mtu <= RtpPacket::HEADER_SIZE {
@@ -172,7 +313,8 @@ impl RtpPacketizer {
         }
         // At this point assume just a standard fixed header, no csrc, no extension.  Only the last chunk may require padding.
         let chunk_size = self.mtu - RtpPacket::HEADER_SIZE;
-        let chunk_count = payload.len() / chunk_size + 1;
+        // Empty payload still makes one packet, payload of exact chunk multiple no empty trailing one.
+        let chunk_count = payload.len().div_ceil(chunk_size).max(1);
         let mut packets = Vec::<RtpPacket>::with_capacity(chunk_count);
 
         for index in 0..chunk_count {
@@ -247,6 +389,19 @@ mod tests {
         assert_eq!(5, packet.payload.len());
     }
 
+    #[test]
+    fn serialize_basic_packet() {
+        let data: [u8; 25] = [
+            0x90, 0xe0, 0x69, 0x8f, 0xd9, 0xc2, 0x93, 0xda, 0x1c, 0x64,
+            0x27, 0x82, 0x00, 0x01, 0x00, 0x01, 0xFF, 0xFF, 0xFF, 0xFF,
+            0x98, 0x36, 0xbe, 0x88, 0x9e,
+        ];
+        let packet = RtpPacket::from_slice(&data).unwrap();
+        assert_eq!(&data[..], &packet.to_vec()[..]);
+        let packet = RtpPacket::new(false, 98, 1, 2, 3, &data[20..]);
+        assert_eq!(RtpPacket::from_slice(&packet.to_vec()).unwrap(), packet);
+    }
+
     #[test]
     fn parse_padded_packet() {
         let data: [u8; 25] = [
@@ -326,6 +481,49 @@ mod tests {
         }
     }
 
+    #[test]
+    fn parse_one_byte_extension_elements() {
+        let data: [u8; 28] = [
+            0x90, 0xe0, 0x69, 0x8f, 0xd9, 0xc2, 0x93, 0xda, 0x1c, 0x64,
+		    0x27, 0x82, 0xBE, 0xDE, 0x00, 0x02, 0x51, 0x01, 0x02, 0x00,
+		    0x00, 0x30, 0xAB, 0x00, 0x98, 0x36, 0xbe, 0x88,
+        ];
+        let packet = RtpPacket::from_slice(&data).unwrap();
+        let extension = packet.extension().unwrap();
+        let elements: Vec<(u8, &[u8])> = extension.elements().collect();
+        assert_eq!(2, elements.len());
+        assert_eq!((5, &[0x01u8, 0x02][..]), elements[0]);
+        assert_eq!((3, &[0xABu8][..]), elements[1]);
+        assert_eq!(Some(&[0xABu8][..]), extension.element(3));
+        assert_eq!(None, extension.element(4));
+    }
+
+    #[test]
+    fn parse_two_byte_extension_elements() {
+        let data: [u8; 24] = [
+            0x90, 0xe0, 0x69, 0x8f, 0xd9, 0xc2, 0x93, 0xda, 0x1c, 0x64,
+		    0x27, 0x82, 0x10, 0x00, 0x00, 0x02, 0x07, 0x00, 0x03, 0x03,
+		    0x01, 0x02, 0x03, 0x00,
+        ];
+        let packet = RtpPacket::from_slice(&data).unwrap();
+        let extension = packet.extension().unwrap();
+        let elements: Vec<(u8, &[u8])> = extension.elements().collect();
+        assert_eq!(2, elements.len());
+        assert_eq!((7, &[][..]), elements[0]);
+        assert_eq!((3, &[0x01u8, 0x02, 0x03][..]), elements[1]);
+    }
+
+    #[test]
+    fn parse_csrc_packet() {
+        let data: [u8; 20] = [
+            0x82, 0x60, 0x69, 0x8f, 0xd9, 0xc2, 0x93, 0xda, 0x1c, 0x64,
+		    0x27, 0x82, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x02,
+        ];
+        let packet = RtpPacket::from_slice(&data).unwrap();
+        assert_eq!(&[1u32, 2][..], &packet.csrc[..packet.cc as usize]);
+        assert_eq!(0, packet.payload.len());
+    }
+
     #[test]
     fn packetize_two_packets() {
         let data = [0u8; 128];
@@ -341,6 +539,19 @@ mod tests {
         assert_eq!(40, packet.payload.len());
     }
 
+    #[test]
+    fn packetize_exact_chunk_multiple() {
+        let mut packetizer = RtpPacketizer::new(100, 98, 0x1234ABCD);
+        let packets = packetizer.packetize(&[0u8; 176], 2000);
+        assert_eq!(vec![88, 88], packets.iter().map(|packet| packet.payload.len()).collect::<Vec<_>>());
+        assert!(!packets[0].mark && packets[1].mark);
+        let seq = packets[1].seq_number;
+        let packets = packetizer.packetize(&[], 2000);
+        assert_eq!(1, packets.len());
+        assert!(packets[0].mark);
+        assert_eq!(seq.wrapping_add(1), packets[0].seq_number);
+    }
+
     #[test]
     fn packetize_undersize_mtu() {
         let data = [0u8; 128];
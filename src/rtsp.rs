@@ -0,0 +1,713 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::interleaved::InterleavedFrame;
+use crate::tunnel::TunnelError;
+
+#[derive(Debug)]
+pub enum RtspError {
+    // Request or status line that cannot be parsed.
+    InvalidStartLine(String),
+    InvalidHeader(String),
+    InvalidVersion(String),
+    InvalidContentLength(String),
+    // Message head exceeds RtspParser::MAX_HEAD_SIZE without terminating empty line.
+    HeadTooLarge(usize),
+    // Content-Length exceeds RtspParser::MAX_BODY_SIZE.
+    BodyTooLarge(usize),
+    // Interleaved data does not fit 16 bit length field.
+    FrameTooLarge(usize),
+    // Broken RTSP over HTTP tunnel.
+    Tunnel(TunnelError),
+}
+
+impl From<TunnelError> for RtspError {
+    fn from(error: TunnelError) -> Self {
+        RtspError::Tunnel(error)
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Method {
+    Options,
+    Describe,
+    Announce,
+    Setup,
+    Play,
+    Pause,
+    Teardown,
+    GetParameter,
+    SetParameter,
+    Record,
+    Redirect,
+    // Server to client notification of RTSP 2.0 (RFC 7826 section 13.5).
+    PlayNotify,
+    Other(String),
+}
+
+impl Method {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Method::Options => "OPTIONS",
+            Method::Describe => "DESCRIBE",
+            Method::Announce => "ANNOUNCE",
+            Method::Setup => "SETUP",
+            Method::Play => "PLAY",
+            Method::Pause => "PAUSE",
+            Method::Teardown => "TEARDOWN",
+            Method::GetParameter => "GET_PARAMETER",
+            Method::SetParameter => "SET_PARAMETER",
+            Method::Record => "RECORD",
+            Method::Redirect => "REDIRECT",
+            Method::PlayNotify => "PLAY_NOTIFY",
+            Method::Other(method) => method,
+        }
+    }
+}
+
+impl FromStr for Method {
+    type Err = ();
+
+    // Methods are case-sensitive (RFC 2326 section 6.1).
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Ok(match text {
+            "OPTIONS" => Method::Options,
+            "DESCRIBE" => Method::Describe,
+            "ANNOUNCE" => Method::Announce,
+            "SETUP" => Method::Setup,
+            "PLAY" => Method::Play,
+            "PAUSE" => Method::Pause,
+            "TEARDOWN" => Method::Teardown,
+            "GET_PARAMETER" => Method::GetParameter,
+            "SET_PARAMETER" => Method::SetParameter,
+            "RECORD" => Method::Record,
+            "REDIRECT" => Method::Redirect,
+            "PLAY_NOTIFY" => Method::PlayNotify,
+            "" => return Err(()),
+            method => Method::Other(method.to_string()),
+        })
+    }
+}
+
+impl fmt::Display for Method {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        f.write_str(self.as_str())
+    }
+}
+
+// Protocol version, ordered so that the older version compares lower.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum RtspVersion {
+    // RFC 2326.
+    V1_0,
+    // RFC 7826, not backwards compatible with 1.0.
+    V2_0,
+}
+
+impl RtspVersion {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RtspVersion::V1_0 => "RTSP/1.0",
+            RtspVersion::V2_0 => "RTSP/2.0",
+        }
+    }
+}
+
+impl FromStr for RtspVersion {
+    type Err = RtspError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text {
+            "RTSP/1.0" => Ok(RtspVersion::V1_0),
+            "RTSP/2.0" => Ok(RtspVersion::V2_0),
+            version => Err(RtspError::InvalidVersion(version.to_string())),
+        }
+    }
+}
+
+// Headers keep order and spelling of names as received, lookups are case-insensitive.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Headers {
+    entries: Vec<(String, String)>,
+}
+
+impl Headers {
+    pub const ACCEPT: &'static str = "Accept";
+    pub const ACCEPT_RANGES: &'static str = "Accept-Ranges";
+    pub const AUTHORIZATION: &'static str = "Authorization";
+    pub const CONTENT_BASE: &'static str = "Content-Base";
+    pub const CONTENT_LENGTH: &'static str = "Content-Length";
+    pub const CONTENT_LOCATION: &'static str = "Content-Location";
+    pub const CONTENT_TYPE: &'static str = "Content-Type";
+    pub const CSEQ: &'static str = "CSeq";
+    pub const IMMEDIATE: &'static str = "Immediate";
+    pub const MEDIA_PROPERTIES: &'static str = "Media-Properties";
+    pub const NOTIFY_REASON: &'static str = "Notify-Reason";
+    pub const PUBLIC: &'static str = "Public";
+    pub const RANGE: &'static str = "Range";
+    pub const RATE_CONTROL: &'static str = "Rate-Control";
+    pub const REQUIRE: &'static str = "Require";
+    pub const RTP_INFO: &'static str = "RTP-Info";
+    pub const SCALE: &'static str = "Scale";
+    pub const SESSION: &'static str = "Session";
+    pub const TRANSPORT: &'static str = "Transport";
+    pub const UNSUPPORTED: &'static str = "Unsupported";
+    pub const USER_AGENT: &'static str = "User-Agent";
+    pub const WWW_AUTHENTICATE: &'static str = "WWW-Authenticate";
+
+    pub fn new() -> Headers {
+        Headers::default()
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.entries.iter().find(|(key, _)| key.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+    }
+
+    pub fn get_all<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.entries.iter().filter(move |(key, _)| key.eq_ignore_ascii_case(name)).map(|(_, value)| value.as_str())
+    }
+
+    // Replaces all values of the header with a single one.
+    pub fn set(&mut self, name: &str, value: impl fmt::Display) {
+        match self.entries.iter().position(|(key, _)| key.eq_ignore_ascii_case(name)) {
+            Some(index) => {
+                self.entries[index].1 = value.to_string();
+                let mut seen = 0;
+                self.entries.retain(|(key, _)| !key.eq_ignore_ascii_case(name) || { seen += 1; seen == 1 });
+            },
+            None => self.append(name, value),
+        }
+    }
+
+    pub fn append(&mut self, name: &str, value: impl fmt::Display) {
+        self.entries.push((name.to_string(), value.to_string()));
+    }
+
+    pub fn remove(&mut self, name: &str) {
+        self.entries.retain(|(key, _)| !key.eq_ignore_ascii_case(name));
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.entries.iter().map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // CSeq number, missing or malformed header yields None.
+    pub fn cseq(&self) -> Option<u32> {
+        self.get(Headers::CSEQ).and_then(|value| value.trim().parse().ok())
+    }
+
+    fn write(&self, buf: &mut Vec<u8>, body: &[u8]) {
+        for (key, value) in &self.entries {
+            if !key.eq_ignore_ascii_case(Headers::CONTENT_LENGTH) {
+                buf.extend_from_slice(format!("{}: {}\r\n", key, value).as_bytes());
+            }
+        }
+        if !body.is_empty() {
+            buf.extend_from_slice(format!("{}: {}\r\n", Headers::CONTENT_LENGTH, body.len()).as_bytes());
+        }
+        buf.extend_from_slice(b"\r\n");
+        buf.extend_from_slice(body);
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Request {
+    pub method: Method,
+    pub uri: String,
+    pub version: RtspVersion,
+    pub headers: Headers,
+    pub body: Vec<u8>,
+}
+
+impl Request {
+    pub fn new(method: Method, uri: impl Into<String>, cseq: u32) -> Request {
+        let mut headers = Headers::new();
+        headers.set(Headers::CSEQ, cseq);
+        Request { method, uri: uri.into(), version: RtspVersion::V1_0, headers, body: Vec::new() }
+    }
+
+    pub fn cseq(&self) -> Option<u32> {
+        self.headers.cseq()
+    }
+
+    // Serializes the request, Content-Length is derived from the body.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut buf = format!("{} {} {}\r\n", self.method, self.uri, self.version.as_str()).into_bytes();
+        self.headers.write(&mut buf, &self.body);
+        buf
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Response {
+    pub version: RtspVersion,
+    pub status: u16,
+    pub reason: String,
+    pub headers: Headers,
+    pub body: Vec<u8>,
+}
+
+impl Response {
+    pub const OK: u16 = 200;
+    pub const MOVED_PERMANENTLY: u16 = 301;
+    pub const MOVED_TEMPORARILY: u16 = 302;
+    pub const BAD_REQUEST: u16 = 400;
+    pub const UNAUTHORIZED: u16 = 401;
+    pub const FORBIDDEN: u16 = 403;
+    pub const NOT_FOUND: u16 = 404;
+    pub const METHOD_NOT_ALLOWED: u16 = 405;
+    pub const UNSUPPORTED_MEDIA_TYPE: u16 = 415;
+    pub const SESSION_NOT_FOUND: u16 = 454;
+    pub const METHOD_NOT_VALID_IN_THIS_STATE: u16 = 455;
+    pub const AGGREGATE_OPERATION_NOT_ALLOWED: u16 = 459;
+    pub const UNSUPPORTED_TRANSPORT: u16 = 461;
+    pub const INTERNAL_SERVER_ERROR: u16 = 500;
+    pub const NOT_IMPLEMENTED: u16 = 501;
+    pub const VERSION_NOT_SUPPORTED: u16 = 505;
+    pub const OPTION_NOT_SUPPORTED: u16 = 551;
+
+    // Response echoing CSeq of the request, reason phrase is taken from RFC 2326 section 7.1.1.
+    pub fn new(status: u16, cseq: Option<u32>) -> Response {
+        let mut headers = Headers::new();
+        if let Some(cseq) = cseq {
+            headers.set(Headers::CSEQ, cseq);
+        }
+        Response { version: RtspVersion::V1_0, status, reason: Response::reason_phrase(status).to_string(), headers, body: Vec::new() }
+    }
+
+    pub fn reason_phrase(status: u16) -> &'static str {
+        match status {
+            100 => "Continue",
+            200 => "OK",
+            201 => "Created",
+            301 => "Moved Permanently",
+            302 => "Moved Temporarily",
+            304 => "Not Modified",
+            400 => "Bad Request",
+            401 => "Unauthorized",
+            403 => "Forbidden",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            406 => "Not Acceptable",
+            408 => "Request Timeout",
+            415 => "Unsupported Media Type",
+            451 => "Parameter Not Understood",
+            453 => "Not Enough Bandwidth",
+            454 => "Session Not Found",
+            455 => "Method Not Valid in This State",
+            457 => "Invalid Range",
+            459 => "Aggregate operation not allowed",
+            460 => "Only aggregate operation allowed",
+            461 => "Unsupported transport",
+            500 => "Internal Server Error",
+            501 => "Not Implemented",
+            503 => "Service Unavailable",
+            505 => "RTSP Version not supported",
+            551 => "Option not supported",
+            _ => "",
+        }
+    }
+
+    pub fn cseq(&self) -> Option<u32> {
+        self.headers.cseq()
+    }
+
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut buf = format!("{} {} {}\r\n", self.version.as_str(), self.status, self.reason).into_bytes();
+        self.headers.write(&mut buf, &self.body);
+        buf
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Message {
+    Request(Request),
+    Response(Response),
+}
+
+impl Message {
+    pub fn headers(&self) -> &Headers {
+        match self {
+            Message::Request(request) => &request.headers,
+            Message::Response(response) => &response.headers,
+        }
+    }
+
+    pub fn body(&self) -> &[u8] {
+        match self {
+            Message::Request(request) => &request.body,
+            Message::Response(response) => &response.body,
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        match self {
+            Message::Request(request) => request.to_vec(),
+            Message::Response(response) => response.to_vec(),
+        }
+    }
+}
+
+// Item of an RTSP connection: a text message or binary data interleaved with messages (RFC 2326 section 10.12).
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum RtspItem {
+    Message(Message),
+    Interleaved(InterleavedFrame),
+}
+
+// RtspParser splits a byte stream of an RTSP connection into items.  Data is pushed as it arrives, partial
+// messages stay buffered until complete:
+//
+//   $ <channel: 1 byte> <length: 2 bytes> <data>   interleaved binary data
+//   <start line> CRLF *(<header> CRLF) CRLF [body]  request or response, body sized by Content-Length
+//
+// Lines may end with bare LF and header values may be folded onto lines starting with whitespace.  Some
+// firmware emits stray bytes between items, data that can start neither is discarded.  A message with a
+// broken head is discarded too, together with its body when Content-Length can be read, so the items after
+// it are still returned once its error has been reported.
+#[derive(Default)]
+pub struct RtspParser {
+    buf: Vec<u8>,
+    discarded: usize,
+    // Bytes of a rejected message body still to be dropped as they arrive.
+    skip: usize,
+    // Parsed head of the message whose body is not complete yet.
+    head: Option<MessageHead>,
+}
+
+struct MessageHead {
+    len: usize,
+    start: String,
+    headers: Headers,
+    body_len: usize,
+}
+
+impl RtspParser {
+    pub const MAX_HEAD_SIZE: usize = 65536;
+    pub const MAX_BODY_SIZE: usize = 1 << 20;
+
+    pub fn new() -> RtspParser {
+        RtspParser::default()
+    }
+
+    pub fn push(&mut self, data: &[u8]) {
+        self.buf.extend_from_slice(data);
+    }
+
+    // Bytes received but not yet returned as an item.
+    pub fn buffered(&self) -> usize {
+        self.buf.len()
+    }
+
+    // Bytes skipped so far because they could not start an item.
+    pub fn discarded(&self) -> usize {
+        self.discarded
+    }
+
+    // Returns next complete item, None if more data is needed.
+    pub fn poll_item(&mut self) -> Result<Option<RtspItem>, RtspError> {
+        if self.skip > 0 {
+            let skip = self.skip.min(self.buf.len());
+            self.buf.drain(..skip);
+            self.discarded += skip;
+            self.skip -= skip;
+            if self.skip > 0 {
+                return Ok(None)
+            }
+        }
+        if self.head.is_none() {
+            self.resync();
+        }
+        if self.head.is_none() && self.buf.first() == Some(&InterleavedFrame::MAGIC) {
+            if self.buf.len() < InterleavedFrame::HEADER_SIZE {
+                return Ok(None)
+            }
+            let len = InterleavedFrame::HEADER_SIZE + u16::from_be_bytes([self.buf[2], self.buf[3]]) as usize;
+            if self.buf.len() < len {
+                return Ok(None)
+            }
+            let channel = self.buf[1];
+            let data = self.buf[InterleavedFrame::HEADER_SIZE..len].to_vec();
+            self.buf.drain(..len);
+            return Ok(Some(RtspItem::Interleaved(InterleavedFrame::new(channel, data)?)))
+        }
+        let head = match self.head.take() {
+            Some(head) => head,
+            None => match self.poll_head()? {
+                Some(head) => head,
+                None => return Ok(None),
+            },
+        };
+        if self.buf.len() < head.len + head.body_len {
+            self.head = Some(head);
+            return Ok(None)
+        }
+        let MessageHead { len: head_len, start, headers, body_len } = head;
+        let body = self.buf[head_len..head_len + body_len].to_vec();
+        self.buf.drain(..head_len + body_len);
+        let message = match start.strip_prefix("RTSP/") {
+            Some(_) => {
+                let mut fields = start.splitn(3, ' ');
+                let version = fields.next().unwrap_or_default().parse()?;
+                let status = fields.next()
+                    .and_then(|status| status.parse().ok())
+                    .ok_or_else(|| RtspError::InvalidStartLine(start.to_string()))?;
+                let reason = fields.next().unwrap_or_default().to_string();
+                Message::Response(Response { version, status, reason, headers, body })
+            },
+            None => {
+                let fields: Vec<&str> = start.split_ascii_whitespace().collect();
+                let [method, uri, version] = fields[..] else {
+                    return Err(RtspError::InvalidStartLine(start.to_string()))
+                };
+                let method = method.parse().map_err(|_| RtspError::InvalidStartLine(start.to_string()))?;
+                Message::Request(Request { method, uri: uri.to_string(), version: version.parse()?, headers, body })
+            },
+        };
+        Ok(Some(RtspItem::Message(message)))
+    }
+
+    // Parses the head at the start of the buffer.  A broken head is dropped, a body of known length is dropped
+    // as it arrives.
+    fn poll_head(&mut self) -> Result<Option<MessageHead>, RtspError> {
+        let Some(len) = RtspParser::head_len(&self.buf) else {
+            if self.buf.len() > RtspParser::MAX_HEAD_SIZE {
+                let len = self.buf.len();
+                self.buf.clear();
+                self.discarded += len;
+                return Err(RtspError::HeadTooLarge(len))
+            }
+            return Ok(None)
+        };
+        let text = String::from_utf8_lossy(&self.buf[..len]).into_owned();
+        let head = RtspParser::parse_head(&text).and_then(|(start, headers)| {
+            let body_len = match headers.get(Headers::CONTENT_LENGTH) {
+                Some(value) => value.trim().parse::<usize>().map_err(|_| RtspError::InvalidContentLength(value.to_string()))?,
+                None => 0,
+            };
+            if body_len > RtspParser::MAX_BODY_SIZE {
+                self.skip = body_len;
+                return Err(RtspError::BodyTooLarge(body_len))
+            }
+            Ok(MessageHead { len, start, headers, body_len })
+        });
+        if head.is_err() {
+            self.buf.drain(..len);
+            self.discarded += len;
+        }
+        head.map(Some)
+    }
+
+    // Drops empty lines, which are allowed between messages, and bytes that cannot start an item.
+    fn resync(&mut self) {
+        loop {
+            let blank = self.buf.iter().take_while(|byte| matches!(byte, b'\r' | b'\n')).count();
+            self.buf.drain(..blank);
+            if self.buf.is_empty() || self.buf[0] == InterleavedFrame::MAGIC || RtspParser::may_start_message(&self.buf) {
+                return
+            }
+            let skip = 1 + self.buf[1..].iter()
+                .position(|byte| matches!(byte, b'$' | b'A'..=b'Z' | b'\r' | b'\n'))
+                .unwrap_or(self.buf.len() - 1);
+            self.buf.drain(..skip);
+            self.discarded += skip;
+        }
+    }
+
+    // Checks whether the data is, or may become once complete, a method or version token of a start line.
+    fn may_start_message(buf: &[u8]) -> bool {
+        const MAX_TOKEN: usize = 32;
+        const VERSION: &[u8] = b"RTSP/";
+        let token = match buf.iter().position(|byte| *byte == b' ') {
+            Some(len) => &buf[..len],
+            None if buf.len() > MAX_TOKEN => return false,
+            None => buf,
+        };
+        !token.is_empty() && (token.starts_with(VERSION) || VERSION.starts_with(token)
+            || token.iter().all(|byte| byte.is_ascii_uppercase() || *byte == b'_' || *byte == b'-'))
+    }
+
+    // Length of the head including terminating empty line.
+    pub(crate) fn head_len(buf: &[u8]) -> Option<usize> {
+        let mut previous = None;
+        for (index, byte) in buf.iter().enumerate() {
+            if *byte == b'\n' {
+                if let Some(previous) = previous {
+                    let between = &buf[previous + 1..index];
+                    if between.is_empty() || between == b"\r" {
+                        return Some(index + 1)
+                    }
+                }
+                previous = Some(index);
+            }
+        }
+        None
+    }
+
+    pub(crate) fn parse_head(head: &str) -> Result<(String, Headers), RtspError> {
+        let mut lines = head.lines().filter(|line| !line.is_empty());
+        let start = lines.next().unwrap_or_default().trim().to_string();
+        let mut headers = Headers::new();
+        for line in lines {
+            if line.starts_with([' ', '\t']) {
+                let (_, value) = headers.entries.last_mut().ok_or_else(|| RtspError::InvalidHeader(line.to_string()))?;
+                value.push(' ');
+                value.push_str(line.trim());
+                continue
+            }
+            let (name, value) = line.split_once(':').ok_or_else(|| RtspError::InvalidHeader(line.to_string()))?;
+            if name.trim().is_empty() {
+                return Err(RtspError::InvalidHeader(line.to_string()))
+            }
+            headers.append(name.trim(), value.trim());
+        }
+        Ok((start, headers))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_request_in_pieces() {
+        let data = b"DESCRIBE rtsp://192.168.1.64/Streaming/Channels/101 RTSP/1.0\r\n\
+            cseq: 2\r\n\
+            Accept: application/sdp\r\n\
+            User-Agent: test\r\n\r\n";
+        let mut parser = RtspParser::new();
+        for chunk in data.chunks(7) {
+            assert!(parser.poll_item().unwrap().is_none());
+            parser.push(chunk);
+        }
+        let Some(RtspItem::Message(Message::Request(request))) = parser.poll_item().unwrap() else { panic!() };
+        assert_eq!(Method::Describe, request.method);
+        assert_eq!("rtsp://192.168.1.64/Streaming/Channels/101", request.uri);
+        assert_eq!(Some(2), request.cseq());
+        assert_eq!(Some("application/sdp"), request.headers.get("accept"));
+        assert_eq!(0, parser.buffered());
+        assert_eq!(&data[..], &request.to_vec()[..]);
+    }
+
+    #[test]
+    fn parse_response_with_body_and_interleaved_data() {
+        let mut data = b"RTSP/1.0 200 OK\n\
+            CSeq: 3\n\
+            Content-Type: application/sdp\n\
+            X-Folded: first\n \t second\n\
+            Content-Length: 10\n\n\
+            v=0\r\ns=-\r\n".to_vec();
+        data.extend_from_slice(&[b'$', 1, 0, 3, 0x81, 0xc9, 0x00, b'$', 0]);
+        let mut parser = RtspParser::new();
+        parser.push(&data);
+        let Some(RtspItem::Message(Message::Response(response))) = parser.poll_item().unwrap() else { panic!() };
+        assert_eq!(200, response.status);
+        assert_eq!("OK", response.reason);
+        assert!(response.is_success());
+        assert_eq!(Some(3), response.cseq());
+        assert_eq!(Some("first second"), response.headers.get("x-folded"));
+        assert_eq!(b"v=0\r\ns=-\r\n", &response.body[..]);
+        let frame = InterleavedFrame::new(1, vec![0x81, 0xc9, 0x00]).unwrap();
+        assert_eq!(Some(RtspItem::Interleaved(frame)), parser.poll_item().unwrap());
+        assert_eq!(None, parser.poll_item().unwrap());
+        assert_eq!(2, parser.buffered());
+    }
+
+    #[test]
+    fn serialize_response() {
+        let mut response = Response::new(Response::SESSION_NOT_FOUND, Some(7));
+        response.headers.set(Headers::SESSION, "12345678");
+        response.body = b"error".to_vec();
+        assert_eq!(&b"RTSP/1.0 454 Session Not Found\r\nCSeq: 7\r\nSession: 12345678\r\nContent-Length: 5\r\n\r\nerror"[..], &response.to_vec()[..]);
+        response.headers.append("Session", "other");
+        response.headers.set("session", "1");
+        assert_eq!(vec!["1"], response.headers.get_all(Headers::SESSION).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn parse_rtsp_2_0_messages() {
+        let mut parser = RtspParser::new();
+        parser.push(b"PLAY_NOTIFY rtsp://example.com/fizzle/foo RTSP/2.0\r\nCSeq: 854\r\nNotify-Reason: end-of-stream\r\n\r\n");
+        parser.push(b"RTSP/2.0 200 OK\r\nCSeq: 2\r\nAccept-Ranges: npt, clock\r\n\r\n");
+        let Some(RtspItem::Message(Message::Request(request))) = parser.poll_item().unwrap() else { panic!() };
+        assert_eq!(Method::PlayNotify, request.method);
+        assert_eq!(RtspVersion::V2_0, request.version);
+        assert_eq!(Some("end-of-stream"), request.headers.get(Headers::NOTIFY_REASON));
+        let Some(RtspItem::Message(Message::Response(mut response))) = parser.poll_item().unwrap() else { panic!() };
+        assert_eq!(RtspVersion::V2_0, response.version);
+        assert!(RtspVersion::V1_0 < response.version);
+        response.headers.remove(Headers::ACCEPT_RANGES);
+        assert_eq!(&b"RTSP/2.0 200 OK\r\nCSeq: 2\r\n\r\n"[..], &response.to_vec()[..]);
+    }
+
+    #[test]
+    fn reject_malformed_messages() {
+        let mut parser = RtspParser::new();
+        parser.push(b"PLAY rtsp://host RTSP/2.5\r\nCSeq: 1\r\n\r\n");
+        assert!(matches!(parser.poll_item().unwrap_err(), RtspError::InvalidVersion(version) if version == "RTSP/2.5"));
+        let mut parser = RtspParser::new();
+        parser.push(b"RTSP/1.0 200 OK\r\nContent-Length: x\r\n\r\n");
+        assert!(matches!(parser.poll_item().unwrap_err(), RtspError::InvalidContentLength(_)));
+        let mut parser = RtspParser::new();
+        parser.push(b"RTSP/1.0 200 OK\r\nbroken\r\n\r\n");
+        assert!(matches!(parser.poll_item().unwrap_err(), RtspError::InvalidHeader(_)));
+        let mut parser = RtspParser::new();
+        parser.push(b"OPTIONS * RTSP/1.0\r\nX-Long: ");
+        parser.push(&vec![b'a'; RtspParser::MAX_HEAD_SIZE]);
+        assert!(matches!(parser.poll_item().unwrap_err(), RtspError::HeadTooLarge(_)));
+    }
+
+    #[test]
+    fn skip_broken_messages() {
+        let mut parser = RtspParser::new();
+        parser.push(b"RTSP/1.0 200 OK\r\nbroken\r\n\r\nRTSP/1.0 200 OK\r\nCSeq: 2\r\n\r\n");
+        assert!(matches!(parser.poll_item().unwrap_err(), RtspError::InvalidHeader(_)));
+        let Some(RtspItem::Message(Message::Response(response))) = parser.poll_item().unwrap() else { panic!() };
+        assert_eq!(Some(2), response.cseq());
+
+        parser.push(b"ANNOUNCE rtsp://server/live RTSP/1.0\r\nCSeq: 3\r\nContent-Length: 2000000\r\n\r\n");
+        parser.push(&[b'R'; 1_000_000]);
+        assert!(matches!(parser.poll_item().unwrap_err(), RtspError::BodyTooLarge(2_000_000)));
+        assert!(matches!(parser.poll_item(), Ok(None)));
+        parser.push(&[b'R'; 1_000_000]);
+        parser.push(b"OPTIONS * RTSP/1.0\r\nCSeq: 4\r\n\r\n");
+        let Some(RtspItem::Message(Message::Request(request))) = parser.poll_item().unwrap() else { panic!() };
+        assert_eq!(Some(4), request.cseq());
+        assert!(parser.discarded() > 2_000_000);
+    }
+
+    #[test]
+    fn wait_for_body() {
+        let mut parser = RtspParser::new();
+        parser.push(b"RTSP/1.0 200 OK\r\nCSeq: 1\r\nContent-Length: 4\r\n\r\nv=");
+        assert!(matches!(parser.poll_item(), Ok(None)));
+        assert!(parser.head.is_some());
+        parser.push(b"0\n$");
+        let Some(RtspItem::Message(Message::Response(response))) = parser.poll_item().unwrap() else { panic!() };
+        assert_eq!(b"v=0\n", &response.body[..]);
+        assert!(matches!(parser.poll_item(), Ok(None)));
+        assert_eq!(1, parser.buffered());
+    }
+
+    #[test]
+    fn limit_body_size() {
+        let mut parser = RtspParser::new();
+        parser.push(format!("RTSP/1.0 200 OK\r\nContent-Length: {}\r\n\r\n", RtspParser::MAX_BODY_SIZE).as_bytes());
+        assert!(matches!(parser.poll_item(), Ok(None)));
+        let mut parser = RtspParser::new();
+        parser.push(b"ANNOUNCE rtsp://server/live RTSP/1.0\r\nCSeq: 1\r\nContent-Length: 1048577\r\n\r\nv=0");
+        assert!(matches!(parser.poll_item().unwrap_err(), RtspError::BodyTooLarge(1048577)));
+    }
+}
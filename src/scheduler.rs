@@ -0,0 +1,394 @@
+use std::time::{Duration, Instant};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+
+// RtcpTimeout tells what kind of RTCP packet is due when the transmission timer expires.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RtcpTimeout {
+    Report,
+    Bye,
+}
+
+// RtcpScheduler computes RTCP transmission times per RFC 3550 section 6.3 and appendix A.7.  It does not
+// perform any I/O: the caller reads the next deadline with poll_timeout(), calls handle_timeout() once the
+// deadline is reached and sends the compound packet it is asked for, then reports its size with sent().
+pub struct RtcpScheduler {
+    bandwidth: f64,       // RTCP bandwidth in octets per second
+    reduced_min: Option<Duration>,
+    members: usize,
+    pmembers: usize,
+    senders: usize,
+    avg_rtcp_size: f64,
+    we_sent: bool,
+    last_media: Option<Instant>,
+    // Times of the last two reports sent, the latest first.
+    reports: [Option<Instant>; 2],
+    initial: bool,
+    leaving: bool,
+    tp: Instant,
+    tn: Instant,
+    rng: StdRng,
+}
+
+impl RtcpScheduler {
+    // Minimum interval between reports.
+    pub const MIN_TIME: Duration = Duration::from_secs(5);
+    // Fraction of session bandwidth allocated to RTCP.
+    const RTCP_FRACTION: f64 = 0.05;
+    // Fraction of RTCP bandwidth shared by active senders.
+    const SENDER_FRACTION: f64 = 0.25;
+    // Compensation for timer reconsideration converging to a value below the average, e - 3/2.
+    const COMPENSATION: f64 = std::f64::consts::E - 1.5;
+    // Size of IPv4 and UDP headers counted towards average RTCP packet size.
+    const HEADER_OVERHEAD: usize = 28;
+    // BYE reconsideration is only used in sessions with more members than this.
+    const BYE_THRESHOLD: usize = 50;
+
+    // Creates scheduler for a session with given bandwidth in bits per second and schedules the first report.
+    pub fn new(session_bandwidth: u64, now: Instant) -> Self {
+        let mut scheduler = RtcpScheduler {
+            bandwidth: session_bandwidth as f64 / 8.0 * RtcpScheduler::RTCP_FRACTION,
+            reduced_min: None,
+            members: 1,
+            pmembers: 1,
+            senders: 0,
+            avg_rtcp_size: 128.0,
+            we_sent: false,
+            last_media: None,
+            reports: [None; 2],
+            initial: true,
+            leaving: false,
+            tp: now,
+            tn: now,
+            rng: StdRng::from_entropy(),
+        };
+        scheduler.tn = now + scheduler.randomized_interval();
+        scheduler
+    }
+
+    // Seeds random generator used for randomization of intervals to make the schedule reproducible.
+    pub fn reseed(&mut self, seed: u64) {
+        self.rng = StdRng::seed_from_u64(seed);
+    }
+
+    // Enables reduced minimum interval of 360 seconds divided by session bandwidth in kilobits per second
+    // (RFC 3550 section 6.2).  The minimum still applies to the first report.
+    pub fn set_reduced_minimum(&mut self, enabled: bool) {
+        let kbps = self.bandwidth / RtcpScheduler::RTCP_FRACTION * 8.0 / 1000.0;
+        self.reduced_min = (enabled && kbps > 0.0).then(|| Duration::from_secs_f64(360.0 / kbps).min(RtcpScheduler::MIN_TIME));
+    }
+
+    // Records that this participant sent RTP.
+    pub fn media_sent(&mut self, now: Instant) {
+        self.last_media = Some(now);
+        self.we_sent = true;
+    }
+
+    // Whether RTP was sent since the second previous report, the next report is then SR and RR otherwise
+    // (RFC 3550 section 6.4).
+    pub fn we_sent(&self) -> bool {
+        self.we_sent
+    }
+
+    // Updates the number of members and senders in the session including this participant.  When the number of
+    // members goes down, e.g. on BYE or timeout, reverse reconsideration moves next transmission closer so the
+    // report rate does not drop (RFC 3550 section 6.3.4).
+    pub fn update_members(&mut self, now: Instant, members: usize, senders: usize) {
+        let members = members.max(1);
+        self.senders = senders.min(members);
+        if self.leaving {
+            return
+        }
+        if members < self.pmembers {
+            let ratio = members as f64 / self.pmembers as f64;
+            if self.tn > now {
+                self.tn = now + (self.tn - now).mul_f64(ratio);
+            }
+            if now > self.tp {
+                self.tp = now - (now - self.tp).mul_f64(ratio);
+            }
+            self.pmembers = members;
+        }
+        self.members = members;
+    }
+
+    // Accounts for the size of a received compound RTCP packet, excluding IP and UDP headers.
+    pub fn received(&mut self, size: usize) {
+        if !self.leaving {
+            self.update_avg_size(size);
+        }
+    }
+
+    // Deterministic reporting interval Td, used also to time out inactive members.
+    pub fn deterministic_interval(&self) -> Duration {
+        let mut min = match self.reduced_min {
+            Some(min) if !self.initial => min,
+            _ => RtcpScheduler::MIN_TIME,
+        };
+        if self.initial {
+            min /= 2;
+        }
+        let mut bandwidth = self.bandwidth;
+        let mut n = self.members as f64;
+        if self.senders as f64 <= self.members as f64 * RtcpScheduler::SENDER_FRACTION {
+            if self.we_sent {
+                bandwidth *= RtcpScheduler::SENDER_FRACTION;
+                n = self.senders as f64;
+            } else {
+                bandwidth *= 1.0 - RtcpScheduler::SENDER_FRACTION;
+                n -= self.senders as f64;
+            }
+        }
+        if bandwidth <= 0.0 {
+            return min
+        }
+        Duration::from_secs_f64(self.avg_rtcp_size * n / bandwidth).max(min)
+    }
+
+    // Calculated interval T: deterministic interval randomized to [0.5, 1.5] and divided by compensation factor.
+    fn randomized_interval(&mut self) -> Duration {
+        let factor = self.rng.gen_range(0.5..1.5);
+        self.deterministic_interval().mul_f64(factor / RtcpScheduler::COMPENSATION)
+    }
+
+    // Time the next RTCP packet is due.
+    pub fn poll_timeout(&self) -> Instant {
+        self.tn
+    }
+
+    // Applies timer reconsideration when the deadline is reached.  Returns the kind of packet to send now,
+    // or None if the transmission is rescheduled because group size changed.
+    pub fn handle_timeout(&mut self, now: Instant) -> Option<RtcpTimeout> {
+        if now < self.tn {
+            return None
+        }
+        let tn = self.tp + self.randomized_interval();
+        let result = if tn <= now {
+            self.tp = now;
+            self.initial = false;
+            self.tn = now + self.randomized_interval();
+            Some(if self.leaving { RtcpTimeout::Bye } else { RtcpTimeout::Report })
+        } else {
+            self.tn = tn;
+            None
+        };
+        self.pmembers = self.members;
+        result
+    }
+
+    // Accounts for the size of the compound RTCP packet just sent, excluding IP and UDP headers, and
+    // reschedules the next report with updated average.
+    pub fn sent(&mut self, now: Instant, size: usize) {
+        self.update_avg_size(size);
+        self.reports = [Some(now), self.reports[0]];
+        self.we_sent = self.last_media.is_some_and(|media| self.reports[1].is_none_or(|report| media >= report));
+        self.tp = now;
+        self.initial = false;
+        self.tn = now + self.randomized_interval();
+        self.pmembers = self.members;
+    }
+
+    // Starts leaving the session.  Returns true when BYE may be sent immediately, otherwise BYE is delayed by
+    // BYE reconsideration (RFC 3550 section 6.3.7) and handle_timeout() returns RtcpTimeout::Bye when due.
+    pub fn leave(&mut self, now: Instant) -> bool {
+        self.leaving = true;
+        if self.members <= RtcpScheduler::BYE_THRESHOLD {
+            return true
+        }
+        self.tp = now;
+        self.members = 1;
+        self.pmembers = 1;
+        self.senders = 0;
+        self.we_sent = false;
+        self.initial = true;
+        self.avg_rtcp_size = 128.0;
+        self.tn = now + self.randomized_interval();
+        false
+    }
+
+    // Counts a BYE received from another member while leaving towards the group size used for BYE timing.
+    pub fn bye_received(&mut self, size: usize) {
+        if self.leaving {
+            self.members += 1;
+            self.update_avg_size(size);
+        }
+    }
+
+    fn update_avg_size(&mut self, size: usize) {
+        let size = (size + RtcpScheduler::HEADER_OVERHEAD) as f64;
+        self.avg_rtcp_size += (size - self.avg_rtcp_size) / 16.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::OnceLock;
+
+    // Fixed base of the test clocks, times are explicit offsets from it.
+    fn base() -> Instant {
+        static BASE: OnceLock<Instant> = OnceLock::new();
+        *BASE.get_or_init(Instant::now)
+    }
+
+    // Scheduler with seeded random generator, including the first report time.
+    fn scheduler(session_bandwidth: u64, seed: u64) -> RtcpScheduler {
+        let mut scheduler = RtcpScheduler::new(session_bandwidth, base());
+        scheduler.reseed(seed);
+        scheduler.tn = base() + scheduler.randomized_interval();
+        scheduler
+    }
+
+    // Bounds of calculated interval given deterministic interval.
+    fn bounds(td: Duration) -> (Duration, Duration) {
+        (td.mul_f64(0.5 / RtcpScheduler::COMPENSATION), td.mul_f64(1.5 / RtcpScheduler::COMPENSATION))
+    }
+
+    #[test]
+    fn initial_interval() {
+        let start = base();
+        let scheduler = scheduler(64_000, 0);
+        assert_eq!(Duration::from_millis(2500), scheduler.deterministic_interval());
+        let (min, max) = bounds(Duration::from_millis(2500));
+        let delay = scheduler.poll_timeout() - start;
+        assert!(delay >= min && delay <= max);
+    }
+
+    #[test]
+    fn deterministic_interval() {
+        let start = base();
+        let mut scheduler = scheduler(64_000, 0);
+        scheduler.initial = false;
+        assert_eq!(RtcpScheduler::MIN_TIME, scheduler.deterministic_interval());
+
+        // 64 kbit/s gives 400 octets/s of RTCP, receivers share 300 octets/s.
+        scheduler.update_members(start, 1000, 0);
+        assert_eq!(Duration::from_secs_f64(128.0 * 1000.0 / 300.0), scheduler.deterministic_interval());
+
+        // Senders share 100 octets/s.
+        scheduler.update_members(start, 1000, 10);
+        scheduler.media_sent(start);
+        assert_eq!(Duration::from_secs_f64(128.0 * 10.0 / 100.0).max(RtcpScheduler::MIN_TIME), scheduler.deterministic_interval());
+        scheduler.update_members(start, 1000, 100);
+        assert_eq!(Duration::from_secs_f64(128.0 * 100.0 / 100.0), scheduler.deterministic_interval());
+
+        // More than quarter of members are senders, everyone shares the whole bandwidth.
+        scheduler.update_members(start, 1000, 500);
+        assert_eq!(Duration::from_secs_f64(128.0 * 1000.0 / 400.0), scheduler.deterministic_interval());
+    }
+
+    #[test]
+    fn choose_sender_report() {
+        let start = base();
+        let mut scheduler = scheduler(64_000, 0);
+        assert!(!scheduler.we_sent());
+        scheduler.media_sent(start + Duration::from_secs(1));
+        assert!(scheduler.we_sent());
+        // Media sent before the second previous report no longer counts.
+        scheduler.sent(start + Duration::from_secs(2), 100);
+        assert!(scheduler.we_sent());
+        scheduler.sent(start + Duration::from_secs(7), 100);
+        assert!(!scheduler.we_sent());
+        scheduler.media_sent(start + Duration::from_secs(8));
+        scheduler.sent(start + Duration::from_secs(12), 100);
+        assert!(scheduler.we_sent());
+        scheduler.sent(start + Duration::from_secs(17), 100);
+        assert!(!scheduler.we_sent());
+    }
+
+    #[test]
+    fn reduced_minimum() {
+        let start = base();
+        let mut scheduler = scheduler(1_000_000, 0);
+        scheduler.set_reduced_minimum(true);
+        assert_eq!(Duration::from_millis(2500), scheduler.deterministic_interval());
+        scheduler.sent(start, 100);
+        assert_eq!(Duration::from_millis(360), scheduler.deterministic_interval());
+    }
+
+    #[test]
+    fn average_size() {
+        let mut scheduler = scheduler(64_000, 0);
+        scheduler.received(100);
+        assert_eq!(128.0, scheduler.avg_rtcp_size);
+        scheduler.received(260);
+        assert_eq!(138.0, scheduler.avg_rtcp_size);
+    }
+
+    #[test]
+    fn send_reports_on_schedule() {
+        let start = base();
+        let mut scheduler = scheduler(64_000, 7);
+        let mut now = start;
+        let mut reports = 0;
+        while now < start + Duration::from_secs(60) {
+            now += Duration::from_millis(10);
+            if scheduler.handle_timeout(now) == Some(RtcpTimeout::Report) {
+                reports += 1;
+                let (min, max) = bounds(RtcpScheduler::MIN_TIME);
+                let delay = scheduler.poll_timeout() - now;
+                assert!(delay >= min && delay <= max);
+                scheduler.sent(now, 100);
+            }
+        }
+        // On average one report every 5 seconds divided by compensation factor.
+        assert!((10..=18).contains(&reports));
+    }
+
+    #[test]
+    fn timer_reconsideration() {
+        let start = base();
+        let mut scheduler = scheduler(64_000, 1);
+        let deadline = scheduler.poll_timeout();
+        // A flood of new members makes the interval much longer, transmission is postponed.
+        scheduler.update_members(start, 1000, 0);
+        assert_eq!(None, scheduler.handle_timeout(deadline - Duration::from_millis(1)));
+        assert_eq!(None, scheduler.handle_timeout(deadline));
+        let (min, max) = bounds(Duration::from_secs_f64(128.0 * 1000.0 / 300.0));
+        let delay = scheduler.poll_timeout() - start;
+        assert!(delay >= min && delay <= max);
+    }
+
+    #[test]
+    fn reverse_reconsideration() {
+        let start = base();
+        let mut scheduler = scheduler(64_000, 0);
+        scheduler.update_members(start, 1000, 0);
+        scheduler.sent(start, 100);
+        let now = start + Duration::from_secs(100);
+        let before = scheduler.poll_timeout();
+        scheduler.update_members(now, 100, 0);
+        let after = scheduler.poll_timeout();
+        let expected = (before - now).mul_f64(0.1);
+        assert!((after - now).abs_diff(expected) < Duration::from_millis(1));
+        assert_eq!(now - Duration::from_secs(10), scheduler.tp);
+    }
+
+    #[test]
+    fn leave_small_session() {
+        let start = base();
+        let mut scheduler = scheduler(64_000, 0);
+        scheduler.update_members(start, 10, 2);
+        assert!(scheduler.leave(start));
+    }
+
+    #[test]
+    fn leave_large_session() {
+        let start = base();
+        let mut scheduler = scheduler(64_000, 0);
+        scheduler.update_members(start, 100, 2);
+        assert!(!scheduler.leave(start));
+        let (min, max) = bounds(Duration::from_millis(2500));
+        let delay = scheduler.poll_timeout() - start;
+        assert!(delay >= min && delay <= max);
+        scheduler.bye_received(100);
+        let mut now = start;
+        loop {
+            now += Duration::from_millis(10);
+            if let Some(timeout) = scheduler.handle_timeout(now) {
+                assert_eq!(RtcpTimeout::Bye, timeout);
+                break
+            }
+        }
+    }
+}
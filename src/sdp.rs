@@ -0,0 +1,821 @@
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug)]
+pub enum SdpError {
+    // Line number (1-based) and text of a line that cannot be parsed.
+    InvalidLine(usize, String),
+}
+
+// Media direction attributes (RFC 8866 section 6.7).
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Direction {
+    SendRecv,
+    SendOnly,
+    RecvOnly,
+    Inactive,
+}
+
+impl Direction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Direction::SendRecv => "sendrecv",
+            Direction::SendOnly => "sendonly",
+            Direction::RecvOnly => "recvonly",
+            Direction::Inactive => "inactive",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Direction> {
+        match name {
+            "sendrecv" => Some(Direction::SendRecv),
+            "sendonly" => Some(Direction::SendOnly),
+            "recvonly" => Some(Direction::RecvOnly),
+            "inactive" => Some(Direction::Inactive),
+            _ => None,
+        }
+    }
+
+    // Direction as seen from the other side of the session.
+    pub fn reverse(&self) -> Direction {
+        match self {
+            Direction::SendOnly => Direction::RecvOnly,
+            Direction::RecvOnly => Direction::SendOnly,
+            direction => *direction,
+        }
+    }
+
+    pub fn sends(&self) -> bool {
+        matches!(self, Direction::SendRecv | Direction::SendOnly)
+    }
+
+    pub fn receives(&self) -> bool {
+        matches!(self, Direction::SendRecv | Direction::RecvOnly)
+    }
+}
+
+// o=<username> <sess-id> <sess-version> <nettype> <addrtype> <unicast-address>
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Origin {
+    pub username: String,
+    pub session_id: String,
+    pub session_version: String,
+    pub net_type: String,
+    pub addr_type: String,
+    pub address: String,
+}
+
+// c=<nettype> <addrtype> <connection-address>, the address may carry TTL and count for multicast.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Connection {
+    pub net_type: String,
+    pub addr_type: String,
+    pub address: String,
+}
+
+// b=<bwtype>:<bandwidth>
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Bandwidth {
+    pub kind: String,
+    pub value: u64,
+}
+
+// t=<start-time> <stop-time> followed by r= lines, repeat times are kept verbatim.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct Timing {
+    pub start: u64,
+    pub stop: u64,
+    pub repeats: Vec<String>,
+}
+
+// a=<name>[:<value>]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Attribute {
+    pub name: String,
+    pub value: Option<String>,
+}
+
+impl Attribute {
+    pub fn new(name: impl Into<String>, value: Option<String>) -> Attribute {
+        Attribute { name: name.into(), value }
+    }
+
+    pub fn flag(name: impl Into<String>) -> Attribute {
+        Attribute::new(name, None)
+    }
+
+    pub fn value(name: impl Into<String>, value: impl fmt::Display) -> Attribute {
+        Attribute::new(name, Some(value.to_string()))
+    }
+}
+
+// a=rtpmap:<payload type> <encoding name>/<clock rate>[/<encoding parameters>]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RtpMap {
+    pub payload_type: u8,
+    pub encoding: String,
+    pub clock_rate: u32,
+    pub channels: Option<u16>,
+}
+
+impl FromStr for RtpMap {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (payload_type, rest) = text.trim().split_once(' ').ok_or(())?;
+        let mut parts = rest.trim().split('/');
+        let encoding = parts.next().filter(|encoding| !encoding.is_empty()).ok_or(())?;
+        let clock_rate = parts.next().ok_or(())?.trim().parse().map_err(|_| ())?;
+        let channels = parts.next().map(|channels| channels.trim().parse()).transpose().map_err(|_| ())?;
+        Ok(RtpMap { payload_type: payload_type.parse().map_err(|_| ())?, encoding: encoding.to_string(), clock_rate, channels })
+    }
+}
+
+impl fmt::Display for RtpMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{} {}/{}", self.payload_type, self.encoding, self.clock_rate)?;
+        if let Some(channels) = self.channels {
+            write!(f, "/{}", channels)?;
+        }
+        Ok(())
+    }
+}
+
+// a=fmtp:<payload type> <format specific parameters>
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Fmtp {
+    pub payload_type: u8,
+    pub params: String,
+}
+
+impl Fmtp {
+    // Iterates over semicolon separated key=value pairs, keys without value yield an empty value.
+    pub fn pairs(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.params.split(';')
+            .map(str::trim)
+            .filter(|param| !param.is_empty())
+            .map(|param| match param.split_once('=') {
+                Some((key, value)) => (key.trim(), value.trim()),
+                None => (param, ""),
+            })
+    }
+
+    // Value of the parameter, keys are case-insensitive.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.pairs().find(|(name, _)| name.eq_ignore_ascii_case(key)).map(|(_, value)| value)
+    }
+}
+
+impl FromStr for Fmtp {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim();
+        let (payload_type, params) = text.split_once(' ').unwrap_or((text, ""));
+        Ok(Fmtp { payload_type: payload_type.parse().map_err(|_| ())?, params: params.trim().to_string() })
+    }
+}
+
+impl fmt::Display for Fmtp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{} {}", self.payload_type, self.params)
+    }
+}
+
+// a=extmap:<id>[/<direction>] <URI> [<extension attributes>] (RFC 8285)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExtMap {
+    pub id: u8,
+    pub direction: Option<Direction>,
+    pub uri: String,
+    pub attributes: Option<String>,
+}
+
+impl FromStr for ExtMap {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut fields = text.trim().splitn(3, ' ');
+        let id = fields.next().ok_or(())?;
+        let (id, direction) = match id.split_once('/') {
+            Some((id, direction)) => (id, Some(Direction::from_name(direction).ok_or(())?)),
+            None => (id, None),
+        };
+        let uri = fields.next().filter(|uri| !uri.is_empty()).ok_or(())?;
+        let attributes = fields.next().map(|attributes| attributes.trim().to_string());
+        Ok(ExtMap { id: id.parse().map_err(|_| ())?, direction, uri: uri.to_string(), attributes })
+    }
+}
+
+impl fmt::Display for ExtMap {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{}", self.id)?;
+        if let Some(direction) = self.direction {
+            write!(f, "/{}", direction.as_str())?;
+        }
+        write!(f, " {}", self.uri)?;
+        if let Some(attributes) = &self.attributes {
+            write!(f, " {}", attributes)?;
+        }
+        Ok(())
+    }
+}
+
+// a=rtcp-fb:<payload type or *> <type> [<parameter>] (RFC 4585 section 4.2)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct RtcpFb {
+    // None stands for all payload types.
+    pub payload_type: Option<u8>,
+    pub kind: String,
+    pub param: Option<String>,
+}
+
+impl FromStr for RtcpFb {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut fields = text.trim().splitn(3, ' ');
+        let payload_type = match fields.next().ok_or(())? {
+            "*" => None,
+            payload_type => Some(payload_type.parse().map_err(|_| ())?),
+        };
+        let kind = fields.next().filter(|kind| !kind.is_empty()).ok_or(())?;
+        let param = fields.next().map(|param| param.trim().to_string());
+        Ok(RtcpFb { payload_type, kind: kind.to_string(), param })
+    }
+}
+
+impl fmt::Display for RtcpFb {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match self.payload_type {
+            Some(payload_type) => write!(f, "{} {}", payload_type, self.kind)?,
+            None => write!(f, "* {}", self.kind)?,
+        }
+        if let Some(param) = &self.param {
+            write!(f, " {}", param)?;
+        }
+        Ok(())
+    }
+}
+
+// a=ssrc:<ssrc-id> <attribute>[:<value>] (RFC 5576)
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SsrcAttribute {
+    pub ssrc: u32,
+    pub name: String,
+    pub value: Option<String>,
+}
+
+impl FromStr for SsrcAttribute {
+    type Err = ();
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let (ssrc, attribute) = text.trim().split_once(' ').ok_or(())?;
+        let (name, value) = match attribute.split_once(':') {
+            Some((name, value)) => (name, Some(value.to_string())),
+            None => (attribute, None),
+        };
+        Ok(SsrcAttribute { ssrc: ssrc.parse().map_err(|_| ())?, name: name.to_string(), value })
+    }
+}
+
+impl fmt::Display for SsrcAttribute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "{} {}", self.ssrc, self.name)?;
+        if let Some(value) = &self.value {
+            write!(f, ":{}", value)?;
+        }
+        Ok(())
+    }
+}
+
+// Attribute list shared by session and media descriptions with typed lookups.  Malformed attributes are
+// kept for serialization but skipped by typed accessors.
+pub trait Attributes {
+    fn attribute_list(&self) -> &[Attribute];
+
+    // Value of the first attribute with given name, empty for flags.
+    fn attribute(&self, name: &str) -> Option<&str> {
+        self.attribute_list().iter()
+            .find(|attr| attr.name == name)
+            .map(|attr| attr.value.as_deref().unwrap_or_default())
+    }
+
+    fn attributes<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a str> {
+        self.attribute_list().iter()
+            .filter(move |attr| attr.name == name)
+            .map(|attr| attr.value.as_deref().unwrap_or_default())
+    }
+
+    fn has_attribute(&self, name: &str) -> bool {
+        self.attribute(name).is_some()
+    }
+
+    fn direction(&self) -> Option<Direction> {
+        self.attribute_list().iter().rev().find_map(|attr| Direction::from_name(&attr.name))
+    }
+
+    fn control(&self) -> Option<&str> {
+        self.attribute("control")
+    }
+
+    fn range(&self) -> Option<&str> {
+        self.attribute("range")
+    }
+
+    fn rtpmaps(&self) -> Vec<RtpMap> {
+        self.attributes("rtpmap").filter_map(|value| value.parse().ok()).collect()
+    }
+
+    fn fmtps(&self) -> Vec<Fmtp> {
+        self.attributes("fmtp").filter_map(|value| value.parse().ok()).collect()
+    }
+
+    fn extmaps(&self) -> Vec<ExtMap> {
+        self.attributes("extmap").filter_map(|value| value.parse().ok()).collect()
+    }
+
+    fn rtcp_fbs(&self) -> Vec<RtcpFb> {
+        self.attributes("rtcp-fb").filter_map(|value| value.parse().ok()).collect()
+    }
+
+    fn ssrcs(&self) -> Vec<SsrcAttribute> {
+        self.attributes("ssrc").filter_map(|value| value.parse().ok()).collect()
+    }
+}
+
+// m=<media> <port>[/<number of ports>] <proto> <fmt> ...
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct MediaDescription {
+    pub media: String,
+    pub port: u16,
+    pub port_count: Option<u16>,
+    pub proto: String,
+    pub formats: Vec<String>,
+    pub info: Option<String>,
+    pub connections: Vec<Connection>,
+    pub bandwidths: Vec<Bandwidth>,
+    pub key: Option<String>,
+    pub attributes: Vec<Attribute>,
+    // Lines of unknown type or malformed c= and b= lines as (type, value) pairs in input order.
+    pub unknown: Vec<(String, String)>,
+}
+
+impl MediaDescription {
+    pub fn new(media: impl Into<String>, port: u16, proto: impl Into<String>, formats: Vec<String>) -> MediaDescription {
+        MediaDescription {
+            media: media.into(),
+            port,
+            port_count: None,
+            proto: proto.into(),
+            formats,
+            info: None,
+            connections: Vec::new(),
+            bandwidths: Vec::new(),
+            key: None,
+            attributes: Vec::new(),
+            unknown: Vec::new(),
+        }
+    }
+
+    // RTP payload types listed in m= line, non-numeric formats are skipped.
+    pub fn payload_types(&self) -> Vec<u8> {
+        self.formats.iter().filter_map(|format| format.parse().ok()).collect()
+    }
+
+    pub fn rtpmap(&self, payload_type: u8) -> Option<RtpMap> {
+        self.rtpmaps().into_iter().find(|rtpmap| rtpmap.payload_type == payload_type)
+    }
+
+    pub fn fmtp(&self, payload_type: u8) -> Option<Fmtp> {
+        self.fmtps().into_iter().find(|fmtp| fmtp.payload_type == payload_type)
+    }
+
+    // Rejected or disabled media stream (RFC 3264 section 6).
+    pub fn is_rejected(&self) -> bool {
+        self.port == 0
+    }
+
+    fn parse(line: &str) -> Option<MediaDescription> {
+        let mut fields = line.split_ascii_whitespace();
+        let media = fields.next()?;
+        let port = fields.next()?;
+        let (port, port_count) = match port.split_once('/') {
+            Some((port, count)) => (port.parse().ok()?, Some(count.parse().ok()?)),
+            None => (port.parse().ok()?, None),
+        };
+        let proto = fields.next()?;
+        let mut description = MediaDescription::new(media, port, proto, fields.map(String::from).collect());
+        description.port_count = port_count;
+        Some(description)
+    }
+}
+
+impl Attributes for MediaDescription {
+    fn attribute_list(&self) -> &[Attribute] {
+        &self.attributes
+    }
+}
+
+// SessionDescription is a parsed SDP (RFC 8866).  Parsing is lenient: both CRLF and LF line endings are
+// accepted, trailing whitespace and empty lines are ignored, missing v= and t= lines are tolerated and lines
+// of unknown type as well as malformed c= and b= lines are kept as unknown.  Serialization writes lines in the order of RFC 8866 section 5 with CRLF, so
+// conformant input is reproduced exactly, unknown lines follow the attributes of their section.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct SessionDescription {
+    pub version: u8,
+    pub origin: Option<Origin>,
+    pub name: Option<String>,
+    pub info: Option<String>,
+    pub uri: Option<String>,
+    pub emails: Vec<String>,
+    pub phones: Vec<String>,
+    pub connection: Option<Connection>,
+    pub bandwidths: Vec<Bandwidth>,
+    pub timings: Vec<Timing>,
+    pub time_zones: Option<String>,
+    pub key: Option<String>,
+    pub attributes: Vec<Attribute>,
+    // Session level lines of unknown type or malformed c= and b= lines as (type, value) pairs in input order.
+    pub unknown: Vec<(String, String)>,
+    pub media: Vec<MediaDescription>,
+}
+
+impl SessionDescription {
+    // Connection of the media, falls back to session level connection.
+    pub fn media_connection<'a>(&'a self, media: &'a MediaDescription) -> Option<&'a Connection> {
+        media.connections.first().or(self.connection.as_ref())
+    }
+
+    // Direction of the media, falls back to session level attribute and then to sendrecv.
+    pub fn media_direction(&self, media: &MediaDescription) -> Direction {
+        media.direction().or(self.direction()).unwrap_or(Direction::SendRecv)
+    }
+}
+
+impl Attributes for SessionDescription {
+    fn attribute_list(&self) -> &[Attribute] {
+        &self.attributes
+    }
+}
+
+fn parse_connection(value: &str) -> Option<Connection> {
+    let mut fields = value.split_ascii_whitespace();
+    Some(Connection {
+        net_type: fields.next()?.to_string(),
+        addr_type: fields.next()?.to_string(),
+        address: fields.next()?.to_string(),
+    })
+}
+
+fn parse_bandwidth(value: &str) -> Option<Bandwidth> {
+    let (kind, value) = value.split_once(':')?;
+    Some(Bandwidth { kind: kind.trim().to_string(), value: value.trim().parse().ok()? })
+}
+
+fn parse_attribute(value: &str) -> Attribute {
+    match value.split_once(':') {
+        Some((name, value)) => Attribute::new(name, Some(value.to_string())),
+        None => Attribute::flag(value),
+    }
+}
+
+impl FromStr for SessionDescription {
+    type Err = SdpError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut session = SessionDescription::default();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim_end();
+            if line.is_empty() {
+                continue
+            }
+            let invalid = || SdpError::InvalidLine(index + 1, line.to_string());
+            let (kind, value) = line.split_once('=').ok_or_else(invalid)?;
+            let kind = kind.trim();
+            if kind == "m" {
+                session.media.push(MediaDescription::parse(value).ok_or_else(invalid)?);
+                continue
+            }
+            if let Some(media) = session.media.last_mut() {
+                match kind {
+                    "i" => media.info = Some(value.to_string()),
+                    "c" => match parse_connection(value) {
+                        Some(connection) => media.connections.push(connection),
+                        None => media.unknown.push((kind.to_string(), value.to_string())),
+                    },
+                    "b" => match parse_bandwidth(value) {
+                        Some(bandwidth) => media.bandwidths.push(bandwidth),
+                        None => media.unknown.push((kind.to_string(), value.to_string())),
+                    },
+                    "k" => media.key = Some(value.to_string()),
+                    "a" => media.attributes.push(parse_attribute(value)),
+                    _ => media.unknown.push((kind.to_string(), value.to_string())),
+                }
+                continue
+            }
+            match kind {
+                "v" => session.version = value.trim().parse().map_err(|_| invalid())?,
+                "o" => {
+                    let fields: Vec<&str> = value.split_ascii_whitespace().collect();
+                    if fields.len() != 6 {
+                        return Err(invalid())
+                    }
+                    session.origin = Some(Origin {
+                        username: fields[0].to_string(),
+                        session_id: fields[1].to_string(),
+                        session_version: fields[2].to_string(),
+                        net_type: fields[3].to_string(),
+                        addr_type: fields[4].to_string(),
+                        address: fields[5].to_string(),
+                    });
+                },
+                "s" => session.name = Some(value.to_string()),
+                "i" => session.info = Some(value.to_string()),
+                "u" => session.uri = Some(value.to_string()),
+                "e" => session.emails.push(value.to_string()),
+                "p" => session.phones.push(value.to_string()),
+                "c" => match parse_connection(value) {
+                    Some(connection) => session.connection = Some(connection),
+                    None => session.unknown.push((kind.to_string(), value.to_string())),
+                },
+                "b" => match parse_bandwidth(value) {
+                    Some(bandwidth) => session.bandwidths.push(bandwidth),
+                    None => session.unknown.push((kind.to_string(), value.to_string())),
+                },
+                "t" => {
+                    let mut fields = value.split_ascii_whitespace().map(str::parse::<u64>);
+                    match (fields.next(), fields.next()) {
+                        (Some(Ok(start)), Some(Ok(stop))) => session.timings.push(Timing { start, stop, repeats: Vec::new() }),
+                        _ => return Err(invalid()),
+                    }
+                },
+                "r" => match session.timings.last_mut() {
+                    Some(timing) => timing.repeats.push(value.to_string()),
+                    None => return Err(invalid()),
+                },
+                "z" => session.time_zones = Some(value.to_string()),
+                "k" => session.key = Some(value.to_string()),
+                "a" => session.attributes.push(parse_attribute(value)),
+                _ => session.unknown.push((kind.to_string(), value.to_string())),
+            }
+        }
+        Ok(session)
+    }
+}
+
+impl fmt::Display for Connection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "c={} {} {}\r\n", self.net_type, self.addr_type, self.address)
+    }
+}
+
+impl fmt::Display for Bandwidth {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "b={}:{}\r\n", self.kind, self.value)
+    }
+}
+
+impl fmt::Display for Attribute {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        match &self.value {
+            Some(value) => write!(f, "a={}:{}\r\n", self.name, value),
+            None => write!(f, "a={}\r\n", self.name),
+        }
+    }
+}
+
+impl fmt::Display for MediaDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "m={} {}", self.media, self.port)?;
+        if let Some(count) = self.port_count {
+            write!(f, "/{}", count)?;
+        }
+        write!(f, " {}", self.proto)?;
+        for format in &self.formats {
+            write!(f, " {}", format)?;
+        }
+        write!(f, "\r\n")?;
+        if let Some(info) = &self.info {
+            write!(f, "i={}\r\n", info)?;
+        }
+        for connection in &self.connections {
+            write!(f, "{}", connection)?;
+        }
+        for bandwidth in &self.bandwidths {
+            write!(f, "{}", bandwidth)?;
+        }
+        if let Some(key) = &self.key {
+            write!(f, "k={}\r\n", key)?;
+        }
+        for attribute in &self.attributes {
+            write!(f, "{}", attribute)?;
+        }
+        for (kind, value) in &self.unknown {
+            write!(f, "{}={}\r\n", kind, value)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SessionDescription {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        write!(f, "v={}\r\n", self.version)?;
+        if let Some(origin) = &self.origin {
+            write!(f, "o={} {} {} {} {} {}\r\n", origin.username, origin.session_id, origin.session_version,
+                origin.net_type, origin.addr_type, origin.address)?;
+        }
+        if let Some(name) = &self.name {
+            write!(f, "s={}\r\n", name)?;
+        }
+        if let Some(info) = &self.info {
+            write!(f, "i={}\r\n", info)?;
+        }
+        if let Some(uri) = &self.uri {
+            write!(f, "u={}\r\n", uri)?;
+        }
+        for email in &self.emails {
+            write!(f, "e={}\r\n", email)?;
+        }
+        for phone in &self.phones {
+            write!(f, "p={}\r\n", phone)?;
+        }
+        if let Some(connection) = &self.connection {
+            write!(f, "{}", connection)?;
+        }
+        for bandwidth in &self.bandwidths {
+            write!(f, "{}", bandwidth)?;
+        }
+        for timing in &self.timings {
+            write!(f, "t={} {}\r\n", timing.start, timing.stop)?;
+            for repeat in &timing.repeats {
+                write!(f, "r={}\r\n", repeat)?;
+            }
+        }
+        if let Some(zones) = &self.time_zones {
+            write!(f, "z={}\r\n", zones)?;
+        }
+        if let Some(key) = &self.key {
+            write!(f, "k={}\r\n", key)?;
+        }
+        for attribute in &self.attributes {
+            write!(f, "{}", attribute)?;
+        }
+        for (kind, value) in &self.unknown {
+            write!(f, "{}={}\r\n", kind, value)?;
+        }
+        for media in &self.media {
+            write!(f, "{}", media)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Example from RFC 8866 section 5.
+    const RFC_EXAMPLE: &str = "v=0\r\n\
+        o=jdoe 3724394400 3724394405 IN IP4 198.51.100.1\r\n\
+        s=Call to John Smith\r\n\
+        i=SDP Offer #1\r\n\
+        u=http://www.jdoe.example.com/home.html\r\n\
+        e=Jane Doe <jane@jdoe.example.com>\r\n\
+        p=+1 617 555-6011\r\n\
+        c=IN IP4 198.51.100.1\r\n\
+        t=0 0\r\n\
+        m=audio 49170 RTP/AVP 0\r\n\
+        m=audio 49180 RTP/AVP 0\r\n\
+        m=video 51372 RTP/AVP 99\r\n\
+        c=IN IP6 2001:db8::2\r\n\
+        a=rtpmap:99 h263-1998/90000\r\n";
+
+    const CAMERA: &str = "v=0\r\n\
+        o=- 1681234567890123 1 IN IP4 192.168.1.64\r\n\
+        s=Media Presentation\r\n\
+        e=NONE\r\n\
+        b=AS:5100\r\n\
+        t=0 0\r\n\
+        a=control:rtsp://192.168.1.64/Streaming/Channels/101/\r\n\
+        a=range:npt=now-\r\n\
+        m=video 0 RTP/AVP 96\r\n\
+        c=IN IP4 0.0.0.0\r\n\
+        b=AS:5000\r\n\
+        a=recvonly\r\n\
+        a=x-dimensions:1920,1080\r\n\
+        a=control:trackID=1\r\n\
+        a=rtpmap:96 H264/90000\r\n\
+        a=fmtp:96 profile-level-id=420029; packetization-mode=1; sprop-parameter-sets=Z00AKpY1QPAET8s3AQEBQAABwgAAV+QB,aO48gA==\r\n\
+        a=extmap:1/sendonly urn:ietf:params:rtp-hdrext:ntp-64\r\n\
+        a=rtcp-fb:* nack pli\r\n\
+        a=ssrc:12345 cname:camera\r\n\
+        m=audio 0 RTP/AVP 0\r\n\
+        a=control:trackID=2\r\n";
+
+    #[test]
+    fn round_trip_rfc_example() {
+        let session = RFC_EXAMPLE.parse::<SessionDescription>().unwrap();
+        assert_eq!("jdoe", session.origin.as_ref().unwrap().username);
+        assert_eq!(Some("Call to John Smith"), session.name.as_deref());
+        assert_eq!(1, session.timings.len());
+        assert_eq!(3, session.media.len());
+        let video = &session.media[2];
+        assert_eq!("IN IP6 2001:db8::2", {
+            let connection = session.media_connection(video).unwrap();
+            format!("{} {} {}", connection.net_type, connection.addr_type, connection.address)
+        });
+        assert_eq!("198.51.100.1", session.media_connection(&session.media[0]).unwrap().address);
+        assert_eq!(Some(RtpMap { payload_type: 99, encoding: "h263-1998".to_string(), clock_rate: 90000, channels: None }), video.rtpmap(99));
+        assert_eq!(RFC_EXAMPLE, session.to_string());
+    }
+
+    #[test]
+    fn round_trip_camera_sdp() {
+        let session = CAMERA.parse::<SessionDescription>().unwrap();
+        assert_eq!(Some("rtsp://192.168.1.64/Streaming/Channels/101/"), session.control());
+        assert_eq!(Some("npt=now-"), session.range());
+        assert_eq!(5100, session.bandwidths[0].value);
+        let video = &session.media[0];
+        assert_eq!(Direction::RecvOnly, session.media_direction(video));
+        assert_eq!(Some("trackID=1"), video.control());
+        assert_eq!(vec![96], video.payload_types());
+        let fmtp = video.fmtp(96).unwrap();
+        assert_eq!(Some("1"), fmtp.get("packetization-mode"));
+        assert_eq!(Some("Z00AKpY1QPAET8s3AQEBQAABwgAAV+QB,aO48gA=="), fmtp.get("sprop-parameter-sets"));
+        assert_eq!(vec![ExtMap { id: 1, direction: Some(Direction::SendOnly), uri: "urn:ietf:params:rtp-hdrext:ntp-64".to_string(), attributes: None }], video.extmaps());
+        assert_eq!(vec![RtcpFb { payload_type: None, kind: "nack".to_string(), param: Some("pli".to_string()) }], video.rtcp_fbs());
+        assert_eq!(vec![SsrcAttribute { ssrc: 12345, name: "cname".to_string(), value: Some("camera".to_string()) }], video.ssrcs());
+        assert_eq!(Some("1920,1080"), video.attribute("x-dimensions"));
+        assert_eq!(Direction::SendRecv, session.media_direction(&session.media[1]));
+        assert_eq!(CAMERA, session.to_string());
+    }
+
+    #[test]
+    fn parse_non_conformant_sdp() {
+        let text = "v=0\n\
+            o=- 0 0 IN IP4 127.0.0.1 \n\
+            s=No Name\n\
+            \n\
+            c=IN IP4 0.0.0.0\n\
+            a=tool:libavformat  \n\
+            m=audio 0 RTP/AVP 97\n\
+            a=rtpmap:97 MPEG4-GENERIC/48000/2\n\
+            a=fmtp:97 profile-level-id=1;mode=AAC-hbr;sizelength=13;indexlength=3;indexdeltalength=3;config=1190\n";
+        let session = text.parse::<SessionDescription>().unwrap();
+        assert!(session.timings.is_empty());
+        assert_eq!("127.0.0.1", session.origin.as_ref().unwrap().address);
+        assert_eq!(Some("libavformat"), session.attribute("tool"));
+        let audio = &session.media[0];
+        assert_eq!(Some(2), audio.rtpmap(97).unwrap().channels);
+        assert_eq!(Some("1190"), audio.fmtp(97).unwrap().get("config"));
+        let normalized: Vec<&str> = text.lines().map(str::trim_end).filter(|line| !line.is_empty()).collect();
+        assert_eq!(normalized.join("\r\n") + "\r\n", session.to_string());
+    }
+
+    #[test]
+    fn round_trip_unknown_lines() {
+        let text = "v=0\r\n\
+            o=- 0 0 IN IP4 127.0.0.1\r\n\
+            s=-\r\n\
+            t=0 0\r\n\
+            a=control:*\r\n\
+            y=0110000000\r\n\
+            x=session\r\n\
+            m=video 0 RTP/AVP 96\r\n\
+            a=rtpmap:96 H264/90000\r\n\
+            t=0 0\r\n\
+            y=0110000001\r\n";
+        let session = text.parse::<SessionDescription>().unwrap();
+        assert_eq!(vec![("y".to_string(), "0110000000".to_string()), ("x".to_string(), "session".to_string())], session.unknown);
+        assert_eq!(vec![("t".to_string(), "0 0".to_string()), ("y".to_string(), "0110000001".to_string())], session.media[0].unknown);
+        assert_eq!(text, session.to_string());
+    }
+
+    #[test]
+    fn keep_malformed_connection_and_bandwidth() {
+        let text = "v=0\r\n\
+            s=-\r\n\
+            c=IN IP4\r\n\
+            b=AS:unlimited\r\n\
+            m=audio 0 RTP/AVP 0\r\n\
+            c=IN IP4 0.0.0.0\r\n\
+            b=AS\r\n";
+        let session = text.parse::<SessionDescription>().unwrap();
+        assert!(session.connection.is_none() && session.bandwidths.is_empty());
+        assert_eq!(vec![("c".to_string(), "IN IP4".to_string()), ("b".to_string(), "AS:unlimited".to_string())], session.unknown);
+        assert_eq!("0.0.0.0", session.media[0].connections[0].address);
+        assert_eq!(vec![("b".to_string(), "AS".to_string())], session.media[0].unknown);
+        assert_eq!(text, session.to_string());
+    }
+
+    #[test]
+    fn reject_malformed_lines() {
+        let error = "v=0\r\nm=video x RTP/AVP 96\r\n".parse::<SessionDescription>().unwrap_err();
+        assert!(matches!(error, SdpError::InvalidLine(2, line) if line == "m=video x RTP/AVP 96"));
+        let error = "v=0\r\no=- 0\r\n".parse::<SessionDescription>().unwrap_err();
+        assert!(matches!(error, SdpError::InvalidLine(2, _)));
+        let error = "v=0\r\ngarbage\r\n".parse::<SessionDescription>().unwrap_err();
+        assert!(matches!(error, SdpError::InvalidLine(2, _)));
+    }
+}
@@ -0,0 +1,1129 @@
+use std::collections::VecDeque;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+use rand::Rng;
+
+use crate::headers::{SessionHeader, TransportSpec};
+use crate::interleaved::{ChannelMap, InterleavedFrame};
+use crate::mux::PacketKind;
+use crate::rtp::RtpPacket;
+use crate::rtsp::{Headers, Message, Method, Request, Response, RtspError, RtspItem, RtspParser, RtspVersion};
+use crate::sdp::{Attribute, Attributes, Connection, MediaDescription, Origin, SessionDescription, Timing};
+use crate::tunnel::{Base64Decoder, TunnelError, TunnelMethod, TunnelRequest};
+
+// Identifier of a client connection assigned by RtspServer::connect().
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct ConnectionId(u64);
+
+// Mount is a presentation served under a path, e.g. "/live".  Media sections describe the streams as they
+// appear in SDP, the server adds control attributes.  Mounts are configured by the application or announced
+// by publishing clients.
+#[derive(Clone, Debug)]
+pub struct Mount {
+    path: String,
+    name: String,
+    media: Vec<MediaDescription>,
+    // Control URL of each media relative to the mount path.
+    controls: Vec<String>,
+    announced: bool,
+    // Multicast group, first port and TTL used for media 0, following media use next port pairs.
+    multicast: Option<(IpAddr, u16, u8)>,
+    // Last RTP sequence number and timestamp seen per media, for RTP-Info.
+    last: Vec<Option<(u16, u32)>>,
+}
+
+impl Mount {
+    pub fn new(path: impl Into<String>, name: impl Into<String>) -> Mount {
+        let path = path.into();
+        Mount {
+            path: format!("/{}", path.trim_matches('/')),
+            name: name.into(),
+            media: Vec::new(),
+            controls: Vec::new(),
+            announced: false,
+            multicast: None,
+            last: Vec::new(),
+        }
+    }
+
+    // Adds a stream and returns its index used by RtspServer::send_rtp().
+    pub fn add_media(&mut self, media: MediaDescription) -> usize {
+        self.controls.push(format!("stream={}", self.media.len()));
+        self.media.push(media);
+        self.last.push(None);
+        self.media.len() - 1
+    }
+
+    pub fn set_multicast(&mut self, group: IpAddr, port: u16, ttl: u8) {
+        self.multicast = Some((group, port, ttl));
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn media(&self) -> &[MediaDescription] {
+        &self.media
+    }
+
+    // Whether the mount was created by ANNOUNCE.
+    pub fn is_announced(&self) -> bool {
+        self.announced
+    }
+
+    // Mount of a published presentation, keeping control URLs the publisher will set up.
+    fn announced(path: &str, description: &SessionDescription) -> Mount {
+        let mut mount = Mount::new(path, description.name.clone().unwrap_or_default());
+        for media in &description.media {
+            // Absolute control URLs are reduced to the last segment, the mount path precedes it.
+            let control = media.control().map(|control| control.rsplit('/').next().unwrap_or(control).to_string());
+            let mut media = media.clone();
+            media.attributes.retain(|attribute| attribute.name != "control");
+            let index = mount.add_media(media);
+            if let Some(control) = control.filter(|control| !control.is_empty() && control != "*") {
+                mount.controls[index] = control;
+            }
+        }
+        mount.announced = true;
+        mount
+    }
+
+    fn multicast_group(&self, index: usize) -> Option<(IpAddr, u16, u8)> {
+        self.multicast.map(|(group, port, ttl)| (group, port.wrapping_add(2 * index as u16), ttl))
+    }
+
+    // Live presentation description (RFC 2326 appendix C).
+    pub fn session_description(&self, address: IpAddr) -> SessionDescription {
+        let addr_type = if address.is_ipv4() { "IP4" } else { "IP6" };
+        let mut description = SessionDescription {
+            origin: Some(Origin {
+                username: "-".to_string(),
+                session_id: "0".to_string(),
+                session_version: "1".to_string(),
+                net_type: "IN".to_string(),
+                addr_type: addr_type.to_string(),
+                address: address.to_string(),
+            }),
+            name: Some(self.name.clone()),
+            timings: vec![Timing::default()],
+            attributes: vec![Attribute::value("control", "*"), Attribute::value("range", "npt=now-")],
+            ..Default::default()
+        };
+        for (index, media) in self.media.iter().enumerate() {
+            let mut media = media.clone();
+            media.attributes.retain(|attribute| attribute.name != "control");
+            if let Some((group, port, ttl)) = self.multicast_group(index) {
+                media.port = port;
+                media.connections = vec![Connection {
+                    net_type: "IN".to_string(),
+                    addr_type: if group.is_ipv4() { "IP4" } else { "IP6" }.to_string(),
+                    address: if group.is_ipv4() { format!("{}/{}", group, ttl) } else { group.to_string() },
+                }];
+            }
+            media.attributes.push(Attribute::value("control", &self.controls[index]));
+            description.media.push(media);
+        }
+        description
+    }
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+enum Delivery {
+    Udp { rtp: SocketAddr, rtcp: SocketAddr },
+    Tcp { connection: ConnectionId },
+    Multicast,
+}
+
+// Role of a client connection, decided by its first bytes.
+enum Role {
+    Undecided(Vec<u8>),
+    Rtsp,
+    // Halves of RTSP over HTTP tunnel, requests arrive base64 encoded on POST and are answered on GET.
+    TunnelGet { cookie: String },
+    TunnelPost { get: ConnectionId, decoder: Base64Decoder },
+}
+
+struct ClientConnection {
+    id: ConnectionId,
+    peer: SocketAddr,
+    parser: RtspParser,
+    role: Role,
+}
+
+// Delivery, Transport header of the response and interleaved channels chosen by SETUP.
+type Negotiated = (Delivery, TransportSpec, Option<(u8, u8)>);
+
+#[derive(Clone, Debug)]
+struct Stream {
+    media: usize,
+    delivery: Delivery,
+    ssrc: u32,
+    // Sequence number of the first packet sent to the client and offset added to source numbers.
+    start_seq: u16,
+    seq_offset: Option<u16>,
+    // Set up with mode=record, the client sends media.
+    record: bool,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SessionState {
+    Ready,
+    Playing,
+    Recording,
+}
+
+#[derive(Clone, Debug)]
+struct Session {
+    id: String,
+    mount: usize,
+    connection: ConnectionId,
+    state: SessionState,
+    streams: Vec<Stream>,
+    channels: ChannelMap,
+    expires: Instant,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum CloseReason {
+    Teardown,
+    Timeout,
+    Disconnected,
+    // The mount was replaced by another with the same path.
+    Replaced,
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum ServerEvent {
+    SessionCreated { session: String, path: String },
+    Playing { session: String },
+    Paused { session: String },
+    SessionClosed { session: String, reason: CloseReason },
+    // Publisher announced presentation that replaced the mount at the path.
+    Announced { path: String },
+    Recording { session: String },
+    // RTP or RTCP packet sent by a recording client, RTP may be passed on to send_rtp() with the same path.
+    Received { path: String, media: usize, kind: PacketKind, data: Vec<u8> },
+}
+
+// Data to send: bytes on a client connection or an RTP or RTCP datagram from the server ports.
+#[derive(Debug, Eq, PartialEq)]
+pub enum ServerTransmit {
+    Tcp { connection: ConnectionId, data: Vec<u8> },
+    Udp { destination: SocketAddr, kind: PacketKind, data: Vec<u8> },
+}
+
+// RtspServer is a sans-IO RTSP 1.0 server.  Requests of client connections are routed to mounts by URL path.
+// SETUP negotiates UDP unicast, multicast or TCP interleaved delivery and creates a session that expires
+// unless refreshed by requests or RTCP.  Media produced once, e.g. by RtpPacketizer, is passed to
+// send_rtp() and copied to every playing session with its own SSRC and sequence numbers.  Clients may publish
+// to paths without configured mount by ANNOUNCE, SETUP with mode=record over TCP and RECORD, their packets
+// are reported as events.
+pub struct RtspServer {
+    address: IpAddr,
+    server_ports: Option<(u16, u16)>,
+    timeout: Duration,
+    mounts: Vec<Mount>,
+    connections: Vec<ClientConnection>,
+    next_connection: u64,
+    sessions: Vec<Session>,
+    transmit: VecDeque<ServerTransmit>,
+    events: VecDeque<ServerEvent>,
+}
+
+impl RtspServer {
+    pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(60);
+
+    pub fn new(address: IpAddr) -> RtspServer {
+        RtspServer {
+            address,
+            server_ports: None,
+            timeout: RtspServer::DEFAULT_TIMEOUT,
+            mounts: Vec::new(),
+            connections: Vec::new(),
+            next_connection: 0,
+            sessions: Vec::new(),
+            transmit: VecDeque::new(),
+            events: VecDeque::new(),
+        }
+    }
+
+    // Ports of RTP and RTCP sockets the application sends UDP datagrams from, UDP delivery needs them.
+    pub fn set_udp_ports(&mut self, rtp: u16, rtcp: u16) {
+        self.server_ports = Some((rtp, rtcp));
+    }
+
+    pub fn set_session_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    pub fn add_mount(&mut self, mount: Mount) {
+        // Sessions refer to mounts by index, a mount with the same path is replaced in place and sessions set up
+        // for its old media are closed.
+        let Some(index) = self.mounts.iter().position(|existing| existing.path == mount.path) else {
+            self.mounts.push(mount);
+            return
+        };
+        self.mounts[index] = mount;
+        let closed = self.sessions.iter()
+            .filter(|session| session.mount == index)
+            .map(|session| session.id.clone())
+            .collect::<Vec<_>>();
+        for session in closed {
+            self.close_session(&session, CloseReason::Replaced);
+        }
+    }
+
+    pub fn mount(&self, path: &str) -> Option<&Mount> {
+        self.mounts.iter().find(|mount| mount.path == path)
+    }
+
+    pub fn session_state(&self, session: &str) -> Option<SessionState> {
+        self.sessions.iter().find(|existing| existing.id == session).map(|session| session.state)
+    }
+
+    pub fn connect(&mut self, peer: SocketAddr) -> ConnectionId {
+        self.next_connection += 1;
+        let id = ConnectionId(self.next_connection);
+        self.connections.push(ClientConnection { id, peer, parser: RtspParser::new(), role: Role::Undecided(Vec::new()) });
+        id
+    }
+
+    // Drops the connection and sessions delivering media over it.
+    pub fn disconnect(&mut self, connection: ConnectionId) {
+        self.connections.retain(|existing| existing.id != connection);
+        let closed = self.sessions.iter()
+            .filter(|session| session.streams.iter().any(|stream| stream.delivery == Delivery::Tcp { connection }))
+            .map(|session| session.id.clone())
+            .collect::<Vec<_>>();
+        for session in closed {
+            self.close_session(&session, CloseReason::Disconnected);
+        }
+    }
+
+    // Handles data received on a client connection.  Connections starting with HTTP GET or POST are tunnels,
+    // requests decoded from POST are processed as if received on GET connection with the same cookie.
+    pub fn handle_input(&mut self, connection: ConnectionId, data: &[u8], now: Instant) -> Result<(), RtspError> {
+        let Some(index) = self.connections.iter().position(|existing| existing.id == connection) else {
+            return Ok(())
+        };
+        if let Err(error) = self.decode_input(index, data) {
+            if let Role::Undecided(_) = self.connections[index].role {
+                let data = b"HTTP/1.0 400 Bad Request\r\n\r\n".to_vec();
+                self.transmit.push_back(ServerTransmit::Tcp { connection, data });
+            }
+            return Err(error.into())
+        }
+        let (reply_to, peer) = match self.connections[index].role {
+            Role::TunnelPost { get, .. } => (get, self.connections[index].peer),
+            _ => (connection, self.connections[index].peer),
+        };
+        loop {
+            let Some(client) = self.connections.iter_mut().find(|existing| existing.id == connection) else {
+                return Ok(())
+            };
+            let item = match client.parser.poll_item() {
+                Ok(Some(item)) => item,
+                Ok(None) => return Ok(()),
+                Err(error) => {
+                    let data = Response::new(Response::BAD_REQUEST, None).to_vec();
+                    self.transmit.push_back(ServerTransmit::Tcp { connection: reply_to, data });
+                    return Err(error)
+                },
+            };
+            match item {
+                RtspItem::Message(Message::Request(request)) => {
+                    let response = self.handle_request(reply_to, peer, &request, now);
+                    self.transmit.push_back(ServerTransmit::Tcp { connection: reply_to, data: response.to_vec() });
+                },
+                RtspItem::Message(Message::Response(_)) => {},
+                // RTCP of the client keeps its session alive.
+                RtspItem::Interleaved(frame) => self.receive_frame(reply_to, frame, now),
+            }
+        }
+    }
+
+    // Moves connection data to the RTSP parser, opening tunnel halves on the way.
+    fn decode_input(&mut self, index: usize, data: &[u8]) -> Result<(), TunnelError> {
+        let client = &mut self.connections[index];
+        let pending = match &mut client.role {
+            Role::Rtsp => {
+                client.parser.push(data);
+                return Ok(())
+            },
+            // GET half only sends after its request.
+            Role::TunnelGet { .. } => return Ok(()),
+            Role::TunnelPost { decoder, .. } => {
+                client.parser.push(&decoder.decode(data)?);
+                return Ok(())
+            },
+            Role::Undecided(pending) => {
+                pending.extend_from_slice(data);
+                pending
+            },
+        };
+        match TunnelRequest::is_tunnel(pending) {
+            None => return Ok(()),
+            Some(false) => {
+                let pending = std::mem::take(pending);
+                client.parser.push(&pending);
+                client.role = Role::Rtsp;
+                return Ok(())
+            },
+            Some(true) => {},
+        }
+        let Some((request, len)) = TunnelRequest::parse(pending)? else {
+            return Ok(())
+        };
+        let rest = pending.split_off(len);
+        let id = client.id;
+        match request.method {
+            TunnelMethod::Get => {
+                self.connections[index].role = Role::TunnelGet { cookie: request.cookie };
+                self.transmit.push_back(ServerTransmit::Tcp { connection: id, data: TunnelRequest::response() });
+            },
+            TunnelMethod::Post => {
+                let get = self.connections.iter()
+                    .find(|existing| matches!(&existing.role, Role::TunnelGet { cookie } if *cookie == request.cookie))
+                    .map(|existing| existing.id)
+                    .ok_or(TunnelError::UnknownCookie(request.cookie))?;
+                let mut decoder = Base64Decoder::new();
+                let decoded = decoder.decode(&rest)?;
+                let client = &mut self.connections[index];
+                client.parser.push(&decoded);
+                client.role = Role::TunnelPost { get, decoder };
+            },
+        }
+        Ok(())
+    }
+
+    // Datagram received on server UDP ports, RTCP receiver reports keep sessions alive.
+    pub fn handle_udp(&mut self, source: SocketAddr, now: Instant) {
+        let expires = now + self.timeout;
+        for session in &mut self.sessions {
+            let from_client = session.streams.iter().any(|stream| match stream.delivery {
+                Delivery::Udp { rtp, rtcp } => rtp == source || rtcp == source,
+                _ => false,
+            });
+            if from_client {
+                session.expires = expires;
+            }
+        }
+    }
+
+    // Sends a packet of the mount media to all sessions playing it.
+    pub fn send_rtp(&mut self, path: &str, media: usize, packet: &RtpPacket) {
+        let Some(mount_index) = self.mounts.iter().position(|mount| mount.path == path) else {
+            return
+        };
+        let mount = &mut self.mounts[mount_index];
+        if let Some(last) = mount.last.get_mut(media) {
+            *last = Some((packet.seq_number(), packet.timestamp()));
+        }
+        let multicast = mount.multicast_group(media);
+        let data = packet.to_vec();
+        let mut multicast_sent = false;
+        for session in &mut self.sessions {
+            if session.mount != mount_index || session.state != SessionState::Playing {
+                continue
+            }
+            for stream in session.streams.iter_mut().filter(|stream| stream.media == media) {
+                if stream.delivery == Delivery::Multicast {
+                    // Group members share the source stream unchanged.
+                    if let (Some((group, port, _)), false) = (multicast, multicast_sent) {
+                        let destination = SocketAddr::new(group, port);
+                        self.transmit.push_back(ServerTransmit::Udp { destination, kind: PacketKind::Rtp, data: data.clone() });
+                        multicast_sent = true;
+                    }
+                    continue
+                }
+                let offset = *stream.seq_offset.get_or_insert(stream.start_seq.wrapping_sub(packet.seq_number()));
+                let mut data = data.clone();
+                data[2..4].copy_from_slice(&packet.seq_number().wrapping_add(offset).to_be_bytes());
+                data[8..12].copy_from_slice(&stream.ssrc.to_be_bytes());
+                match stream.delivery {
+                    Delivery::Udp { rtp, .. } => {
+                        self.transmit.push_back(ServerTransmit::Udp { destination: rtp, kind: PacketKind::Rtp, data });
+                    },
+                    Delivery::Tcp { connection } => {
+                        let frame = session.channels.frame(media, PacketKind::Rtp, data);
+                        if let Ok(Some(frame)) = frame {
+                            self.transmit.push_back(ServerTransmit::Tcp { connection, data: frame.to_vec() });
+                        }
+                    },
+                    Delivery::Multicast => {},
+                }
+            }
+        }
+    }
+
+    pub fn poll_transmit(&mut self) -> Option<ServerTransmit> {
+        self.transmit.pop_front()
+    }
+
+    pub fn poll_event(&mut self) -> Option<ServerEvent> {
+        self.events.pop_front()
+    }
+
+    pub fn poll_timeout(&self) -> Option<Instant> {
+        self.sessions.iter().map(|session| session.expires).min()
+    }
+
+    pub fn handle_timeout(&mut self, now: Instant) {
+        let expired = self.sessions.iter()
+            .filter(|session| session.expires <= now)
+            .map(|session| session.id.clone())
+            .collect::<Vec<_>>();
+        for session in expired {
+            self.close_session(&session, CloseReason::Timeout);
+        }
+    }
+
+    fn close_session(&mut self, id: &str, reason: CloseReason) {
+        if let Some(index) = self.sessions.iter().position(|session| session.id == id) {
+            self.sessions.remove(index);
+            self.events.push_back(ServerEvent::SessionClosed { session: id.to_string(), reason });
+        }
+    }
+
+    // Interleaved frame from a client, RTCP of players and media of publishers keep their session alive.
+    fn receive_frame(&mut self, connection: ConnectionId, frame: InterleavedFrame, now: Instant) {
+        let expires = now + self.timeout;
+        for session in &mut self.sessions {
+            if session.connection != connection {
+                continue
+            }
+            let Some((media, kind)) = session.channels.route(&frame) else {
+                continue
+            };
+            session.expires = expires;
+            if session.state == SessionState::Recording {
+                let path = self.mounts[session.mount].path.clone();
+                self.events.push_back(ServerEvent::Received { path, media, kind, data: frame.data().to_vec() });
+            }
+        }
+    }
+
+    // Splits request URL into mount index and stream index of a stream control URL.
+    fn resolve(&self, uri: &str) -> Option<(usize, Option<usize>)> {
+        let path = uri_path(uri);
+        self.mounts.iter().enumerate().find_map(|(index, mount)| {
+            let rest = path.strip_prefix(mount.path.trim_end_matches('/'))?;
+            match rest.strip_prefix('/') {
+                None if rest.is_empty() => Some((index, None)),
+                Some(control) => mount.controls.iter().position(|existing| existing == control).map(|stream| (index, Some(stream))),
+                None => None,
+            }
+        })
+    }
+
+    fn handle_request(&mut self, connection: ConnectionId, peer: SocketAddr, request: &Request, now: Instant) -> Response {
+        let mut response = self.route_request(connection, peer, request, now);
+        if let Some(cseq) = request.headers.get(Headers::CSEQ) {
+            response.headers.set(Headers::CSEQ, cseq);
+        }
+        response
+    }
+
+    fn route_request(&mut self, connection: ConnectionId, peer: SocketAddr, request: &Request, now: Instant) -> Response {
+        // RTSP 2.0 clients fall back to 1.0 on 505.
+        if request.version != RtspVersion::V1_0 {
+            return Response::new(Response::VERSION_NOT_SUPPORTED, None)
+        }
+        let session = match request.headers.get(Headers::SESSION).map(str::parse::<SessionHeader>) {
+            Some(Ok(header)) => match self.sessions.iter().position(|session| session.id == header.id) {
+                Some(index) => {
+                    self.sessions[index].expires = now + self.timeout;
+                    Some(index)
+                },
+                None => return Response::new(Response::SESSION_NOT_FOUND, None),
+            },
+            Some(Err(_)) => return Response::new(Response::BAD_REQUEST, None),
+            None => None,
+        };
+        match (&request.method, session) {
+            (Method::Options, _) => {
+                let mut response = Response::new(Response::OK, None);
+                response.headers.set(Headers::PUBLIC, "OPTIONS, DESCRIBE, ANNOUNCE, SETUP, PLAY, PAUSE, RECORD, TEARDOWN, GET_PARAMETER, SET_PARAMETER");
+                response
+            },
+            (Method::Describe, _) => self.describe(request),
+            (Method::Announce, _) => self.announce(request),
+            (Method::Setup, session) => self.setup(connection, peer, request, session, now),
+            (Method::Play, Some(session)) => self.play(request, session),
+            (Method::Record, Some(session)) => self.record(session),
+            (Method::Pause, Some(session)) => {
+                if self.sessions[session].state == SessionState::Playing {
+                    self.sessions[session].state = SessionState::Ready;
+                    self.events.push_back(ServerEvent::Paused { session: self.sessions[session].id.clone() });
+                }
+                self.with_session(Response::new(Response::OK, None), session)
+            },
+            (Method::Teardown, Some(session)) => {
+                let id = self.sessions[session].id.clone();
+                self.close_session(&id, CloseReason::Teardown);
+                Response::new(Response::OK, None)
+            },
+            (Method::GetParameter | Method::SetParameter, Some(session)) => self.with_session(Response::new(Response::OK, None), session),
+            (Method::GetParameter | Method::SetParameter, None) => Response::new(Response::OK, None),
+            (Method::Play | Method::Pause | Method::Record | Method::Teardown, None) => Response::new(Response::SESSION_NOT_FOUND, None),
+            _ => Response::new(Response::NOT_IMPLEMENTED, None),
+        }
+    }
+
+    fn with_session(&self, mut response: Response, session: usize) -> Response {
+        let timeout = self.timeout.as_secs();
+        response.headers.set(Headers::SESSION, SessionHeader { id: self.sessions[session].id.clone(), timeout: Some(timeout) });
+        response
+    }
+
+    fn describe(&self, request: &Request) -> Response {
+        let Some((mount, None)) = self.resolve(&request.uri) else {
+            return Response::new(Response::NOT_FOUND, None)
+        };
+        let mut response = Response::new(Response::OK, None);
+        response.headers.set(Headers::CONTENT_TYPE, "application/sdp");
+        response.headers.set(Headers::CONTENT_BASE, format!("{}/", request.uri.trim_end_matches('/')));
+        response.body = self.mounts[mount].session_description(self.address).to_string().into_bytes();
+        response
+    }
+
+    fn announce(&mut self, request: &Request) -> Response {
+        let content_type = request.headers.get(Headers::CONTENT_TYPE).unwrap_or_default();
+        if !content_type.trim().eq_ignore_ascii_case("application/sdp") {
+            return Response::new(Response::UNSUPPORTED_MEDIA_TYPE, None)
+        }
+        let description = match String::from_utf8_lossy(&request.body).parse::<SessionDescription>() {
+            Ok(description) if !description.media.is_empty() => description,
+            _ => return Response::new(Response::BAD_REQUEST, None),
+        };
+        let mount = Mount::announced(uri_path(&request.uri), &description);
+        if let Some(index) = self.mounts.iter().position(|existing| existing.path == mount.path) {
+            // Configured mounts cannot be overwritten, announced ones only once their publisher and players are
+            // gone.
+            if !self.mounts[index].announced {
+                return Response::new(Response::FORBIDDEN, None)
+            }
+            if self.sessions.iter().any(|session| session.mount == index) {
+                return Response::new(Response::METHOD_NOT_VALID_IN_THIS_STATE, None)
+            }
+        }
+        self.events.push_back(ServerEvent::Announced { path: mount.path.clone() });
+        self.add_mount(mount);
+        Response::new(Response::OK, None)
+    }
+
+    fn setup(&mut self, connection: ConnectionId, peer: SocketAddr, request: &Request, session: Option<usize>, now: Instant) -> Response {
+        let Some((mount, Some(media))) = self.resolve(&request.uri) else {
+            return Response::new(Response::NOT_FOUND, None)
+        };
+        if let Some(session) = session {
+            if self.sessions[session].mount != mount {
+                return Response::new(Response::AGGREGATE_OPERATION_NOT_ALLOWED, None)
+            }
+            if self.sessions[session].state != SessionState::Ready {
+                return Response::new(Response::METHOD_NOT_VALID_IN_THIS_STATE, None)
+            }
+        }
+        let specs = match request.headers.get(Headers::TRANSPORT).map(TransportSpec::parse_list) {
+            Some(Ok(specs)) => specs,
+            _ => return Response::new(Response::UNSUPPORTED_TRANSPORT, None),
+        };
+        let channels = session.map(|session| self.sessions[session].channels.clone()).unwrap_or_default();
+        let Some((delivery, transport, interleaved)) = specs.iter()
+            .find_map(|spec| self.negotiate(connection, peer, mount, media, spec, &channels)) else {
+            return Response::new(Response::UNSUPPORTED_TRANSPORT, None)
+        };
+
+        let record = transport.mode().is_some_and(|mode| mode.eq_ignore_ascii_case("record"));
+        // Sessions either play or record all their streams.
+        if session.is_some_and(|session| self.sessions[session].streams.iter().any(|stream| stream.record != record)) {
+            return Response::new(Response::METHOD_NOT_VALID_IN_THIS_STATE, None)
+        }
+        let mut rng = rand::thread_rng();
+        let stream = Stream { media, delivery, ssrc: rng.gen(), start_seq: rng.gen(), seq_offset: None, record };
+        let mut transport = transport;
+        if stream.delivery != Delivery::Multicast && !record {
+            transport.set("ssrc", Some(format!("{:08X}", stream.ssrc)));
+        }
+        let session = match session {
+            Some(session) => session,
+            None => {
+                let id = format!("{:016X}", rng.gen::<u64>());
+                self.events.push_back(ServerEvent::SessionCreated { session: id.clone(), path: self.mounts[mount].path.clone() });
+                self.sessions.push(Session {
+                    id,
+                    mount,
+                    connection,
+                    state: SessionState::Ready,
+                    streams: Vec::new(),
+                    channels: ChannelMap::new(),
+                    expires: now + self.timeout,
+                });
+                self.sessions.len() - 1
+            },
+        };
+        let state = &mut self.sessions[session];
+        state.streams.retain(|existing| existing.media != media);
+        state.streams.push(stream);
+        if let Some((rtp, rtcp)) = interleaved {
+            state.channels.insert(media, rtp, Some(rtcp));
+        }
+        let mut response = self.with_session(Response::new(Response::OK, None), session);
+        response.headers.set(Headers::TRANSPORT, transport);
+        response
+    }
+
+    // Delivery, response transport and interleaved channels for the first acceptable client specification.
+    fn negotiate(&self, connection: ConnectionId, peer: SocketAddr, mount: usize, media: usize, spec: &TransportSpec,
+        channels: &ChannelMap) -> Option<Negotiated> {
+        if !spec.protocol.to_ascii_uppercase().starts_with(TransportSpec::PROFILE) {
+            return None
+        }
+        // Published media is only received on the RTSP connection.
+        let record = spec.mode().is_some_and(|mode| mode.eq_ignore_ascii_case("record"));
+        if record && !(self.mounts[mount].announced && spec.is_tcp()) {
+            return None
+        }
+        if spec.is_tcp() {
+            let (rtp, rtcp) = match spec.interleaved() {
+                Some((rtp, rtcp)) => (rtp, rtcp.unwrap_or(rtp.wrapping_add(1))),
+                // Lowest free pair when the client leaves the choice to the server.
+                None => (0..=127u8).map(|pair| (2 * pair, 2 * pair + 1))
+                    .find(|(rtp, _)| InterleavedFrame::new(*rtp, Vec::new()).ok().and_then(|frame| channels.route(&frame)).is_none())?,
+            };
+            let mut transport = TransportSpec::tcp(rtp);
+            transport.set("interleaved", Some(format!("{}-{}", rtp, rtcp)));
+            if record {
+                transport.set("mode", Some("record".to_string()));
+            }
+            return Some((Delivery::Tcp { connection }, transport, Some((rtp, rtcp))))
+        }
+        if spec.is_multicast() {
+            let (group, port, ttl) = self.mounts[mount].multicast_group(media)?;
+            let mut transport = TransportSpec::multicast();
+            transport.set("destination", Some(group.to_string()));
+            transport.set("port", Some(format!("{}-{}", port, port.wrapping_add(1))));
+            transport.set("ttl", Some(ttl.to_string()));
+            return Some((Delivery::Multicast, transport, None))
+        }
+        let (server_rtp, server_rtcp) = self.server_ports?;
+        let (rtp, rtcp) = spec.client_port()?;
+        let rtcp = rtcp.unwrap_or(rtp.wrapping_add(1));
+        let mut transport = TransportSpec::udp(rtp);
+        transport.set("client_port", Some(format!("{}-{}", rtp, rtcp)));
+        transport.set("server_port", Some(format!("{}-{}", server_rtp, server_rtcp)));
+        let delivery = Delivery::Udp { rtp: SocketAddr::new(peer.ip(), rtp), rtcp: SocketAddr::new(peer.ip(), rtcp) };
+        Some((delivery, transport, None))
+    }
+
+    fn play(&mut self, request: &Request, session: usize) -> Response {
+        let base = request.uri.trim_end_matches('/').to_string();
+        let state = &mut self.sessions[session];
+        if state.streams.iter().any(|stream| stream.record) {
+            return Response::new(Response::METHOD_NOT_VALID_IN_THIS_STATE, None)
+        }
+        let Some(mount) = self.mounts.get(state.mount) else {
+            return Response::new(Response::NOT_FOUND, None)
+        };
+        let rtp_info = state.streams.iter()
+            .filter(|stream| stream.delivery != Delivery::Multicast)
+            .map(|stream| {
+                let url = format!("{}/{}", base, mount.controls.get(stream.media)?);
+                let last = mount.last.get(stream.media).copied().flatten();
+                // Next sequence number the client sees and timestamp of the latest packet of the source.
+                let seq = match (stream.seq_offset, last) {
+                    (Some(offset), Some((seq, _))) => seq.wrapping_add(offset).wrapping_add(1),
+                    _ => stream.start_seq,
+                };
+                Some(match last {
+                    Some((_, rtptime)) => format!("url={};seq={};rtptime={}", url, seq, rtptime),
+                    None => format!("url={};seq={}", url, seq),
+                })
+            })
+            .collect::<Option<Vec<_>>>();
+        let Some(rtp_info) = rtp_info else {
+            return Response::new(Response::NOT_FOUND, None)
+        };
+        if state.state != SessionState::Playing {
+            state.state = SessionState::Playing;
+            self.events.push_back(ServerEvent::Playing { session: state.id.clone() });
+        }
+        let mut response = self.with_session(Response::new(Response::OK, None), session);
+        response.headers.set(Headers::RANGE, "npt=now-");
+        if !rtp_info.is_empty() {
+            response.headers.set(Headers::RTP_INFO, rtp_info.join(","));
+        }
+        response
+    }
+
+    fn record(&mut self, session: usize) -> Response {
+        let state = &mut self.sessions[session];
+        if state.streams.is_empty() || state.streams.iter().any(|stream| !stream.record) {
+            return Response::new(Response::METHOD_NOT_VALID_IN_THIS_STATE, None)
+        }
+        if state.state != SessionState::Recording {
+            state.state = SessionState::Recording;
+            self.events.push_back(ServerEvent::Recording { session: state.id.clone() });
+        }
+        self.with_session(Response::new(Response::OK, None), session)
+    }
+}
+
+// Path of request URL without query and trailing slash.
+fn uri_path(uri: &str) -> &str {
+    let path = match uri.split_once("://") {
+        Some((_, rest)) => rest.find('/').map(|index| &rest[index..]).unwrap_or("/"),
+        None => uri,
+    };
+    path.split(['?', '#']).next().unwrap_or_default().trim_end_matches('/')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+    use crate::client::{ClientError, ClientEvent, RtspClient};
+    use crate::rtp::RtpPacketizer;
+    use crate::sdp::{Fmtp, RtpMap};
+    use crate::tunnel::HttpTunnelClient;
+
+    fn server() -> RtspServer {
+        let mut server = RtspServer::new(IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1)));
+        server.set_udp_ports(6970, 6971);
+        let mut mount = Mount::new("live", "Camera");
+        let mut video = MediaDescription::new("video", 0, "RTP/AVP", vec!["96".to_string()]);
+        video.attributes.push(Attribute::value("rtpmap", RtpMap { payload_type: 96, encoding: "H264".to_string(), clock_rate: 90000, channels: None }));
+        video.attributes.push(Attribute::value("fmtp", Fmtp { payload_type: 96, params: "packetization-mode=1".to_string() }));
+        mount.add_media(video);
+        mount.add_media(MediaDescription::new("audio", 0, "RTP/AVP", vec!["0".to_string()]));
+        mount.set_multicast(IpAddr::V4(Ipv4Addr::new(232, 0, 0, 1)), 5000, 16);
+        server.add_mount(mount);
+        server
+    }
+
+    fn request(server: &mut RtspServer, connection: ConnectionId, request: Request, now: Instant) -> Response {
+        server.handle_input(connection, &request.to_vec(), now).unwrap();
+        let Some(ServerTransmit::Tcp { data, .. }) = server.poll_transmit() else { panic!() };
+        let mut parser = RtspParser::new();
+        parser.push(&data);
+        let Some(RtspItem::Message(Message::Response(response))) = parser.poll_item().unwrap() else { panic!() };
+        response
+    }
+
+    fn with_header(mut request: Request, name: &str, value: &str) -> Request {
+        request.headers.set(name, value);
+        request
+    }
+
+    #[test]
+    fn describe_mount() {
+        let now = Instant::now();
+        let mut server = server();
+        let connection = server.connect("192.0.2.10:40000".parse().unwrap());
+        let response = request(&mut server, connection, Request::new(Method::Describe, "rtsp://192.0.2.1/live", 2), now);
+        assert_eq!(200, response.status);
+        assert_eq!(Some(2), response.cseq());
+        assert_eq!(Some("rtsp://192.0.2.1/live/"), response.headers.get(Headers::CONTENT_BASE));
+        let description = String::from_utf8(response.body).unwrap().parse::<SessionDescription>().unwrap();
+        assert_eq!(Some("*"), description.control());
+        assert_eq!(Some("stream=1"), description.media[1].control());
+        assert_eq!(Some("packetization-mode=1"), description.media[0].fmtp(96).map(|fmtp| fmtp.params).as_deref());
+        assert_eq!("232.0.0.1/16", description.media[0].connections[0].address);
+        assert_eq!(5002, description.media[1].port);
+
+        let response = request(&mut server, connection, Request::new(Method::Describe, "rtsp://192.0.2.1/other", 3), now);
+        assert_eq!(404, response.status);
+        let response = request(&mut server, connection, Request::new(Method::Options, "*", 4), now);
+        assert!(response.headers.get(Headers::PUBLIC).unwrap().contains("SETUP"));
+    }
+
+    #[test]
+    fn play_to_udp_and_tcp_clients() {
+        let now = Instant::now();
+        let mut server = server();
+        let udp = server.connect("192.0.2.10:40000".parse().unwrap());
+        let setup = Request::new(Method::Setup, "rtsp://192.0.2.1/live/stream=0", 1);
+        let response = request(&mut server, udp, with_header(setup, Headers::TRANSPORT, "RTP/AVP;unicast;client_port=5000-5001"), now);
+        assert_eq!(200, response.status);
+        let session = response.headers.get(Headers::SESSION).unwrap().parse::<SessionHeader>().unwrap();
+        assert_eq!(Some(60), session.timeout);
+        let transport = response.headers.get(Headers::TRANSPORT).unwrap().parse::<TransportSpec>().unwrap();
+        assert_eq!(Some((6970, Some(6971))), transport.server_port());
+        let udp_ssrc = transport.ssrc().unwrap();
+        assert_eq!(Some(ServerEvent::SessionCreated { session: session.id.clone(), path: "/live".to_string() }), server.poll_event());
+
+        let tcp = server.connect("192.0.2.11:40000".parse().unwrap());
+        let setup = Request::new(Method::Setup, "rtsp://192.0.2.1/live/stream=0", 1);
+        let response = request(&mut server, tcp, with_header(setup, Headers::TRANSPORT, "RTP/AVP/TCP;unicast"), now);
+        let tcp_session = response.headers.get(Headers::SESSION).unwrap().parse::<SessionHeader>().unwrap();
+        let transport = response.headers.get(Headers::TRANSPORT).unwrap().parse::<TransportSpec>().unwrap();
+        assert_eq!(Some((0, Some(1))), transport.interleaved());
+        let setup = with_header(Request::new(Method::Setup, "rtsp://192.0.2.1/live/stream=1", 2), Headers::SESSION, &tcp_session.id);
+        let response = request(&mut server, tcp, with_header(setup, Headers::TRANSPORT, "RTP/AVP/TCP;unicast"), now);
+        let transport = response.headers.get(Headers::TRANSPORT).unwrap().parse::<TransportSpec>().unwrap();
+        assert_eq!(Some((2, Some(3))), transport.interleaved());
+
+        let packet = RtpPacket::new(true, 96, 100, 9000, 0xaaaa, &[1, 2, 3]);
+        server.send_rtp("/live", 0, &packet);
+        assert!(server.poll_transmit().is_none());
+
+        let play = with_header(Request::new(Method::Play, "rtsp://192.0.2.1/live/", 2), Headers::SESSION, &session.id);
+        let response = request(&mut server, udp, play, now);
+        assert_eq!(200, response.status);
+        let rtp_info = response.headers.get(Headers::RTP_INFO).unwrap();
+        assert!(rtp_info.starts_with("url=rtsp://192.0.2.1/live/stream=0;seq="));
+        assert!(rtp_info.ends_with(";rtptime=9000"));
+        let play = with_header(Request::new(Method::Play, "rtsp://192.0.2.1/live/", 3), Headers::SESSION, &tcp_session.id);
+        request(&mut server, tcp, play, now);
+
+        server.send_rtp("/live", 0, &RtpPacket::new(true, 96, 101, 12000, 0xaaaa, &[4, 5]));
+        server.send_rtp("/live", 0, &RtpPacket::new(true, 96, 103, 15000, 0xaaaa, &[6]));
+        let mut udp_seqs = Vec::new();
+        let mut tcp_seqs = Vec::new();
+        while let Some(transmit) = server.poll_transmit() {
+            match transmit {
+                ServerTransmit::Udp { destination, kind, data } => {
+                    assert_eq!("192.0.2.10:5000".parse::<SocketAddr>().unwrap(), destination);
+                    assert_eq!(PacketKind::Rtp, kind);
+                    let packet = RtpPacket::from_slice(&data).unwrap();
+                    assert_eq!(udp_ssrc, packet.ssrc());
+                    udp_seqs.push(packet.seq_number());
+                },
+                ServerTransmit::Tcp { connection, data } => {
+                    assert_eq!(tcp, connection);
+                    let mut parser = RtspParser::new();
+                    parser.push(&data);
+                    let Some(RtspItem::Interleaved(frame)) = parser.poll_item().unwrap() else { panic!() };
+                    assert_eq!(0, frame.channel());
+                    tcp_seqs.push(frame.rtp().unwrap().seq_number());
+                },
+            }
+        }
+        assert_eq!(2, udp_seqs.len());
+        assert_eq!(udp_seqs[0].wrapping_add(2), udp_seqs[1]);
+        assert_eq!(tcp_seqs[0].wrapping_add(2), tcp_seqs[1]);
+
+        server.disconnect(tcp);
+        let events = std::iter::from_fn(|| server.poll_event()).collect::<Vec<_>>();
+        assert!(events.contains(&ServerEvent::SessionClosed { session: tcp_session.id.clone(), reason: CloseReason::Disconnected }));
+    }
+
+    #[test]
+    fn expire_and_reject_sessions() {
+        let now = Instant::now();
+        let mut server = server();
+        server.set_session_timeout(Duration::from_secs(30));
+        let connection = server.connect("192.0.2.10:40000".parse().unwrap());
+        let setup = Request::new(Method::Setup, "rtsp://192.0.2.1/live/stream=1", 1);
+        let response = request(&mut server, connection, with_header(setup, Headers::TRANSPORT, "RTP/AVP;multicast"), now);
+        let session = response.headers.get(Headers::SESSION).unwrap().parse::<SessionHeader>().unwrap();
+        let transport = response.headers.get(Headers::TRANSPORT).unwrap().parse::<TransportSpec>().unwrap();
+        assert_eq!(Some("232.0.0.1"), transport.destination());
+        assert_eq!(Some((5002, Some(5003))), transport.port());
+        assert_eq!(Some(now + Duration::from_secs(30)), server.poll_timeout());
+
+        let keepalive = with_header(Request::new(Method::GetParameter, "rtsp://192.0.2.1/live/", 2), Headers::SESSION, &session.id);
+        request(&mut server, connection, keepalive, now + Duration::from_secs(20));
+        server.handle_timeout(now + Duration::from_secs(30));
+        assert_eq!(Some(SessionState::Ready), server.session_state(&session.id));
+        server.handle_timeout(now + Duration::from_secs(50));
+        assert_eq!(None, server.session_state(&session.id));
+
+        let play = with_header(Request::new(Method::Play, "rtsp://192.0.2.1/live/", 3), Headers::SESSION, &session.id);
+        assert_eq!(454, request(&mut server, connection, play, now).status);
+        let setup = Request::new(Method::Setup, "rtsp://192.0.2.1/live/stream=0", 4);
+        assert_eq!(461, request(&mut server, connection, with_header(setup, Headers::TRANSPORT, "RTP/SAVP;unicast;client_port=5000-5001"), now).status);
+        let setup = Request::new(Method::Setup, "rtsp://192.0.2.1/live/stream=7", 5);
+        assert_eq!(404, request(&mut server, connection, with_header(setup, Headers::TRANSPORT, "RTP/AVP;unicast;client_port=5000-5001"), now).status);
+        assert_eq!(454, request(&mut server, connection, Request::new(Method::Record, "rtsp://192.0.2.1/live/", 6), now).status);
+        assert_eq!(501, request(&mut server, connection, Request::new(Method::Redirect, "rtsp://192.0.2.1/live/", 6), now).status);
+        let mut options = Request::new(Method::Options, "*", 7);
+        options.version = RtspVersion::V2_0;
+        assert_eq!(505, request(&mut server, connection, options, now).status);
+    }
+
+    #[test]
+    fn close_sessions_of_replaced_mount() {
+        let now = Instant::now();
+        let mut server = server();
+        let connection = server.connect("192.0.2.10:40000".parse().unwrap());
+        let setup = Request::new(Method::Setup, "rtsp://192.0.2.1/live/stream=1", 1);
+        let response = request(&mut server, connection, with_header(setup, Headers::TRANSPORT, "RTP/AVP/TCP;unicast"), now);
+        let session = response.headers.get(Headers::SESSION).unwrap().parse::<SessionHeader>().unwrap();
+        assert!(matches!(server.poll_event(), Some(ServerEvent::SessionCreated { .. })));
+
+        let mut mount = Mount::new("live", "Camera");
+        mount.add_media(MediaDescription::new("video", 0, "RTP/AVP", vec!["96".to_string()]));
+        server.add_mount(mount);
+        assert_eq!(Some(ServerEvent::SessionClosed { session: session.id.clone(), reason: CloseReason::Replaced }), server.poll_event());
+        let play = with_header(Request::new(Method::Play, "rtsp://192.0.2.1/live/", 2), Headers::SESSION, &session.id);
+        assert_eq!(454, request(&mut server, connection, play, now).status);
+    }
+
+    #[test]
+    fn play_over_http_tunnel() {
+        let now = Instant::now();
+        let mut server = server();
+        let mut tunnel = HttpTunnelClient::new("rtsp://192.0.2.1/live");
+        let mut client = RtspClient::new("rtsp://192.0.2.1/live");
+        let peer = "192.0.2.10:40000".parse().unwrap();
+        let get = server.connect(peer);
+        server.handle_input(get, &tunnel.get_request(), now).unwrap();
+        let post = server.connect(peer);
+        server.handle_input(post, &tunnel.post_request(), now).unwrap();
+
+        let exchange = |server: &mut RtspServer, client: &mut RtspClient, tunnel: &mut HttpTunnelClient| {
+            while let Some(data) = client.poll_transmit() {
+                server.handle_input(post, &tunnel.encode(&data), now).unwrap();
+            }
+            while let Some(transmit) = server.poll_transmit() {
+                let ServerTransmit::Tcp { connection, data } = transmit else { panic!() };
+                assert_eq!(get, connection);
+                client.handle_input(&tunnel.handle_input(&data).unwrap(), now).unwrap();
+            }
+        };
+        client.describe();
+        exchange(&mut server, &mut client, &mut tunnel);
+        assert!(tunnel.is_established());
+        assert!(matches!(client.poll_event(), Some(ClientEvent::Described(_))));
+        client.setup(0, TransportSpec::tcp(0)).unwrap();
+        exchange(&mut server, &mut client, &mut tunnel);
+        assert!(matches!(client.poll_event(), Some(ClientEvent::SetUp { stream: 0, .. })));
+        client.play(None).unwrap();
+        exchange(&mut server, &mut client, &mut tunnel);
+        assert!(matches!(client.poll_event(), Some(ClientEvent::Playing { .. })));
+
+        // POST connection may close between requests without ending the session.
+        server.disconnect(post);
+        server.send_rtp("/live", 0, &RtpPacket::new(true, 96, 7, 9000, 0xaaaa, &[1, 2, 3]));
+        exchange(&mut server, &mut client, &mut tunnel);
+        let Some(ClientEvent::Frame { stream: 0, kind: PacketKind::Rtp, frame }) = client.poll_event() else { panic!() };
+        assert_eq!(&[1, 2, 3], frame.rtp().unwrap().payload());
+
+        let stranger = server.connect(peer);
+        let mut other = HttpTunnelClient::new("rtsp://192.0.2.1/live");
+        assert!(matches!(server.handle_input(stranger, &other.post_request(), now), Err(RtspError::Tunnel(TunnelError::UnknownCookie(_)))));
+        let Some(ServerTransmit::Tcp { data, .. }) = server.poll_transmit() else { panic!() };
+        assert!(matches!(other.handle_input(&data), Err(TunnelError::Status(400))));
+    }
+
+    // Passes data both ways until neither side has anything to send.
+    fn exchange(server: &mut RtspServer, connection: ConnectionId, client: &mut RtspClient, now: Instant) {
+        while let Some(data) = client.poll_transmit() {
+            server.handle_input(connection, &data, now).unwrap();
+            while let Some(transmit) = server.poll_transmit() {
+                let ServerTransmit::Tcp { data, .. } = transmit else { panic!() };
+                client.handle_input(&data, now).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn publish_with_record() {
+        let now = Instant::now();
+        let mut server = server();
+        let mut video = MediaDescription::new("video", 0, "RTP/AVP", vec!["96".to_string()]);
+        video.attributes.push(Attribute::value("rtpmap", RtpMap { payload_type: 96, encoding: "H264".to_string(), clock_rate: 90000, channels: None }));
+        let description = SessionDescription { name: Some("Publisher".to_string()), media: vec![video], ..Default::default() };
+
+        let connection = server.connect("192.0.2.20:40000".parse().unwrap());
+        let mut client = RtspClient::new("rtsp://192.0.2.1/live");
+        client.announce(&description).unwrap();
+        exchange(&mut server, connection, &mut client, now);
+        assert!(matches!(client.poll_event(), Some(ClientEvent::Failed { method: Method::Announce, error: ClientError::Forbidden })));
+
+        let mut client = RtspClient::new("rtsp://192.0.2.1/cam");
+        client.announce(&description).unwrap();
+        let mut transport = TransportSpec::tcp(0);
+        transport.set("mode", Some("record".to_string()));
+        client.setup(0, transport).unwrap();
+        client.record(None).unwrap();
+        exchange(&mut server, connection, &mut client, now);
+        let events = std::iter::from_fn(|| client.poll_event()).collect::<Vec<_>>();
+        assert!(matches!(&events[..], [ClientEvent::Announced, ClientEvent::SetUp { .. }, ClientEvent::Recording]));
+        assert_eq!(Some("streamid=0"), server.mount("/cam").unwrap().session_description(server.address).media[0].control());
+        assert!(server.mount("/cam").unwrap().is_announced());
+
+        let mut packetizer = RtpPacketizer::new(100, 96, 0x1234);
+        let payload = vec![7; 250];
+        for packet in packetizer.packetize(&payload, 3000) {
+            client.send_rtp(0, &packet).unwrap();
+        }
+        exchange(&mut server, connection, &mut client, now);
+        let events = std::iter::from_fn(|| server.poll_event()).collect::<Vec<_>>();
+        let received = events.iter()
+            .filter_map(|event| match event {
+                ServerEvent::Received { path, media: 0, kind: PacketKind::Rtp, data } if path == "/cam" => Some(data),
+                _ => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(3, received.len());
+        let packet = RtpPacket::from_slice(received[2]).unwrap();
+        assert!(packet.mark());
+        assert_eq!(0x1234, packet.ssrc());
+        assert!(events.contains(&ServerEvent::Announced { path: "/cam".to_string() }));
+
+        // A second publisher must wait for the first one to leave.
+        let other = server.connect("192.0.2.21:40000".parse().unwrap());
+        let mut announce = with_header(Request::new(Method::Announce, "rtsp://192.0.2.1/cam", 1), Headers::CONTENT_TYPE, "application/sdp");
+        announce.body = description.to_string().into_bytes();
+        assert_eq!(455, request(&mut server, other, announce, now).status);
+        // Readers cannot record to configured mounts.
+        let setup = Request::new(Method::Setup, "rtsp://192.0.2.1/live/stream=0", 2);
+        assert_eq!(461, request(&mut server, other, with_header(setup, Headers::TRANSPORT, "RTP/AVP/TCP;unicast;mode=record"), now).status);
+    }
+
+    #[test]
+    fn keep_announced_mount_while_playing() {
+        let now = Instant::now();
+        let mut server = server();
+        let announce = |uri: &str, media: usize, cseq: u32| {
+            let media = (0..media).map(|_| MediaDescription::new("audio", 0, "RTP/AVP", vec!["0".to_string()])).collect();
+            let description = SessionDescription { media, ..Default::default() };
+            let mut announce = with_header(Request::new(Method::Announce, uri, cseq), Headers::CONTENT_TYPE, "application/sdp");
+            announce.body = description.to_string().into_bytes();
+            announce
+        };
+        let publisher = server.connect("192.0.2.20:40000".parse().unwrap());
+        assert_eq!(200, request(&mut server, publisher, announce("rtsp://192.0.2.1/cam", 2, 1), now).status);
+        let player = server.connect("192.0.2.21:40000".parse().unwrap());
+        let setup = Request::new(Method::Setup, "rtsp://192.0.2.1/cam/stream=1", 1);
+        let response = request(&mut server, player, with_header(setup, Headers::TRANSPORT, "RTP/AVP/TCP;unicast"), now);
+        let session = response.headers.get(Headers::SESSION).unwrap().parse::<SessionHeader>().unwrap();
+
+        // Replacing the media would leave the player with streams the mount no longer has.
+        assert_eq!(455, request(&mut server, publisher, announce("rtsp://192.0.2.1/cam", 1, 2), now).status);
+        let play = with_header(Request::new(Method::Play, "rtsp://192.0.2.1/cam/", 2), Headers::SESSION, &session.id);
+        assert_eq!(200, request(&mut server, player, play, now).status);
+        let teardown = with_header(Request::new(Method::Teardown, "rtsp://192.0.2.1/cam/", 3), Headers::SESSION, &session.id);
+        assert_eq!(200, request(&mut server, player, teardown, now).status);
+        assert_eq!(200, request(&mut server, publisher, announce("rtsp://192.0.2.1/cam", 1, 3), now).status);
+        assert_eq!(1, server.mount("/cam").unwrap().media().len());
+    }
+
+    #[test]
+    fn hold_back_interleaved_data() {
+        let now = Instant::now();
+        let mut client = RtspClient::new("rtsp://192.0.2.1/cam");
+        let description = SessionDescription { media: vec![MediaDescription::new("audio", 0, "RTP/AVP", vec!["0".to_string()])], ..Default::default() };
+        let mut server = server();
+        let connection = server.connect("192.0.2.20:40000".parse().unwrap());
+        client.announce(&description).unwrap();
+        let mut transport = TransportSpec::tcp(0);
+        transport.set("mode", Some("record".to_string()));
+        client.setup(0, transport).unwrap();
+        exchange(&mut server, connection, &mut client, now);
+
+        client.set_send_buffer(1000);
+        let packet = RtpPacket::new(false, 0, 1, 160, 0x1234, &[0xff; 160]);
+        let len = InterleavedFrame::HEADER_SIZE + packet.to_vec().len();
+        for _ in 0..1000 / len {
+            client.send_rtp(0, &packet).unwrap();
+        }
+        assert!(matches!(client.send_rtp(0, &packet), Err(ClientError::SendBufferFull)));
+        assert_eq!(1000 / len * len, client.buffered());
+        client.poll_transmit().unwrap();
+        client.send_rtp(0, &packet).unwrap();
+        assert!(matches!(client.send_rtp(1, &packet), Err(ClientError::UnknownStream(1))));
+    }
+}
@@ -0,0 +1,429 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use rand::Rng;
+
+use crate::rtcp::{DataBYE, DataRR, DataSDES, RtcpPacket, SdesChunk};
+use crate::rtp::RtpPacket;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum SessionEvent {
+    NewMember(u32),
+    MemberBye(u32),
+    MemberTimeout(u32),
+    // Local SSRC collided with a remote participant and was replaced, BYE for the old one is queued.
+    SsrcChanged { old: u32, new: u32 },
+    // Packets sent by this participant came back from given address.
+    LoopDetected(SocketAddr),
+    // Remote SSRC is used from more than one address, either a collision between other participants or a loop.
+    ThirdPartyConflict { ssrc: u32, source: SocketAddr },
+}
+
+// Member is an entry of the source identifier table.  Data and control packets of the same source normally
+// arrive from different transport addresses, so both are kept.
+#[derive(Clone, Debug)]
+pub struct Member {
+    ssrc: u32,
+    cname: Option<String>,
+    rtp_source: Option<SocketAddr>,
+    rtcp_source: Option<SocketAddr>,
+    last_seen: Instant,
+    last_rtp: Option<Instant>,
+}
+
+impl Member {
+    fn new(ssrc: u32, now: Instant) -> Member {
+        Member { ssrc, cname: None, rtp_source: None, rtcp_source: None, last_seen: now, last_rtp: None }
+    }
+
+    pub fn ssrc(&self) -> u32 {
+        self.ssrc
+    }
+
+    pub fn cname(&self) -> Option<&str> {
+        self.cname.as_deref()
+    }
+
+    pub fn rtp_source(&self) -> Option<SocketAddr> {
+        self.rtp_source
+    }
+
+    pub fn rtcp_source(&self) -> Option<SocketAddr> {
+        self.rtcp_source
+    }
+
+    pub fn last_seen(&self) -> Instant {
+        self.last_seen
+    }
+
+    // A member is a sender while it keeps sending RTP.
+    pub fn is_sender(&self) -> bool {
+        self.last_rtp.is_some()
+    }
+}
+
+// RtpSession keeps track of local and remote sources of an RTP session and resolves SSRC collisions and
+// loops following the algorithm of RFC 3550 section 8.2.  It does not perform any I/O: BYE packets for
+// replaced SSRCs are queued for the caller to pick up with poll_transmit().
+pub struct RtpSession {
+    cname: String,
+    local: Vec<u32>,
+    members: HashMap<u32, Member>,
+    conflicts: Vec<(SocketAddr, Instant)>,
+    events: VecDeque<SessionEvent>,
+    transmits: VecDeque<Vec<u8>>,
+}
+
+impl RtpSession {
+    // Members are timed out after this many deterministic report intervals of silence.
+    const MEMBER_TIMEOUT: u32 = 5;
+    // Senders that have not sent RTP for this many intervals become receivers.
+    const SENDER_TIMEOUT: u32 = 2;
+    // Entries of the conflicting address list expire after this many intervals.
+    const CONFLICT_TIMEOUT: u32 = 10;
+
+    pub fn new(cname: impl Into<String>) -> Self {
+        RtpSession {
+            cname: cname.into(),
+            local: Vec::new(),
+            members: HashMap::new(),
+            conflicts: Vec::new(),
+            events: VecDeque::new(),
+            transmits: VecDeque::new(),
+        }
+    }
+
+    pub fn cname(&self) -> &str {
+        &self.cname
+    }
+
+    // Allocates a new random local SSRC that does not clash with any known source.
+    pub fn add_local_ssrc(&mut self) -> u32 {
+        let ssrc = self.random_ssrc();
+        self.local.push(ssrc);
+        ssrc
+    }
+
+    pub fn remove_local_ssrc(&mut self, ssrc: u32) {
+        self.local.retain(|local| *local != ssrc);
+    }
+
+    pub fn local_ssrcs(&self) -> &[u32] {
+        &self.local
+    }
+
+    pub fn is_local(&self, ssrc: u32) -> bool {
+        self.local.contains(&ssrc)
+    }
+
+    pub fn member(&self, ssrc: u32) -> Option<&Member> {
+        self.members.get(&ssrc)
+    }
+
+    pub fn members(&self) -> impl Iterator<Item = &Member> {
+        self.members.values()
+    }
+
+    // Number of members including local sources, as used for RTCP interval computation.
+    pub fn member_count(&self) -> usize {
+        self.members.len() + self.local.len()
+    }
+
+    // Number of remote members that are currently senders.
+    pub fn sender_count(&self) -> usize {
+        self.members.values().filter(|member| member.is_sender()).count()
+    }
+
+    // Validates source of the RTP packet and updates the member table.  Returns false if the packet must be
+    // discarded because of a loop or a third-party collision.
+    pub fn receive_rtp(&mut self, packet: &RtpPacket, source: SocketAddr, now: Instant) -> bool {
+        if !self.check_source(packet.ssrc(), source, false, None, now) {
+            return false
+        }
+        if let Some(member) = self.members.get_mut(&packet.ssrc()) {
+            member.last_rtp = Some(now);
+        }
+        for csrc in packet.csrc() {
+            if self.is_local(*csrc) {
+                continue
+            }
+            self.members.entry(*csrc)
+                .or_insert_with(|| {
+                    self.events.push_back(SessionEvent::NewMember(*csrc));
+                    Member::new(*csrc, now)
+                })
+                .last_seen = now;
+        }
+        true
+    }
+
+    // Validates source of the compound RTCP packet and updates the member table from SDES and BYE packets.
+    // Returns false if the packet must be discarded because of a loop or a third-party collision.
+    pub fn receive_rtcp(&mut self, packets: &[RtcpPacket], source: SocketAddr, now: Instant) -> bool {
+        let descriptions: Vec<DataSDES> = packets.iter()
+            .filter_map(|packet| DataSDES::from_packet(packet).ok())
+            .collect();
+        let cname_of = |ssrc: u32| descriptions.iter()
+            .flat_map(|sdes| sdes.chunks().iter())
+            .find(|chunk| chunk.ssrc() == ssrc)
+            .and_then(|chunk| chunk.item(DataSDES::CNAME));
+
+        // Every RTCP packet type except SDES and BYE starts with SSRC of the packet sender.
+        let sender = packets.iter()
+            .find(|packet| packet.payload_type() != RtcpPacket::SDES && packet.payload_type() != RtcpPacket::BYE)
+            .filter(|packet| packet.payload().len() >= 4)
+            .map(|packet| {
+                let slice = packet.payload();
+                u32::from_be_bytes([slice[0], slice[1], slice[2], slice[3]])
+            })
+            .or_else(|| descriptions.first().and_then(|sdes| sdes.chunks().first()).map(|chunk| chunk.ssrc()));
+        if let Some(ssrc) = sender {
+            if !self.check_source(ssrc, source, true, cname_of(ssrc), now) {
+                return false
+            }
+        }
+
+        for chunk in descriptions.iter().flat_map(|sdes| sdes.chunks().iter()) {
+            if self.is_local(chunk.ssrc()) {
+                continue
+            }
+            let member = self.members.entry(chunk.ssrc()).or_insert_with(|| {
+                self.events.push_back(SessionEvent::NewMember(chunk.ssrc()));
+                Member::new(chunk.ssrc(), now)
+            });
+            member.last_seen = now;
+            if let Some(cname) = chunk.item(DataSDES::CNAME) {
+                member.cname = Some(String::from_utf8_lossy(cname).into_owned());
+            }
+        }
+
+        for bye in packets.iter().filter_map(|packet| DataBYE::from_packet(packet).ok()) {
+            for ssrc in bye.sources() {
+                if self.members.remove(ssrc).is_some() {
+                    self.events.push_back(SessionEvent::MemberBye(*ssrc));
+                }
+            }
+        }
+        true
+    }
+
+    // Times out silent members and senders given the deterministic RTCP interval (RFC 3550 section 6.3.5).
+    pub fn handle_timeout(&mut self, now: Instant, interval: Duration) {
+        let member_timeout = interval * RtpSession::MEMBER_TIMEOUT;
+        let sender_timeout = interval * RtpSession::SENDER_TIMEOUT;
+        let conflict_timeout = interval * RtpSession::CONFLICT_TIMEOUT;
+        let events = &mut self.events;
+        self.members.retain(|ssrc, member| {
+            if now.saturating_duration_since(member.last_seen) > member_timeout {
+                events.push_back(SessionEvent::MemberTimeout(*ssrc));
+                return false
+            }
+            if member.last_rtp.is_some_and(|last| now.saturating_duration_since(last) > sender_timeout) {
+                member.last_rtp = None;
+            }
+            true
+        });
+        self.conflicts.retain(|(_, time)| now.saturating_duration_since(*time) <= conflict_timeout);
+    }
+
+    pub fn poll_event(&mut self) -> Option<SessionEvent> {
+        self.events.pop_front()
+    }
+
+    // Returns next queued RTCP datagram, such as BYE for a replaced local SSRC.
+    pub fn poll_transmit(&mut self) -> Option<Vec<u8>> {
+        self.transmits.pop_front()
+    }
+
+    fn check_source(&mut self, ssrc: u32, source: SocketAddr, rtcp: bool, cname: Option<&[u8]>, now: Instant) -> bool {
+        if self.is_local(ssrc) {
+            if let Some((_, time)) = self.conflicts.iter_mut().find(|(addr, _)| *addr == source) {
+                // Own traffic looped back, already known.
+                *time = now;
+                return false
+            }
+            if cname == Some(self.cname.as_bytes()) {
+                self.conflicts.push((source, now));
+                self.events.push_back(SessionEvent::LoopDetected(source));
+                return false
+            }
+            self.conflicts.push((source, now));
+            self.replace_local_ssrc(ssrc);
+            let mut member = Member::new(ssrc, now);
+            self.set_source(&mut member, source, rtcp);
+            self.members.insert(ssrc, member);
+            self.events.push_back(SessionEvent::NewMember(ssrc));
+            return true
+        }
+
+        match self.members.get_mut(&ssrc) {
+            None => {
+                let mut member = Member::new(ssrc, now);
+                self.set_source(&mut member, source, rtcp);
+                self.members.insert(ssrc, member);
+                self.events.push_back(SessionEvent::NewMember(ssrc));
+                true
+            },
+            Some(member) => {
+                let known = if rtcp { &mut member.rtcp_source } else { &mut member.rtp_source };
+                match known {
+                    Some(addr) if *addr != source => {
+                        self.events.push_back(SessionEvent::ThirdPartyConflict { ssrc, source });
+                        false
+                    },
+                    _ => {
+                        *known = Some(source);
+                        member.last_seen = now;
+                        true
+                    },
+                }
+            },
+        }
+    }
+
+    fn set_source(&self, member: &mut Member, source: SocketAddr, rtcp: bool) {
+        if rtcp {
+            member.rtcp_source = Some(source);
+        } else {
+            member.rtp_source = Some(source);
+        }
+    }
+
+    // Picks a new SSRC for colliding local source and queues BYE for the old one.
+    fn replace_local_ssrc(&mut self, old: u32) {
+        let new = self.random_ssrc();
+        for local in self.local.iter_mut().filter(|local| **local == old) {
+            *local = new;
+        }
+        let mut datagram = DataRR::new(old, Vec::new()).to_vec();
+        datagram.extend(DataSDES::new(vec![SdesChunk::new(old, vec![(DataSDES::CNAME, self.cname.as_bytes())])]).to_vec());
+        datagram.extend(DataBYE::new(vec![old], None).to_vec());
+        self.transmits.push_back(datagram);
+        self.events.push_back(SessionEvent::SsrcChanged { old, new });
+    }
+
+    fn random_ssrc(&self) -> u32 {
+        let mut rng = rand::thread_rng();
+        loop {
+            let ssrc = rng.gen::<u32>();
+            if !self.is_local(ssrc) && !self.members.contains_key(&ssrc) {
+                return ssrc
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rtcp::RtcpMode;
+
+    fn addr(port: u16) -> SocketAddr {
+        SocketAddr::from(([192, 168, 1, 10], port))
+    }
+
+    fn compound(ssrc: u32, cname: &[u8]) -> Vec<u8> {
+        let mut data = DataRR::new(ssrc, Vec::new()).to_vec();
+        data.extend(DataSDES::new(vec![SdesChunk::new(ssrc, vec![(DataSDES::CNAME, cname)])]).to_vec());
+        data
+    }
+
+    #[test]
+    fn track_new_members() {
+        let now = Instant::now();
+        let mut session = RtpSession::new("me@host");
+        session.add_local_ssrc();
+        let payload = [0u8; 4];
+        assert!(session.receive_rtp(&RtpPacket::new(false, 96, 1, 0, 0x1111, &payload), addr(5000), now));
+        assert_eq!(Some(SessionEvent::NewMember(0x1111)), session.poll_event());
+        let data = compound(0x1111, b"camera@host");
+        let packets = RtcpPacket::compound_from_slice(&data, RtcpMode::Compound).unwrap();
+        assert!(session.receive_rtcp(&packets, addr(5001), now));
+        assert_eq!(None, session.poll_event());
+        let member = session.member(0x1111).unwrap();
+        assert_eq!(Some("camera@host"), member.cname());
+        assert_eq!(Some(addr(5000)), member.rtp_source());
+        assert_eq!(Some(addr(5001)), member.rtcp_source());
+        assert!(member.is_sender());
+        assert_eq!(2, session.member_count());
+        assert_eq!(1, session.sender_count());
+    }
+
+    #[test]
+    fn resolve_local_collision() {
+        let now = Instant::now();
+        let mut session = RtpSession::new("me@host");
+        let old = session.add_local_ssrc();
+        let payload = [0u8; 4];
+        assert!(session.receive_rtp(&RtpPacket::new(false, 96, 1, 0, old, &payload), addr(5000), now));
+        let new = session.local_ssrcs()[0];
+        assert_ne!(old, new);
+        assert_eq!(Some(SessionEvent::SsrcChanged { old, new }), session.poll_event());
+        assert_eq!(Some(SessionEvent::NewMember(old)), session.poll_event());
+        assert_eq!(Some(addr(5000)), session.member(old).unwrap().rtp_source());
+
+        let data = session.poll_transmit().unwrap();
+        let packets = RtcpPacket::compound_from_slice(&data, RtcpMode::Compound).unwrap();
+        assert_eq!(3, packets.len());
+        assert_eq!(&[old][..], DataBYE::from_packet(&packets[2]).unwrap().sources());
+
+        // Our new SSRC comes back from the conflicting address, it is a loop.
+        assert!(!session.receive_rtp(&RtpPacket::new(false, 96, 1, 0, new, &payload), addr(5000), now));
+        assert_eq!(&[new][..], session.local_ssrcs());
+        assert_eq!(None, session.poll_transmit());
+    }
+
+    #[test]
+    fn detect_loop_by_cname() {
+        let now = Instant::now();
+        let mut session = RtpSession::new("me@host");
+        let local = session.add_local_ssrc();
+        let data = compound(local, b"me@host");
+        let packets = RtcpPacket::compound_from_slice(&data, RtcpMode::Compound).unwrap();
+        assert!(!session.receive_rtcp(&packets, addr(6001), now));
+        assert_eq!(Some(SessionEvent::LoopDetected(addr(6001))), session.poll_event());
+        assert_eq!(&[local][..], session.local_ssrcs());
+        assert!(!session.receive_rtcp(&packets, addr(6001), now));
+        assert_eq!(None, session.poll_event());
+    }
+
+    #[test]
+    fn detect_third_party_conflict() {
+        let now = Instant::now();
+        let mut session = RtpSession::new("me@host");
+        let payload = [0u8; 4];
+        assert!(session.receive_rtp(&RtpPacket::new(false, 96, 1, 0, 0x2222, &payload), addr(5000), now));
+        assert!(!session.receive_rtp(&RtpPacket::new(false, 96, 2, 0, 0x2222, &payload), addr(7000), now));
+        assert_eq!(Some(SessionEvent::NewMember(0x2222)), session.poll_event());
+        assert_eq!(Some(SessionEvent::ThirdPartyConflict { ssrc: 0x2222, source: addr(7000) }), session.poll_event());
+    }
+
+    #[test]
+    fn remove_members_on_bye_and_timeout() {
+        let start = Instant::now();
+        let mut session = RtpSession::new("me@host");
+        let payload = [0u8; 4];
+        session.receive_rtp(&RtpPacket::new(false, 96, 1, 0, 0x1111, &payload), addr(5000), start);
+        session.receive_rtp(&RtpPacket::new(false, 96, 1, 0, 0x2222, &payload), addr(5002), start);
+        let data = compound(0x3333, b"viewer@host");
+        let packets = RtcpPacket::compound_from_slice(&data, RtcpMode::Compound).unwrap();
+        session.receive_rtcp(&packets, addr(5005), start + Duration::from_secs(20));
+        while session.poll_event().is_some() {}
+
+        let interval = Duration::from_secs(5);
+        session.handle_timeout(start + Duration::from_secs(12), interval);
+        assert!(!session.member(0x1111).unwrap().is_sender());
+        assert_eq!(None, session.poll_event());
+        session.handle_timeout(start + Duration::from_secs(26), interval);
+        let mut events = vec![session.poll_event().unwrap(), session.poll_event().unwrap()];
+        events.sort_by_key(|event| format!("{:?}", event));
+        assert_eq!(vec![SessionEvent::MemberTimeout(0x1111), SessionEvent::MemberTimeout(0x2222)], events);
+
+        let mut data = DataRR::new(0x3333, Vec::new()).to_vec();
+        data.extend(DataBYE::new(vec![0x3333], None).to_vec());
+        let packets = RtcpPacket::compound_from_slice(&data, RtcpMode::Compound).unwrap();
+        assert!(session.receive_rtcp(&packets, addr(5005), start + Duration::from_secs(30)));
+        assert_eq!(Some(SessionEvent::MemberBye(0x3333)), session.poll_event());
+        assert_eq!(0, session.member_count());
+    }
+}
@@ -0,0 +1,596 @@
+use std::collections::HashMap;
+use aes::{Aes128, Aes256};
+use aes::cipher::{KeyIvInit, StreamCipher};
+use aes_gcm::{AeadInPlace, Aes128Gcm, Aes256Gcm, KeyInit, Nonce, Tag};
+use hmac::{Hmac, Mac};
+use sha1::Sha1;
+
+type Aes128Ctr = ctr::Ctr128BE<Aes128>;
+type Aes256Ctr = ctr::Ctr128BE<Aes256>;
+type HmacSha1 = Hmac<Sha1>;
+
+#[derive(Debug)]
+pub enum SrtpError {
+    InvalidLen(usize),
+    InvalidKeyLen(usize),
+    AuthFailed,
+    Replayed(u64),
+}
+
+// SrtpProfile is a combination of cipher and authentication transforms with their key and tag sizes.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SrtpProfile {
+    Aes128CmHmacSha1_80,
+    Aes128CmHmacSha1_32,
+    AeadAes128Gcm,
+    AeadAes256Gcm,
+}
+
+impl SrtpProfile {
+    pub fn key_len(&self) -> usize {
+        match self {
+            SrtpProfile::AeadAes256Gcm => 32,
+            _ => 16,
+        }
+    }
+
+    pub fn salt_len(&self) -> usize {
+        if self.is_aead() { 12 } else { 14 }
+    }
+
+    pub fn is_aead(&self) -> bool {
+        matches!(self, SrtpProfile::AeadAes128Gcm | SrtpProfile::AeadAes256Gcm)
+    }
+
+    // Length of authentication tag appended to SRTP packets.
+    pub fn rtp_tag_len(&self) -> usize {
+        match self {
+            SrtpProfile::Aes128CmHmacSha1_80 => 10,
+            SrtpProfile::Aes128CmHmacSha1_32 => 4,
+            _ => 16,
+        }
+    }
+
+    // Length of authentication tag appended to SRTCP packets, short tags are never used for RTCP (RFC 5764).
+    pub fn rtcp_tag_len(&self) -> usize {
+        match self {
+            SrtpProfile::Aes128CmHmacSha1_80 | SrtpProfile::Aes128CmHmacSha1_32 => 10,
+            _ => 16,
+        }
+    }
+
+    fn auth_key_len(&self) -> usize {
+        if self.is_aead() { 0 } else { 20 }
+    }
+}
+
+// Session keys derived from master key and salt for either SRTP or SRTCP.
+struct SessionKeys {
+    enc: Vec<u8>,
+    auth: Vec<u8>,
+    salt: Vec<u8>,
+}
+
+impl SessionKeys {
+    fn derive(profile: SrtpProfile, master_key: &[u8], master_salt: &[u8], label: u8) -> SessionKeys {
+        SessionKeys {
+            enc: kdf(master_key, master_salt, label, profile.key_len()),
+            auth: kdf(master_key, master_salt, label + 1, profile.auth_key_len()),
+            salt: kdf(master_key, master_salt, label + 2, profile.salt_len()),
+        }
+    }
+}
+
+// AES-CM pseudo random function of RFC 3711 section 4.3 with key derivation rate 0.  Shorter master salt of
+// AEAD profiles is padded with zeros on the right (RFC 7714 section 11).
+fn kdf(master_key: &[u8], master_salt: &[u8], label: u8, len: usize) -> Vec<u8> {
+    let mut iv = [0u8; 16];
+    iv[..master_salt.len()].copy_from_slice(master_salt);
+    iv[7] ^= label;
+    let mut out = vec![0u8; len];
+    aes_cm(master_key, &iv, &mut out);
+    out
+}
+
+fn aes_cm(key: &[u8], iv: &[u8; 16], data: &mut [u8]) {
+    if key.len() == 32 {
+        Aes256Ctr::new(key.into(), iv.into()).apply_keystream(data);
+    } else {
+        Aes128Ctr::new(key.into(), iv.into()).apply_keystream(data);
+    }
+}
+
+// Counter mode IV of RFC 3711 section 4.1.1: session salt XOR SSRC XOR packet index, shifted by 16 bits.
+fn cm_iv(salt: &[u8], ssrc: u32, index: u64) -> [u8; 16] {
+    let mut iv = [0u8; 16];
+    iv[..14].copy_from_slice(&salt[..14]);
+    for (byte, value) in iv[4..8].iter_mut().zip(ssrc.to_be_bytes()) {
+        *byte ^= value;
+    }
+    for (byte, value) in iv[8..14].iter_mut().zip(&index.to_be_bytes()[2..]) {
+        *byte ^= value;
+    }
+    iv
+}
+
+// GCM IV of RFC 7714 sections 8.1 and 9.1: session salt XOR 00 00 || SSRC || 48 bit packet index.
+fn gcm_iv(salt: &[u8], ssrc: u32, index: u64) -> [u8; 12] {
+    let mut iv = [0u8; 12];
+    iv[2..6].copy_from_slice(&ssrc.to_be_bytes());
+    iv[6..].copy_from_slice(&index.to_be_bytes()[2..]);
+    for (byte, value) in iv.iter_mut().zip(salt) {
+        *byte ^= value;
+    }
+    iv
+}
+
+// Encrypts data in place and returns GCM authentication tag.
+fn gcm_encrypt(key: &[u8], iv: &[u8; 12], aad: &[u8], data: &mut [u8]) -> Vec<u8> {
+    let nonce = Nonce::from_slice(iv);
+    let tag = if key.len() == 32 {
+        Aes256Gcm::new(key.into()).encrypt_in_place_detached(nonce, aad, data)
+    } else {
+        Aes128Gcm::new(key.into()).encrypt_in_place_detached(nonce, aad, data)
+    };
+    // Encryption only fails for inputs far beyond the size of a datagram.
+    tag.expect("GCM input too large").to_vec()
+}
+
+fn gcm_decrypt(key: &[u8], iv: &[u8; 12], aad: &[u8], data: &mut [u8], tag: &[u8]) -> Result<(), SrtpError> {
+    let nonce = Nonce::from_slice(iv);
+    let tag = Tag::from_slice(tag);
+    let result = if key.len() == 32 {
+        Aes256Gcm::new(key.into()).decrypt_in_place_detached(nonce, aad, data, tag)
+    } else {
+        Aes128Gcm::new(key.into()).decrypt_in_place_detached(nonce, aad, data, tag)
+    };
+    result.map_err(|_| SrtpError::AuthFailed)
+}
+
+fn hmac_sha1(key: &[u8], parts: &[&[u8]]) -> HmacSha1 {
+    let mut mac = <HmacSha1 as Mac>::new_from_slice(key).expect("HMAC accepts any key size");
+    for part in parts {
+        mac.update(part);
+    }
+    mac
+}
+
+// ReplayWindow remembers which of the last 64 packet indices were received (RFC 3711 section 3.3.2).
+#[derive(Clone, Default)]
+struct ReplayWindow {
+    top: Option<u64>,
+    bits: u64,
+}
+
+impl ReplayWindow {
+    const SIZE: u64 = 64;
+
+    fn check(&self, index: u64) -> bool {
+        match self.top {
+            Some(top) if index <= top => {
+                let delta = top - index;
+                delta < ReplayWindow::SIZE && self.bits & (1 << delta) == 0
+            },
+            _ => true,
+        }
+    }
+
+    fn update(&mut self, index: u64) {
+        match self.top {
+            Some(top) if index <= top => self.bits |= 1 << (top - index),
+            Some(top) => {
+                let shift = index - top;
+                self.bits = if shift >= ReplayWindow::SIZE { 1 } else { (self.bits << shift) | 1 };
+                self.top = Some(index);
+            },
+            None => {
+                self.bits = 1;
+                self.top = Some(index);
+            },
+        }
+    }
+}
+
+// Cryptographic state of a single SSRC.
+#[derive(Clone, Default)]
+struct StreamState {
+    roc: u32,
+    last_seq: Option<u16>,
+    rtp_replay: ReplayWindow,
+    rtcp_replay: ReplayWindow,
+    rtcp_index: u32,
+}
+
+impl StreamState {
+    // Estimates rollover counter of a packet from its sequence number (RFC 3711 appendix A).
+    fn estimate_roc(&self, seq: u16) -> u32 {
+        let last = match self.last_seq {
+            Some(last) => last,
+            None => return self.roc,
+        };
+        if last < 0x8000 {
+            if seq > last && seq - last > 0x8000 {
+                return self.roc.wrapping_sub(1)
+            }
+        } else if seq < last - 0x8000 {
+            return self.roc.wrapping_add(1)
+        }
+        self.roc
+    }
+
+    fn update_roc(&mut self, seq: u16, roc: u32) {
+        let index = ((roc as u64) << 16) | seq as u64;
+        let last = self.last_seq.map(|last| ((self.roc as u64) << 16) | last as u64);
+        if last.is_none_or(|last| index > last) {
+            self.roc = roc;
+            self.last_seq = Some(seq);
+        }
+    }
+}
+
+// Length of RTP header including CSRC list and header extension, i.e. the part that is not encrypted.
+fn rtp_header_len(buf: &[u8]) -> Result<usize, SrtpError> {
+    const HEADER_SIZE: usize = 12;
+    if buf.len() < HEADER_SIZE {
+        return Err(SrtpError::InvalidLen(buf.len()))
+    }
+    let mut len = HEADER_SIZE + (buf[0] & 0x0F) as usize * 4;
+    if buf[0] & 0x10 != 0 {
+        if len + 4 > buf.len() {
+            return Err(SrtpError::InvalidLen(buf.len()))
+        }
+        len += 4 + u16::from_be_bytes([buf[len + 2], buf[len + 3]]) as usize * 4;
+    }
+    if len > buf.len() {
+        return Err(SrtpError::InvalidLen(buf.len()))
+    }
+    Ok(len)
+}
+
+fn u32_at(buf: &[u8], off: usize) -> u32 {
+    u32::from_be_bytes([buf[off], buf[off + 1], buf[off + 2], buf[off + 3]])
+}
+
+// SrtpContext protects or unprotects RTP and RTCP packets of one direction of a session keyed with a single
+// master key (RFC 3711, RFC 7714).  Packets are transformed in place in the given buffer, authentication tag
+// and SRTCP index are appended on protection and removed on unprotection.  Rollover counters, replay windows
+// and SRTCP indices are tracked per SSRC.
+pub struct SrtpContext {
+    profile: SrtpProfile,
+    rtp: SessionKeys,
+    rtcp: SessionKeys,
+    streams: HashMap<u32, StreamState>,
+}
+
+impl SrtpContext {
+    // Label of SRTP encryption key, authentication key and salt are this plus 0, 1 and 2.
+    const RTP_LABEL: u8 = 0;
+    // Same for SRTCP.
+    const RTCP_LABEL: u8 = 3;
+    // Encryption flag of SRTCP index.
+    const E_FLAG: u32 = 0x8000_0000;
+    // Fixed RTCP header and sender SSRC are never encrypted.
+    const RTCP_HEADER_SIZE: usize = 8;
+
+    pub fn new(profile: SrtpProfile, master_key: &[u8], master_salt: &[u8]) -> Result<SrtpContext, SrtpError> {
+        if master_key.len() != profile.key_len() {
+            return Err(SrtpError::InvalidKeyLen(master_key.len()))
+        }
+        if master_salt.len() != profile.salt_len() {
+            return Err(SrtpError::InvalidKeyLen(master_salt.len()))
+        }
+        Ok(SrtpContext {
+            profile,
+            rtp: SessionKeys::derive(profile, master_key, master_salt, SrtpContext::RTP_LABEL),
+            rtcp: SessionKeys::derive(profile, master_key, master_salt, SrtpContext::RTCP_LABEL),
+            streams: HashMap::new(),
+        })
+    }
+
+    pub fn profile(&self) -> SrtpProfile {
+        self.profile
+    }
+
+    // Rollover counter currently assumed for the SSRC.
+    pub fn roc(&self, ssrc: u32) -> u32 {
+        self.streams.get(&ssrc).map_or(0, |state| state.roc)
+    }
+
+    // Sets rollover counter of the SSRC, e.g. when joining a stream that is already running.
+    pub fn set_roc(&mut self, ssrc: u32, roc: u32) {
+        self.streams.entry(ssrc).or_default().roc = roc;
+    }
+
+    pub fn protect_rtp(&mut self, buf: &mut Vec<u8>) -> Result<(), SrtpError> {
+        let header_len = rtp_header_len(buf)?;
+        let seq = u16::from_be_bytes([buf[2], buf[3]]);
+        let ssrc = u32_at(buf, 8);
+        let state = self.streams.entry(ssrc).or_default();
+        let roc = state.estimate_roc(seq);
+        state.update_roc(seq, roc);
+        let index = ((roc as u64) << 16) | seq as u64;
+        if self.profile.is_aead() {
+            let iv = gcm_iv(&self.rtp.salt, ssrc, index);
+            let (header, payload) = buf.split_at_mut(header_len);
+            let tag = gcm_encrypt(&self.rtp.enc, &iv, header, payload);
+            buf.extend_from_slice(&tag);
+        } else {
+            aes_cm(&self.rtp.enc, &cm_iv(&self.rtp.salt, ssrc, index), &mut buf[header_len..]);
+            let tag = hmac_sha1(&self.rtp.auth, &[buf, &roc.to_be_bytes()]).finalize().into_bytes();
+            buf.extend_from_slice(&tag[..self.profile.rtp_tag_len()]);
+        }
+        Ok(())
+    }
+
+    pub fn unprotect_rtp(&mut self, buf: &mut Vec<u8>) -> Result<(), SrtpError> {
+        let tag_len = self.profile.rtp_tag_len();
+        let header_len = rtp_header_len(buf)?;
+        if buf.len() < header_len + tag_len {
+            return Err(SrtpError::InvalidLen(buf.len()))
+        }
+        let seq = u16::from_be_bytes([buf[2], buf[3]]);
+        let ssrc = u32_at(buf, 8);
+        // State of a new SSRC is stored only after authentication, forged packets must not grow the map.
+        let unknown = StreamState::default();
+        let state = self.streams.get(&ssrc).unwrap_or(&unknown);
+        let roc = state.estimate_roc(seq);
+        let index = ((roc as u64) << 16) | seq as u64;
+        if !state.rtp_replay.check(index) {
+            return Err(SrtpError::Replayed(index))
+        }
+        let data_len = buf.len() - tag_len;
+        if self.profile.is_aead() {
+            let iv = gcm_iv(&self.rtp.salt, ssrc, index);
+            let (packet, tag) = buf.split_at_mut(data_len);
+            let (header, payload) = packet.split_at_mut(header_len);
+            gcm_decrypt(&self.rtp.enc, &iv, header, payload, tag)?;
+        } else {
+            hmac_sha1(&self.rtp.auth, &[&buf[..data_len], &roc.to_be_bytes()])
+                .verify_truncated_left(&buf[data_len..])
+                .map_err(|_| SrtpError::AuthFailed)?;
+            aes_cm(&self.rtp.enc, &cm_iv(&self.rtp.salt, ssrc, index), &mut buf[header_len..data_len]);
+        }
+        buf.truncate(data_len);
+        let state = self.streams.entry(ssrc).or_default();
+        state.update_roc(seq, roc);
+        state.rtp_replay.update(index);
+        Ok(())
+    }
+
+    pub fn protect_rtcp(&mut self, buf: &mut Vec<u8>) -> Result<(), SrtpError> {
+        if buf.len() < SrtpContext::RTCP_HEADER_SIZE {
+            return Err(SrtpError::InvalidLen(buf.len()))
+        }
+        let ssrc = u32_at(buf, 4);
+        let state = self.streams.entry(ssrc).or_default();
+        let index = state.rtcp_index;
+        state.rtcp_index = (index + 1) & !SrtpContext::E_FLAG;
+        let trailer = (SrtpContext::E_FLAG | index).to_be_bytes();
+        if self.profile.is_aead() {
+            let iv = gcm_iv(&self.rtcp.salt, ssrc, index as u64);
+            let (header, payload) = buf.split_at_mut(SrtpContext::RTCP_HEADER_SIZE);
+            let aad = [&header[..], &trailer].concat();
+            let tag = gcm_encrypt(&self.rtcp.enc, &iv, &aad, payload);
+            buf.extend_from_slice(&tag);
+            buf.extend_from_slice(&trailer);
+        } else {
+            aes_cm(&self.rtcp.enc, &cm_iv(&self.rtcp.salt, ssrc, index as u64), &mut buf[SrtpContext::RTCP_HEADER_SIZE..]);
+            buf.extend_from_slice(&trailer);
+            let tag = hmac_sha1(&self.rtcp.auth, &[buf]).finalize().into_bytes();
+            buf.extend_from_slice(&tag[..self.profile.rtcp_tag_len()]);
+        }
+        Ok(())
+    }
+
+    pub fn unprotect_rtcp(&mut self, buf: &mut Vec<u8>) -> Result<(), SrtpError> {
+        let tag_len = self.profile.rtcp_tag_len();
+        if buf.len() < SrtpContext::RTCP_HEADER_SIZE + 4 + tag_len {
+            return Err(SrtpError::InvalidLen(buf.len()))
+        }
+        let ssrc = u32_at(buf, 4);
+        // The index follows the tag with AEAD and precedes it otherwise.
+        let (trailer_off, tag_off) = if self.profile.is_aead() {
+            (buf.len() - 4, buf.len() - 4 - tag_len)
+        } else {
+            (buf.len() - tag_len - 4, buf.len() - tag_len)
+        };
+        let trailer = u32_at(buf, trailer_off);
+        let encrypted = trailer & SrtpContext::E_FLAG != 0;
+        let index = trailer & !SrtpContext::E_FLAG;
+        let unknown = StreamState::default();
+        if !self.streams.get(&ssrc).unwrap_or(&unknown).rtcp_replay.check(index as u64) {
+            return Err(SrtpError::Replayed(index as u64))
+        }
+        let data_len = trailer_off.min(tag_off);
+        if self.profile.is_aead() {
+            let iv = gcm_iv(&self.rtcp.salt, ssrc, index as u64);
+            let (packet, rest) = buf.split_at_mut(data_len);
+            let (tag, trailer) = rest.split_at(tag_len);
+            if encrypted {
+                let (header, payload) = packet.split_at_mut(SrtpContext::RTCP_HEADER_SIZE);
+                let aad = [&header[..], trailer].concat();
+                gcm_decrypt(&self.rtcp.enc, &iv, &aad, payload, tag)?;
+            } else {
+                // Unencrypted SRTCP is authenticated as a whole (RFC 7714 section 9.2).
+                let aad = [&packet[..], trailer].concat();
+                gcm_decrypt(&self.rtcp.enc, &iv, &aad, &mut [], tag)?;
+            }
+        } else {
+            hmac_sha1(&self.rtcp.auth, &[&buf[..tag_off]])
+                .verify_truncated_left(&buf[tag_off..])
+                .map_err(|_| SrtpError::AuthFailed)?;
+            if encrypted {
+                aes_cm(&self.rtcp.enc, &cm_iv(&self.rtcp.salt, ssrc, index as u64), &mut buf[SrtpContext::RTCP_HEADER_SIZE..data_len]);
+            }
+        }
+        buf.truncate(data_len);
+        self.streams.entry(ssrc).or_default().rtcp_replay.update(index as u64);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hex(text: &str) -> Vec<u8> {
+        let text: String = text.split_whitespace().collect();
+        (0..text.len()).step_by(2).map(|off| u8::from_str_radix(&text[off..off + 2], 16).unwrap()).collect()
+    }
+
+    #[test]
+    fn aes_cm_keystream() {
+        // RFC 3711 appendix B.2.
+        let key = hex("2B7E151628AED2A6ABF7158809CF4F3C");
+        let iv = cm_iv(&hex("F0F1F2F3F4F5F6F7F8F9FAFBFCFD"), 0, 0);
+        let mut keystream = [0u8; 48];
+        aes_cm(&key, &iv, &mut keystream);
+        assert_eq!(hex("E03EAD0935C95E80E166B16DD92B4EB4 D23513162B02D0F72A43A2FE4A5F97AB 41E95B3BB0A2E8DD477901E4FCA894C0"), keystream);
+    }
+
+    #[test]
+    fn derive_session_keys() {
+        // RFC 3711 appendix B.3.
+        let master_key = hex("E1F97A0D3E018BE0D64FA32C06DE4139");
+        let master_salt = hex("0EC675AD498AFEEBB6960B3AABE6");
+        let keys = SessionKeys::derive(SrtpProfile::Aes128CmHmacSha1_80, &master_key, &master_salt, 0);
+        assert_eq!(hex("C61E7A93744F39EE10734AFE3FF7A087"), keys.enc);
+        assert_eq!(hex("30CBBC08863D8C85D49DB34A9AE1"), keys.salt);
+        assert_eq!(hex("CEBE321F6FF7716B6FD4AB49AF256A156D38BAA4"), keys.auth);
+    }
+
+    fn cm_context(profile: SrtpProfile) -> SrtpContext {
+        let master_key = hex("E1F97A0D3E018BE0D64FA32C06DE4139");
+        let master_salt = hex("0EC675AD498AFEEBB6960B3AABE6");
+        SrtpContext::new(profile, &master_key, &master_salt).unwrap()
+    }
+
+    #[test]
+    fn protect_rtp_aes_cm() {
+        let plain = hex("800f1234 decafbad cafebabe abababab abababab abababab abababab");
+        let mut buf = plain.clone();
+        let mut sender = cm_context(SrtpProfile::Aes128CmHmacSha1_80);
+        sender.protect_rtp(&mut buf).unwrap();
+        assert_eq!(hex("800f1234 decafbad cafebabe 4e55dc4c e79978d8 8ca4d215 949d2402 b78d6acc 99ea179b 8dbb"), buf);
+
+        let mut receiver = cm_context(SrtpProfile::Aes128CmHmacSha1_80);
+        receiver.unprotect_rtp(&mut buf).unwrap();
+        assert_eq!(plain, buf);
+    }
+
+    #[test]
+    fn reject_tampered_and_replayed_rtp() {
+        let plain = hex("800f1234 decafbad cafebabe abababab abababab abababab abababab");
+        let mut sender = cm_context(SrtpProfile::Aes128CmHmacSha1_32);
+        let mut receiver = cm_context(SrtpProfile::Aes128CmHmacSha1_32);
+        let mut buf = plain.clone();
+        sender.protect_rtp(&mut buf).unwrap();
+        assert_eq!(plain.len() + 4, buf.len());
+        let protected = buf.clone();
+
+        buf[20] ^= 1;
+        assert!(matches!(receiver.unprotect_rtp(&mut buf).unwrap_err(), SrtpError::AuthFailed));
+        let mut forged = protected.clone();
+        forged[8..12].copy_from_slice(&[1, 2, 3, 4]);
+        assert!(matches!(receiver.unprotect_rtp(&mut forged).unwrap_err(), SrtpError::AuthFailed));
+        let mut forged = hex("81c8000b 01020304 abababab 80000001 00000000 00000000 0000");
+        assert!(matches!(receiver.unprotect_rtcp(&mut forged).unwrap_err(), SrtpError::AuthFailed));
+        assert!(receiver.streams.is_empty());
+        let mut buf = protected.clone();
+        receiver.unprotect_rtp(&mut buf).unwrap();
+        assert_eq!(plain, buf);
+        let mut buf = protected;
+        assert!(matches!(receiver.unprotect_rtp(&mut buf).unwrap_err(), SrtpError::Replayed(0x1234)));
+    }
+
+    #[test]
+    fn unprotect_rtcp_aes_cm() {
+        // SRTCP index 1, encrypted, produced by libsrtp with the same master key.
+        let mut buf = hex("81c8000b cafebabe 7128035b e487b9bd bef89041 f977a5a8 80000001 993e08cd 54d6c123 0798");
+        let mut receiver = cm_context(SrtpProfile::Aes128CmHmacSha1_80);
+        receiver.unprotect_rtcp(&mut buf).unwrap();
+        assert_eq!(hex("81c8000b cafebabe abababab abababab abababab abababab"), buf);
+    }
+
+    #[test]
+    fn protect_rtcp_round_trip() {
+        for profile in [SrtpProfile::Aes128CmHmacSha1_32, SrtpProfile::AeadAes128Gcm] {
+            let (mut sender, mut receiver) = if profile.is_aead() {
+                (gcm_context(), gcm_context())
+            } else {
+                (cm_context(profile), cm_context(profile))
+            };
+            let plain = hex("81c8000b cafebabe abababab abababab abababab abababab");
+            for index in 0..3u32 {
+                let mut buf = plain.clone();
+                sender.protect_rtcp(&mut buf).unwrap();
+                assert_eq!(plain.len() + 4 + profile.rtcp_tag_len(), buf.len());
+                let trailer_off = if profile.is_aead() { buf.len() - 4 } else { buf.len() - 14 };
+                assert_eq!(0x8000_0000 | index, u32_at(&buf, trailer_off));
+                let mut copy = buf.clone();
+                receiver.unprotect_rtcp(&mut buf).unwrap();
+                assert_eq!(plain, buf);
+                assert!(matches!(receiver.unprotect_rtcp(&mut copy).unwrap_err(), SrtpError::Replayed(_)));
+            }
+        }
+    }
+
+    fn gcm_context() -> SrtpContext {
+        SrtpContext::new(SrtpProfile::AeadAes128Gcm, &hex("000102030405060708090a0b0c0d0e0f"), &hex("517569642070726f2071756f")).unwrap()
+    }
+
+    #[test]
+    fn protect_rtp_aes_gcm() {
+        // RFC 7714 section 16.1.1, with session keys set directly as the test vectors do not use the KDF.
+        let plain = hex("8040f17b 8041f8d3 5501a0b2 47616c6c 69612065 7374206f 6d6e6973 20646976 69736120 696e2070 61727465 73207472 6573");
+        let mut context = gcm_context();
+        context.rtp.enc = hex("000102030405060708090a0b0c0d0e0f");
+        context.rtp.salt = hex("517569642070726f2071756f");
+        let mut buf = plain.clone();
+        context.protect_rtp(&mut buf).unwrap();
+        assert_eq!(hex("8040f17b 8041f8d3 5501a0b2 f24de3a3 fb34de6c acba861c 9d7e4bca be633bd5 0d294e6f 42a5f47a
+            51c7d19b 36de3adf 8833899d 7f27beb1 6a9152cf 765ee439 0cce"), buf);
+
+        let mut receiver = gcm_context();
+        receiver.rtp.enc = hex("000102030405060708090a0b0c0d0e0f");
+        receiver.rtp.salt = hex("517569642070726f2071756f");
+        receiver.unprotect_rtp(&mut buf).unwrap();
+        assert_eq!(plain, buf);
+    }
+
+    #[test]
+    fn estimate_rollover_counter() {
+        let mut state = StreamState::default();
+        state.update_roc(65530, 0);
+        assert_eq!(0, state.estimate_roc(65535));
+        assert_eq!(1, state.estimate_roc(3));
+        state.update_roc(3, 1);
+        assert_eq!(1, state.roc);
+        // Late packet from before the wraparound belongs to the previous cycle.
+        assert_eq!(0, state.estimate_roc(65533));
+        state.update_roc(65533, 0);
+        assert_eq!(Some(3), state.last_seq);
+    }
+
+    #[test]
+    fn slide_replay_window() {
+        let mut window = ReplayWindow::default();
+        window.update(100);
+        assert!(!window.check(100));
+        assert!(window.check(99));
+        window.update(99);
+        assert!(!window.check(99));
+        window.update(200);
+        assert!(!window.check(136));
+        assert!(window.check(137));
+        assert!(window.check(201));
+    }
+
+    #[test]
+    fn reject_invalid_keys() {
+        let error = SrtpContext::new(SrtpProfile::AeadAes256Gcm, &[0u8; 16], &[0u8; 12]).err().unwrap();
+        assert!(matches!(error, SrtpError::InvalidKeyLen(16)));
+        let error = SrtpContext::new(SrtpProfile::Aes128CmHmacSha1_80, &[0u8; 16], &[0u8; 12]).err().unwrap();
+        assert!(matches!(error, SrtpError::InvalidKeyLen(12)));
+    }
+}
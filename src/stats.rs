@@ -0,0 +1,210 @@
+use std::time::Instant;
+
+use crate::ntp::NtpTimestamp;
+use crate::rtcp::SynSource;
+
+// ReceptionStats accumulates reception statistics of a single remote source needed for report blocks: sequence
+// number tracking (RFC 3550 appendix A.1), loss (appendix A.3) and interarrival jitter (appendix A.8).
+pub struct ReceptionStats {
+    clock_rate: u32,
+    epoch: Instant,
+    max_seq: u16,
+    cycles: u32,
+    base_seq: u32,
+    bad_seq: Option<u16>,
+    received: u32,
+    expected_prior: u32,
+    received_prior: u32,
+    transit: Option<u32>,
+    jitter: u32,          // scaled by 16 to keep the precision
+    last_sr: u32,
+    sr_arrival: Option<Instant>,
+}
+
+impl ReceptionStats {
+    // Sequence number jump still treated as packet loss.
+    const MAX_DROPOUT: u16 = 3000;
+    // Sequence number step back still treated as reordering.
+    const MAX_MISORDER: u16 = 100;
+
+    // Starts tracking a source from its first packet.  Arrival times are converted into RTP timestamp units at
+    // given clock rate relative to epoch.
+    pub fn new(seq: u16, clock_rate: u32, epoch: Instant) -> Self {
+        let mut stats = ReceptionStats {
+            clock_rate,
+            epoch,
+            max_seq: 0,
+            cycles: 0,
+            base_seq: 0,
+            bad_seq: None,
+            received: 0,
+            expected_prior: 0,
+            received_prior: 0,
+            transit: None,
+            jitter: 0,
+            last_sr: 0,
+            sr_arrival: None,
+        };
+        stats.init_seq(seq);
+        stats
+    }
+
+    fn init_seq(&mut self, seq: u16) {
+        self.base_seq = seq as u32;
+        self.max_seq = seq;
+        self.bad_seq = None;
+        self.cycles = 0;
+        self.received = 0;
+        self.expected_prior = 0;
+        self.received_prior = 0;
+    }
+
+    // Accounts for a received packet.  Returns the number of packets missing right before this one, or None
+    // if the packet is dropped because of a large sequence number jump.  Two sequential packets after a jump
+    // mean the source restarted and tracking starts over.
+    pub fn update(&mut self, seq: u16, timestamp: u32, arrival: Instant) -> Option<u16> {
+        let delta = seq.wrapping_sub(self.max_seq);
+        let mut missing = 0;
+        if delta < ReceptionStats::MAX_DROPOUT {
+            if seq < self.max_seq {
+                self.cycles = self.cycles.wrapping_add(1 << 16);
+            }
+            missing = delta.saturating_sub(1);
+            self.max_seq = seq;
+        } else if delta <= u16::MAX - ReceptionStats::MAX_MISORDER {
+            if self.bad_seq != Some(seq) {
+                self.bad_seq = Some(seq.wrapping_add(1));
+                return None
+            }
+            self.init_seq(seq);
+        }
+        self.received = self.received.wrapping_add(1);
+        self.update_jitter(timestamp, arrival);
+        Some(missing)
+    }
+
+    fn update_jitter(&mut self, timestamp: u32, arrival: Instant) {
+        let elapsed = arrival.saturating_duration_since(self.epoch);
+        let arrival = (elapsed.as_nanos() * self.clock_rate as u128 / 1_000_000_000) as u32;
+        let transit = arrival.wrapping_sub(timestamp);
+        if let Some(prior) = self.transit {
+            let d = (transit.wrapping_sub(prior) as i32).unsigned_abs();
+            self.jitter = self.jitter.wrapping_add(d).wrapping_sub((self.jitter + 8) >> 4);
+        }
+        self.transit = Some(transit);
+    }
+
+    // Remembers the middle bits of the last SR timestamp and its arrival for LSR and DLSR fields.
+    pub fn sr_received(&mut self, ntpts: NtpTimestamp, arrival: Instant) {
+        self.last_sr = ntpts.compact();
+        self.sr_arrival = Some(arrival);
+    }
+
+    pub fn extended_max_seq(&self) -> u32 {
+        self.cycles.wrapping_add(self.max_seq as u32)
+    }
+
+    pub fn expected(&self) -> u32 {
+        self.extended_max_seq().wrapping_sub(self.base_seq).wrapping_add(1)
+    }
+
+    pub fn received(&self) -> u32 {
+        self.received
+    }
+
+    // Cumulative number of packets lost, negative when duplicates arrived.
+    pub fn lost(&self) -> i64 {
+        self.expected() as i64 - self.received as i64
+    }
+
+    // Interarrival jitter in timestamp units.
+    pub fn jitter(&self) -> u32 {
+        self.jitter >> 4
+    }
+
+    // Tells whether any packets arrived since the last report block was made.
+    pub fn is_active(&self) -> bool {
+        self.received != self.received_prior
+    }
+
+    // Builds the report block about the source and starts a new reporting interval.
+    pub fn report(&mut self, ssrc: u32, now: Instant) -> SynSource {
+        let expected = self.expected();
+        let expected_interval = expected.wrapping_sub(self.expected_prior);
+        let received_interval = self.received.wrapping_sub(self.received_prior);
+        self.expected_prior = expected;
+        self.received_prior = self.received;
+        let lost_interval = expected_interval as i64 - received_interval as i64;
+        let fraction = if expected_interval == 0 || lost_interval <= 0 {
+            0
+        } else {
+            ((lost_interval << 8) / expected_interval as i64) as u8
+        };
+        // Cumulative loss is a 24 bit signed value.
+        let lost = self.lost().clamp(-0x80_0000, 0x7F_FFFF) as u32 & 0x00FF_FFFF;
+        let delay = self.sr_arrival.map_or(0, |arrival| NtpTimestamp::compact_from_duration(now.saturating_duration_since(arrival)));
+        SynSource::new(ssrc, fraction, lost, self.extended_max_seq(), self.jitter(), self.last_sr, delay)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn count_loss_across_wraparound() {
+        let start = Instant::now();
+        let mut stats = ReceptionStats::new(65534, 90000, start);
+        assert_eq!(Some(0), stats.update(65534, 0, start));
+        assert_eq!(Some(0), stats.update(65535, 0, start));
+        assert_eq!(Some(2), stats.update(2, 0, start));
+        // Late packet is counted as received but does not move the highest sequence number.
+        assert_eq!(Some(0), stats.update(0, 0, start));
+        assert_eq!(65536 + 2, stats.extended_max_seq());
+        assert_eq!(5, stats.expected());
+        assert_eq!(1, stats.lost());
+
+        let report = stats.report(0x1111, start);
+        assert_eq!(51, report.fraction_lost());
+        assert_eq!(1, report.packets_lost());
+        assert_eq!(65538, report.highest_seq());
+        assert!(!stats.is_active());
+    }
+
+    #[test]
+    fn restart_after_jump() {
+        let start = Instant::now();
+        let mut stats = ReceptionStats::new(100, 8000, start);
+        assert_eq!(Some(0), stats.update(100, 0, start));
+        assert_eq!(None, stats.update(20000, 0, start));
+        assert_eq!(Some(0), stats.update(20001, 0, start));
+        assert_eq!(Some(0), stats.update(20002, 0, start));
+        assert_eq!(2, stats.expected());
+        assert_eq!(0, stats.lost());
+    }
+
+    #[test]
+    fn compute_jitter() {
+        let start = Instant::now();
+        let mut stats = ReceptionStats::new(1, 8000, start);
+        // Packets sent every 20 ms (160 units) arrive 10 ms (80 units) off schedule every other time.
+        for index in 0..200u16 {
+            let skew = if index % 2 == 0 { 0 } else { 10 };
+            let arrival = start + Duration::from_millis(20 * index as u64 + skew);
+            stats.update(index + 1, 160 * index as u32, arrival);
+        }
+        assert!((75..=80).contains(&stats.jitter()));
+    }
+
+    #[test]
+    fn report_delay_since_last_sr() {
+        let start = Instant::now();
+        let mut stats = ReceptionStats::new(1, 8000, start);
+        stats.update(1, 0, start);
+        stats.sr_received(NtpTimestamp::new(0xe535_2f10_8000_1234), start);
+        let report = stats.report(0x1111, start + Duration::from_millis(1500));
+        assert_eq!(0x2f10_8000, report.last_sr());
+        assert_eq!(0x0001_8000, report.delay());
+    }
+}
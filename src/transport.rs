@@ -0,0 +1,319 @@
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::ops::RangeInclusive;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use futures_core::Stream;
+use futures_sink::Sink;
+use tokio::io::ReadBuf;
+use tokio::net::UdpSocket;
+
+use crate::mux::{MuxError, MuxPacket, PacketKind};
+use crate::rtcp::{RtcpMode, RtcpPacket};
+use crate::rtp::RtpPacket;
+
+#[derive(Debug)]
+pub enum TransportError {
+    Io(io::Error),
+    Mux(MuxError),
+    // Datagrams cannot be sent before the peer addresses are known.
+    NoPeer,
+}
+
+impl From<io::Error> for TransportError {
+    fn from(error: io::Error) -> Self {
+        TransportError::Io(error)
+    }
+}
+
+impl From<MuxError> for TransportError {
+    fn from(error: MuxError) -> Self {
+        TransportError::Mux(error)
+    }
+}
+
+// Datagram is a received RTP or RTCP packet that passed validation.  Data is owned so it can outlive the
+// receive buffer; packets are parsed again on access as they borrow the data.
+#[derive(Debug)]
+pub struct Datagram {
+    source: SocketAddr,
+    kind: PacketKind,
+    mode: RtcpMode,
+    data: Vec<u8>,
+}
+
+impl Datagram {
+    pub fn source(&self) -> SocketAddr {
+        self.source
+    }
+
+    pub fn kind(&self) -> PacketKind {
+        self.kind
+    }
+
+    pub fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    pub fn rtp(&self) -> Option<RtpPacket<'_>> {
+        match self.kind {
+            PacketKind::Rtp => RtpPacket::from_slice(&self.data).ok(),
+            PacketKind::Rtcp => None,
+        }
+    }
+
+    pub fn rtcp(&self) -> Option<Vec<RtcpPacket<'_>>> {
+        match self.kind {
+            PacketKind::Rtp => None,
+            PacketKind::Rtcp => RtcpPacket::compound_from_slice(&self.data, self.mode).ok(),
+        }
+    }
+}
+
+// UdpTransport carries RTP and RTCP over a pair of UDP sockets bound to adjacent even and odd ports
+// (RFC 3550 section 11).  It is a Stream of validated datagrams received on either socket and a Sink of
+// RTP packets or (kind, datagram) pairs as produced by Endpoint::poll_transmit().
+pub struct UdpTransport {
+    rtp: UdpSocket,
+    rtcp: UdpSocket,
+    peer: Option<(SocketAddr, SocketAddr)>,
+    mode: RtcpMode,
+    buf: Vec<u8>,
+    pending: Option<(PacketKind, Vec<u8>)>,
+    // Alternates between sockets on receive so a busy RTP socket does not starve RTCP.
+    rtcp_first: bool,
+}
+
+impl UdpTransport {
+    // Large enough for any UDP payload.
+    const BUFFER_SIZE: usize = 65536;
+
+    // Binds RTP to the first even port of the range whose odd neighbour is also free, skipping ports in use.
+    pub async fn bind(ip: IpAddr, ports: RangeInclusive<u16>) -> io::Result<UdpTransport> {
+        let first = ports.start().saturating_add(ports.start() & 1);
+        for port in (first..*ports.end()).step_by(2) {
+            let rtp = match UdpSocket::bind(SocketAddr::new(ip, port)).await {
+                Ok(socket) => socket,
+                Err(error) if error.kind() == io::ErrorKind::AddrInUse => continue,
+                Err(error) => return Err(error),
+            };
+            let rtcp = match UdpSocket::bind(SocketAddr::new(ip, port + 1)).await {
+                Ok(socket) => socket,
+                Err(error) if error.kind() == io::ErrorKind::AddrInUse => continue,
+                Err(error) => return Err(error),
+            };
+            return Ok(UdpTransport::from_sockets(rtp, rtcp))
+        }
+        Err(io::Error::new(io::ErrorKind::AddrInUse, "no free RTP/RTCP port pair in range"))
+    }
+
+    pub fn from_sockets(rtp: UdpSocket, rtcp: UdpSocket) -> UdpTransport {
+        UdpTransport {
+            rtp,
+            rtcp,
+            peer: None,
+            mode: RtcpMode::Compound,
+            buf: vec![0u8; UdpTransport::BUFFER_SIZE],
+            pending: None,
+            rtcp_first: false,
+        }
+    }
+
+    pub fn rtp_addr(&self) -> io::Result<SocketAddr> {
+        self.rtp.local_addr()
+    }
+
+    pub fn rtcp_addr(&self) -> io::Result<SocketAddr> {
+        self.rtcp.local_addr()
+    }
+
+    // Sets RTP and RTCP addresses of the remote participant, e.g. from SDP or RTSP Transport header.
+    pub fn set_peer(&mut self, rtp: SocketAddr, rtcp: SocketAddr) {
+        self.peer = Some((rtp, rtcp));
+    }
+
+    pub fn set_rtcp_mode(&mut self, mode: RtcpMode) {
+        self.mode = mode;
+    }
+
+    fn poll_recv_on(&mut self, kind: PacketKind, cx: &mut Context<'_>) -> Poll<Result<Datagram, TransportError>> {
+        let socket = if kind == PacketKind::Rtp { &self.rtp } else { &self.rtcp };
+        let mut buf = ReadBuf::new(&mut self.buf);
+        let source = match socket.poll_recv_from(cx, &mut buf) {
+            Poll::Ready(Ok(source)) => source,
+            Poll::Ready(Err(error)) => return Poll::Ready(Err(error.into())),
+            Poll::Pending => return Poll::Pending,
+        };
+        let data = buf.filled();
+        // Demultiplex by content, peers may send both kinds to the same port.
+        let kind = match MuxPacket::from_slice(data, self.mode) {
+            Ok(MuxPacket::Rtp(_)) => PacketKind::Rtp,
+            Ok(MuxPacket::Rtcp(_)) => PacketKind::Rtcp,
+            Err(error) => return Poll::Ready(Err(error.into())),
+        };
+        Poll::Ready(Ok(Datagram { source, kind, mode: self.mode, data: data.to_vec() }))
+    }
+
+    fn poll_send_pending(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), TransportError>> {
+        if let Some((kind, data)) = &self.pending {
+            let Some((rtp, rtcp)) = self.peer else {
+                self.pending = None;
+                return Poll::Ready(Err(TransportError::NoPeer))
+            };
+            let (socket, target) = if *kind == PacketKind::Rtp { (&self.rtp, rtp) } else { (&self.rtcp, rtcp) };
+            match socket.poll_send_to(cx, data, target) {
+                Poll::Ready(Ok(_)) => self.pending = None,
+                Poll::Ready(Err(error)) => {
+                    self.pending = None;
+                    return Poll::Ready(Err(error.into()))
+                },
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    // Packets are only accepted with a peer to send them to.
+    fn queue(&mut self, kind: PacketKind, data: Vec<u8>) -> Result<(), TransportError> {
+        if self.peer.is_none() {
+            return Err(TransportError::NoPeer)
+        }
+        self.pending = Some((kind, data));
+        Ok(())
+    }
+}
+
+impl Stream for UdpTransport {
+    type Item = Result<Datagram, TransportError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        this.rtcp_first = !this.rtcp_first;
+        let order = if this.rtcp_first { [PacketKind::Rtcp, PacketKind::Rtp] } else { [PacketKind::Rtp, PacketKind::Rtcp] };
+        for kind in order {
+            if let Poll::Ready(result) = this.poll_recv_on(kind, cx) {
+                return Poll::Ready(Some(result))
+            }
+        }
+        Poll::Pending
+    }
+}
+
+impl Sink<(PacketKind, Vec<u8>)> for UdpTransport {
+    type Error = TransportError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_send_pending(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: (PacketKind, Vec<u8>)) -> Result<(), Self::Error> {
+        self.get_mut().queue(item.0, item.1)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_send_pending(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_send_pending(cx)
+    }
+}
+
+impl<'a> Sink<RtpPacket<'a>> for UdpTransport {
+    type Error = TransportError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_send_pending(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: RtpPacket<'a>) -> Result<(), Self::Error> {
+        self.get_mut().queue(PacketKind::Rtp, item.to_vec())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_send_pending(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.get_mut().poll_send_pending(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::Ipv4Addr;
+    use futures_util::{SinkExt, StreamExt};
+    use crate::rtcp::DataRR;
+    use crate::rtp::RtpPacketizer;
+
+    const LOOPBACK: IpAddr = IpAddr::V4(Ipv4Addr::LOCALHOST);
+
+    async fn pair(ports: RangeInclusive<u16>) -> (UdpTransport, UdpTransport) {
+        let mut left = UdpTransport::bind(LOOPBACK, ports.clone()).await.unwrap();
+        let mut right = UdpTransport::bind(LOOPBACK, ports).await.unwrap();
+        left.set_peer(right.rtp_addr().unwrap(), right.rtcp_addr().unwrap());
+        right.set_peer(left.rtp_addr().unwrap(), left.rtcp_addr().unwrap());
+        (left, right)
+    }
+
+    #[tokio::test]
+    async fn bind_port_pair_skipping_used_ports() {
+        let (left, right) = pair(41001..=41101).await;
+        let rtp = left.rtp_addr().unwrap().port();
+        assert_eq!(0, rtp % 2);
+        assert_eq!(rtp + 1, left.rtcp_addr().unwrap().port());
+        assert!(right.rtp_addr().unwrap().port() > rtp);
+        assert_eq!(0, right.rtp_addr().unwrap().port() % 2);
+
+        let error = UdpTransport::bind(LOOPBACK, rtp..=rtp + 1).await.err().unwrap();
+        assert_eq!(io::ErrorKind::AddrInUse, error.kind());
+    }
+
+    #[tokio::test]
+    async fn exchange_rtp_and_rtcp() {
+        let (mut left, mut right) = pair(41200..=41300).await;
+        let payload = [7u8; 300];
+        let mut packetizer = RtpPacketizer::new(200, 96, 0x1234);
+        for packet in packetizer.packetize(&payload, 3000) {
+            left.send(packet).await.unwrap();
+        }
+        left.send((PacketKind::Rtcp, DataRR::new(0x1234, Vec::new()).to_vec())).await.unwrap();
+
+        let mut sizes = Vec::new();
+        let mut reports = 0;
+        while sizes.len() < 2 || reports < 1 {
+            let datagram = right.next().await.unwrap().unwrap();
+            assert_eq!(left.rtp_addr().unwrap().ip(), datagram.source().ip());
+            match datagram.kind() {
+                PacketKind::Rtp => sizes.push(datagram.rtp().unwrap().payload().len()),
+                PacketKind::Rtcp => {
+                    assert_eq!(left.rtcp_addr().unwrap(), datagram.source());
+                    assert_eq!(RtcpPacket::RR, datagram.rtcp().unwrap()[0].payload_type());
+                    reports += 1;
+                },
+            }
+        }
+        assert_eq!(vec![188, 112], sizes);
+    }
+
+    #[tokio::test]
+    async fn reject_send_without_peer() {
+        let mut left = UdpTransport::bind(LOOPBACK, 41600..=41700).await.unwrap();
+        let mut right = UdpTransport::bind(LOOPBACK, 41600..=41700).await.unwrap();
+        let report = DataRR::new(0x1234, Vec::new()).to_vec();
+        let error = left.send((PacketKind::Rtcp, report.clone())).await.unwrap_err();
+        assert!(matches!(error, TransportError::NoPeer));
+        left.set_peer(right.rtp_addr().unwrap(), right.rtcp_addr().unwrap());
+        left.send((PacketKind::Rtcp, report)).await.unwrap();
+        assert_eq!(PacketKind::Rtcp, right.next().await.unwrap().unwrap().kind());
+    }
+
+    #[tokio::test]
+    async fn report_invalid_datagram() {
+        let (left, mut right) = pair(41400..=41500).await;
+        left.rtp.send_to(&[0x16, 0xfe, 0xff], right.rtp_addr().unwrap()).await.unwrap();
+        let error = right.next().await.unwrap().unwrap_err();
+        assert!(matches!(error, TransportError::Mux(MuxError::NotRtp(0x16))));
+    }
+}
@@ -0,0 +1,554 @@
+use std::fmt;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+use crate::rtcp::{RtcpError, RtcpPacket};
+use crate::rtp::RtpPacket;
+
+// PacketStatus is a reception status and receive delta of a single packet in transport-wide feedback.
+// Deltas are expressed in multiples of 250 microseconds.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PacketStatus {
+    NotReceived,
+    SmallDelta(u8),
+    LargeDelta(i16),
+}
+
+impl PacketStatus {
+    // Resolution of receive deltas in microseconds.
+    pub const DELTA_UNIT: i64 = 250;
+
+    fn symbol(&self) -> u8 {
+        match self {
+            PacketStatus::NotReceived => 0,
+            PacketStatus::SmallDelta(_) => 1,
+            PacketStatus::LargeDelta(_) => 2,
+        }
+    }
+
+    // Picks the smallest status that is able to carry given delta.
+    pub fn from_delta(delta: i64) -> Option<PacketStatus> {
+        if (0..=u8::MAX as i64).contains(&delta) {
+            Some(PacketStatus::SmallDelta(delta as u8))
+        } else if (i16::MIN as i64..=i16::MAX as i64).contains(&delta) {
+            Some(PacketStatus::LargeDelta(delta as i16))
+        } else {
+            None
+        }
+    }
+
+    pub fn delta(&self) -> Option<i64> {
+        match self {
+            PacketStatus::NotReceived => None,
+            PacketStatus::SmallDelta(delta) => Some(*delta as i64),
+            PacketStatus::LargeDelta(delta) => Some(*delta as i64),
+        }
+    }
+}
+
+// DataTWCC encapsulates data for Transport-wide Congestion Control feedback packet
+// (draft-holmer-rmcat-transport-wide-cc-extensions-01).
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |V=2|P|  FMT=15 |   PT=RTPFB=205  |           length            |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                     SSRC of packet sender                     |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                      SSRC of media source                     |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |      base sequence number     |      packet status count      |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                 reference time                | fb pkt. count |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |          packet chunk         |         packet chunk          |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// .                                                               .
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |         packet chunk          |  recv delta   |  recv delta   |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// .                                                               .
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |           recv delta          |  recv delta   | zero padding  |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// Run length chunk.
+//  0                   1
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |T| S |       Run Length        |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// Status vector chunk.
+//  0                   1
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |T|S|       symbol list         |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+#[derive(Clone, Eq, PartialEq)]
+pub struct DataTWCC {
+	sender_ssrc:    u32,                // SSRC of packet sender
+	media_ssrc:     u32,                // SSRC of media source
+	base_seq:       u16,                // Transport-wide sequence number of the first packet
+	reference_time: i32,                // Reference time in multiples of 64ms, signed 24 bits
+	fb_count:       u8,                 // Feedback packet count
+	statuses:       Vec<PacketStatus>,  // Status of each packet starting from base_seq
+}
+
+impl DataTWCC {
+    // Feedback message type of transport-wide congestion control feedback.
+    pub const FMT: u8 = 15;
+    // Resolution of reference time in microseconds.
+    pub const REFERENCE_UNIT: i64 = 64_000;
+
+    const FIXED_SIZE: usize = 16;
+    const MAX_RUN_LENGTH: usize = 0x1FFF;
+
+    pub fn new(
+        sender_ssrc: u32,
+        media_ssrc: u32,
+        base_seq: u16,
+        reference_time: i32,
+        fb_count: u8,
+        statuses: Vec<PacketStatus>,
+    ) -> DataTWCC {
+        DataTWCC {
+            sender_ssrc,
+            media_ssrc,
+            base_seq,
+            reference_time,
+            fb_count,
+            statuses,
+        }
+    }
+
+    pub fn from_packet(packet: &RtcpPacket) -> Result<DataTWCC, RtcpError> {
+        if packet.payload_type() != RtcpPacket::RTPFB {
+            return Err(RtcpError::InvalidPayloadType(packet.payload_type()))
+        }
+        if packet.count() != DataTWCC::FMT {
+            return Err(RtcpError::InvalidFormat(packet.count()))
+        }
+        let slice = packet.payload();
+        let slice_len = slice.len();
+        if slice_len < DataTWCC::FIXED_SIZE {
+            return Err(RtcpError::InvalidLen(slice_len))
+        }
+        let status_count = u16::from_be_bytes([slice[10], slice[11]]) as usize;
+        // Sign extend 24 bit reference time.
+        let reference_time = i32::from_be_bytes([slice[12], slice[13], slice[14], 0]) >> 8;
+
+        let mut symbols = Vec::<u8>::with_capacity(status_count);
+        let mut off = DataTWCC::FIXED_SIZE;
+        while symbols.len() < status_count {
+            if off + 2 > slice_len {
+                return Err(RtcpError::InvalidLen(slice_len))
+            }
+            let chunk = u16::from_be_bytes([slice[off], slice[off + 1]]);
+            off += 2;
+            let remaining = status_count - symbols.len();
+            if chunk & 0x8000 == 0 {
+                let symbol = ((chunk >> 13) & 0x03) as u8;
+                let run = usize::min(remaining, (chunk & 0x1FFF) as usize);
+                symbols.extend(std::iter::repeat_n(symbol, run));
+            } else if chunk & 0x4000 == 0 {
+                for index in 0..usize::min(remaining, 14) {
+                    symbols.push(((chunk >> (13 - index)) & 0x01) as u8);
+                }
+            } else {
+                for index in 0..usize::min(remaining, 7) {
+                    symbols.push(((chunk >> (12 - index * 2)) & 0x03) as u8);
+                }
+            }
+        }
+
+        let mut statuses = Vec::<PacketStatus>::with_capacity(status_count);
+        for symbol in symbols {
+            let status = match symbol {
+                0 => PacketStatus::NotReceived,
+                1 => {
+                    if off + 1 > slice_len {
+                        return Err(RtcpError::InvalidLen(slice_len))
+                    }
+                    off += 1;
+                    PacketStatus::SmallDelta(slice[off - 1])
+                },
+                2 => {
+                    if off + 2 > slice_len {
+                        return Err(RtcpError::InvalidLen(slice_len))
+                    }
+                    off += 2;
+                    PacketStatus::LargeDelta(i16::from_be_bytes([slice[off - 2], slice[off - 1]]))
+                },
+                _ => return Err(RtcpError::InvalidFormat(symbol)),
+            };
+            statuses.push(status);
+        }
+
+        Ok(DataTWCC {
+            sender_ssrc: u32::from_be_bytes([slice[0], slice[1], slice[2], slice[3]]),
+            media_ssrc: u32::from_be_bytes([slice[4], slice[5], slice[6], slice[7]]),
+            base_seq: u16::from_be_bytes([slice[8], slice[9]]),
+            reference_time,
+            fb_count: slice[15],
+            statuses,
+        })
+    }
+
+    pub fn sender_ssrc(&self) -> u32 {
+        self.sender_ssrc
+    }
+
+    pub fn media_ssrc(&self) -> u32 {
+        self.media_ssrc
+    }
+
+    pub fn base_seq(&self) -> u16 {
+        self.base_seq
+    }
+
+    pub fn reference_time(&self) -> i32 {
+        self.reference_time
+    }
+
+    pub fn fb_count(&self) -> u8 {
+        self.fb_count
+    }
+
+    pub fn statuses(&self) -> &[PacketStatus] {
+        &self.statuses
+    }
+
+    // Returns transport-wide sequence numbers of received packets with their arrival times in microseconds,
+    // relative to the same base as the reference time.
+    pub fn arrivals(&self) -> Vec<(u16, i64)> {
+        let mut time = self.reference_time as i64 * DataTWCC::REFERENCE_UNIT;
+        let mut arrivals = Vec::<(u16, i64)>::with_capacity(self.statuses.len());
+        for (index, status) in self.statuses.iter().enumerate() {
+            if let Some(delta) = status.delta() {
+                time += delta * PacketStatus::DELTA_UNIT;
+                arrivals.push((self.base_seq.wrapping_add(index as u16), time));
+            }
+        }
+        arrivals
+    }
+
+    // Serializes feedback into a complete RTCP packet, padded to 32 bit boundary as necessary.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let symbols: Vec<u8> = self.statuses.iter().map(|status| status.symbol()).collect();
+        let chunks = DataTWCC::encode_chunks(&symbols);
+        let deltas_len: usize = self.statuses.iter().map(|status| status.symbol() as usize).sum();
+        let len = RtcpPacket::HEADER_SIZE + DataTWCC::FIXED_SIZE + chunks.len() * 2 + deltas_len;
+        let pad_len = (4 - len % 4) % 4;
+        let size = len + pad_len;
+
+        let mut buf = Vec::<u8>::with_capacity(size);
+        RtcpPacket::write_header(&mut buf, DataTWCC::FMT, RtcpPacket::RTPFB, size);
+        if pad_len > 0 {
+            buf[0] |= 0x20;
+        }
+        buf.extend_from_slice(&self.sender_ssrc.to_be_bytes());
+        buf.extend_from_slice(&self.media_ssrc.to_be_bytes());
+        buf.extend_from_slice(&self.base_seq.to_be_bytes());
+        buf.extend_from_slice(&(self.statuses.len() as u16).to_be_bytes());
+        buf.extend_from_slice(&self.reference_time.to_be_bytes()[1..]);
+        buf.push(self.fb_count);
+        for chunk in chunks {
+            buf.extend_from_slice(&chunk.to_be_bytes());
+        }
+        for status in self.statuses.iter() {
+            match status {
+                PacketStatus::NotReceived => (),
+                PacketStatus::SmallDelta(delta) => buf.push(*delta),
+                PacketStatus::LargeDelta(delta) => buf.extend_from_slice(&delta.to_be_bytes()),
+            }
+        }
+        if pad_len > 0 {
+            buf.resize(size - 1, 0);
+            buf.push(pad_len as u8);
+        }
+        buf
+    }
+
+    // Packs status symbols into run length and status vector chunks.  Long runs go into run length chunks,
+    // everything else into one-bit vectors when possible and two-bit vectors otherwise.
+    fn encode_chunks(symbols: &[u8]) -> Vec<u16> {
+        let mut chunks = Vec::<u16>::new();
+        let mut off = 0;
+        while off < symbols.len() {
+            let rest = &symbols[off..];
+            let run = rest.iter().take(DataTWCC::MAX_RUN_LENGTH).take_while(|symbol| **symbol == rest[0]).count();
+            let span = usize::min(rest.len(), 14);
+            if run >= 14 {
+                chunks.push(((rest[0] as u16) << 13) | run as u16);
+                off += run;
+            } else if rest[..span].iter().all(|symbol| *symbol < 2) {
+                let mut chunk = 0x8000u16;
+                for (index, symbol) in rest[..span].iter().enumerate() {
+                    chunk |= (*symbol as u16) << (13 - index);
+                }
+                chunks.push(chunk);
+                off += span;
+            } else if run >= 7 {
+                chunks.push(((rest[0] as u16) << 13) | run as u16);
+                off += run;
+            } else {
+                let span = usize::min(rest.len(), 7);
+                let mut chunk = 0xC000u16;
+                for (index, symbol) in rest[..span].iter().enumerate() {
+                    chunk |= (*symbol as u16) << (12 - index * 2);
+                }
+                chunks.push(chunk);
+                off += span;
+            }
+        }
+        chunks
+    }
+}
+
+impl fmt::Debug for DataTWCC {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        f.debug_struct("DataTWCC")
+            .field("sender_ssrc", &self.sender_ssrc)
+            .field("media_ssrc", &self.media_ssrc)
+            .field("base_seq", &self.base_seq)
+            .field("reference_time", &self.reference_time)
+            .field("fb_count", &self.fb_count)
+            .field("status_count", &self.statuses.len())
+            .finish()
+    }
+}
+
+// TwccRecorder keeps arrival times of packets carrying transport-wide sequence number header extension
+// and generates feedback for them.
+pub struct TwccRecorder {
+    ext_id: u8,
+    start: Option<Instant>,
+    last_seq: Option<u64>,
+    next_seq: Option<u64>,
+    fb_count: u8,
+    arrivals: BTreeMap<u64, Duration>,
+}
+
+impl TwccRecorder {
+    // Limit on the number of statuses in a single feedback packet to keep it within a typical MTU.
+    const MAX_STATUS_COUNT: u64 = 0x0400;
+
+    pub fn new(ext_id: u8) -> Self {
+        TwccRecorder {
+            ext_id,
+            start: None,
+            last_seq: None,
+            next_seq: None,
+            fb_count: 0,
+            arrivals: BTreeMap::new(),
+        }
+    }
+
+    // Records arrival of the packet if it carries transport-wide sequence number.  Returns false otherwise.
+    pub fn record_packet(&mut self, packet: &RtpPacket, arrival: Instant) -> bool {
+        let seq = packet.extension()
+            .and_then(|extension| extension.element(self.ext_id))
+            .filter(|data| data.len() >= 2)
+            .map(|data| u16::from_be_bytes([data[0], data[1]]));
+        match seq {
+            Some(seq) => {
+                self.record(seq, arrival);
+                true
+            },
+            None => false,
+        }
+    }
+
+    pub fn record(&mut self, seq: u16, arrival: Instant) {
+        let start = *self.start.get_or_insert(arrival);
+        let seq = match self.last_seq {
+            None => seq as u64 + 0x10000,
+            Some(last) => {
+                let delta = seq.wrapping_sub(last as u16) as i16;
+                match last.checked_add_signed(delta as i64) {
+                    Some(seq) => seq,
+                    None => return,
+                }
+            },
+        };
+        if self.last_seq.is_none_or(|last| seq > last) {
+            self.last_seq = Some(seq);
+        }
+        // Packets that were already reported are ignored.
+        if self.next_seq.is_some_and(|next| seq < next) {
+            return
+        }
+        self.arrivals.insert(seq, arrival.saturating_duration_since(start));
+    }
+
+    // Builds feedback for recorded packets, or None if there is nothing to report.  Call repeatedly until
+    // None since large gaps or too many packets are split across several feedback packets.
+    pub fn build_feedback(&mut self, sender_ssrc: u32, media_ssrc: u32) -> Option<DataTWCC> {
+        let (&base_seq, &first) = self.arrivals.iter().next()?;
+        let base_seq = match self.next_seq {
+            Some(next) if base_seq - next < TwccRecorder::MAX_STATUS_COUNT => next,
+            _ => base_seq,
+        };
+        let reference_time = first.as_micros() as i64 / DataTWCC::REFERENCE_UNIT;
+        let mut time = reference_time * DataTWCC::REFERENCE_UNIT / PacketStatus::DELTA_UNIT;
+        let mut statuses = Vec::<PacketStatus>::new();
+        let mut end_seq = base_seq;
+
+        for (&seq, &arrival) in self.arrivals.iter() {
+            if seq - base_seq >= TwccRecorder::MAX_STATUS_COUNT {
+                break
+            }
+            let ticks = arrival.as_micros() as i64 / PacketStatus::DELTA_UNIT;
+            let status = match PacketStatus::from_delta(ticks - time) {
+                Some(status) => status,
+                None => break,
+            };
+            statuses.resize((seq - base_seq) as usize, PacketStatus::NotReceived);
+            statuses.push(status);
+            time = ticks;
+            end_seq = seq + 1;
+        }
+        self.arrivals = self.arrivals.split_off(&end_seq);
+        self.next_seq = Some(end_seq);
+        let fb_count = self.fb_count;
+        self.fb_count = self.fb_count.wrapping_add(1);
+
+        Some(DataTWCC::new(
+            sender_ssrc,
+            media_ssrc,
+            base_seq as u16,
+            ((reference_time as i32) << 8) >> 8,
+            fb_count,
+            statuses,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_run_length_feedback() {
+        let data: [u8; 24] = [
+            0x8f, 0xcd, 0x00, 0x05, 0x00, 0x00, 0x00, 0x01, 0x12, 0x34,
+            0x56, 0x78, 0x00, 0x05, 0x00, 0x04, 0x00, 0x00, 0x10, 0x07,
+            0xb0, 0x00, 0x01, 0x02,
+        ];
+        let packet = RtcpPacket::from_slice(&data).unwrap();
+        let feedback = DataTWCC::from_packet(&packet).unwrap();
+        assert_eq!(1, feedback.sender_ssrc());
+        assert_eq!(0x12345678, feedback.media_ssrc());
+        assert_eq!(5, feedback.base_seq());
+        assert_eq!(16, feedback.reference_time());
+        assert_eq!(7, feedback.fb_count());
+        assert_eq!(
+            &[PacketStatus::SmallDelta(1), PacketStatus::SmallDelta(2), PacketStatus::NotReceived, PacketStatus::NotReceived][..],
+            feedback.statuses(),
+        );
+        assert_eq!(vec![(5, 1024250), (6, 1024750)], feedback.arrivals());
+    }
+
+    #[test]
+    fn parse_status_vector_feedback() {
+        let data: [u8; 28] = [
+            0xaf, 0xcd, 0x00, 0x06, 0x00, 0x00, 0x00, 0x01, 0x12, 0x34,
+            0x56, 0x78, 0xff, 0xfe, 0x00, 0x03, 0xff, 0xff, 0xff, 0x00,
+            0xd8, 0x00, 0x10, 0xff, 0xf6, 0x00, 0x00, 0x03,
+        ];
+        let packet = RtcpPacket::from_slice(&data).unwrap();
+        let feedback = DataTWCC::from_packet(&packet).unwrap();
+        assert_eq!(0xfffe, feedback.base_seq());
+        assert_eq!(-1, feedback.reference_time());
+        assert_eq!(
+            &[PacketStatus::SmallDelta(16), PacketStatus::LargeDelta(-10), PacketStatus::NotReceived][..],
+            feedback.statuses(),
+        );
+        assert_eq!(vec![(0xfffe, -60000), (0xffff, -62500)], feedback.arrivals());
+    }
+
+    #[test]
+    fn parse_truncated_feedback() {
+        let data: [u8; 20] = [
+            0x8f, 0xcd, 0x00, 0x04, 0x00, 0x00, 0x00, 0x01, 0x12, 0x34,
+            0x56, 0x78, 0x00, 0x05, 0x00, 0x04, 0x00, 0x00, 0x10, 0x07,
+        ];
+        let packet = RtcpPacket::from_slice(&data).unwrap();
+        let error = DataTWCC::from_packet(&packet).unwrap_err();
+        assert!(matches!(error, RtcpError::InvalidLen(16)))
+    }
+
+    #[test]
+    fn serialize_round_trip() {
+        let mut statuses = vec![PacketStatus::SmallDelta(4); 20];
+        statuses.extend([PacketStatus::NotReceived, PacketStatus::LargeDelta(-300), PacketStatus::SmallDelta(0)]);
+        statuses.extend([PacketStatus::NotReceived, PacketStatus::SmallDelta(1)].iter().cycle().take(9));
+        let feedback = DataTWCC::new(1, 2, 65000, -5, 200, statuses);
+        let data = feedback.to_vec();
+        assert_eq!(0, data.len() % 4);
+        let packet = RtcpPacket::from_slice(&data).unwrap();
+        assert_eq!(data.len(), packet.size());
+        assert_eq!(feedback, DataTWCC::from_packet(&packet).unwrap());
+    }
+
+    #[test]
+    fn encode_chunk_kinds() {
+        assert_eq!(vec![0x2014], DataTWCC::encode_chunks(&[1; 20]));
+        assert_eq!(vec![0xa800], DataTWCC::encode_chunks(&[1, 0, 1]));
+        assert_eq!(vec![0xd480], DataTWCC::encode_chunks(&[1, 1, 0, 2]));
+        assert_eq!(vec![0x4008, 0xa000], DataTWCC::encode_chunks(&[2, 2, 2, 2, 2, 2, 2, 2, 1]));
+    }
+
+    #[test]
+    fn record_and_build_feedback() {
+        let data: [u8; 24] = [
+            0x90, 0x60, 0x69, 0x8f, 0xd9, 0xc2, 0x93, 0xda, 0x1c, 0x64,
+            0x27, 0x82, 0xBE, 0xDE, 0x00, 0x01, 0x31, 0xff, 0xff, 0x00,
+            0x98, 0x36, 0xbe, 0x88,
+        ];
+        let start = Instant::now();
+        let mut recorder = TwccRecorder::new(3);
+        let packet = RtpPacket::from_slice(&data).unwrap();
+        assert!(recorder.record_packet(&packet, start + Duration::from_millis(70)));
+        assert!(!TwccRecorder::new(4).record_packet(&packet, start));
+        recorder.record(1, start + Duration::from_millis(75));
+        recorder.record(0, start + Duration::from_millis(72));
+        let feedback = recorder.build_feedback(10, 20).unwrap();
+        assert_eq!(0xffff, feedback.base_seq());
+        assert_eq!(0, feedback.reference_time());
+        assert_eq!(0, feedback.fb_count());
+        assert_eq!(
+            &[PacketStatus::SmallDelta(0), PacketStatus::SmallDelta(8), PacketStatus::SmallDelta(12)][..],
+            feedback.statuses(),
+        );
+        assert!(recorder.build_feedback(10, 20).is_none());
+
+        recorder.record(4, start + Duration::from_millis(200));
+        recorder.record(2, start + Duration::from_millis(130));
+        recorder.record(0, start + Duration::from_millis(131));
+        let feedback = recorder.build_feedback(10, 20).unwrap();
+        assert_eq!(2, feedback.base_seq());
+        assert_eq!(0, feedback.reference_time());
+        assert_eq!(1, feedback.fb_count());
+        assert_eq!(
+            &[PacketStatus::SmallDelta(240), PacketStatus::NotReceived, PacketStatus::LargeDelta(280)][..],
+            feedback.statuses(),
+        );
+    }
+
+    #[test]
+    fn split_feedback_on_large_gap() {
+        let start = Instant::now();
+        let mut recorder = TwccRecorder::new(3);
+        recorder.record(10, start);
+        recorder.record(11, start + Duration::from_secs(10));
+        let feedback = recorder.build_feedback(1, 2).unwrap();
+        assert_eq!(10, feedback.base_seq());
+        assert_eq!(1, feedback.statuses().len());
+        let feedback = recorder.build_feedback(1, 2).unwrap();
+        assert_eq!(11, feedback.base_seq());
+        assert_eq!(156, feedback.reference_time());
+        assert_eq!(&[PacketStatus::SmallDelta(64)][..], feedback.statuses());
+    }
+}
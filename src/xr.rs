@@ -0,0 +1,652 @@
+use std::fmt;
+use std::time::Duration;
+
+use crate::ntp::NtpTimestamp;
+use crate::rtcp::{RtcpError, RtcpPacket};
+
+// DataXR encapsulates data for Extended Report packet (RFC 3611).
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |V=2|P|reserved |   PT=XR=207   |             length            |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                              SSRC                             |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// :                         report blocks                         :
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+//
+// Every report block starts with a common header.
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |      BT       | type-specific |         block length          |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// :             type-specific block contents                      :
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DataXR<'a> {
+	ssrc:   u32,             // SSRC of packet sender
+	blocks: Vec<XrBlock<'a>>, // Report blocks
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum XrBlock<'a> {
+    LossRle(RleReport),
+    DuplicateRle(RleReport),
+    ReceiptTimes(ReceiptTimes),
+    ReceiverReferenceTime(NtpTimestamp),
+    Dlrr(Vec<DlrrItem>),
+    StatisticsSummary(StatisticsSummary),
+    VoipMetrics(VoipMetrics),
+    // Block types this implementation does not understand are passed through as is.
+    Unknown { block_type: u8, type_specific: u8, data: &'a [u8] },
+}
+
+// RleChunk is a single chunk of run length encoded loss or duplicate report.
+//  0                   1
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |C|R|        run length         |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |C|        bit vector           |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RleChunk {
+    Null,
+    Run { ones: bool, length: u16 },
+    Vector(u16),
+}
+
+impl RleChunk {
+    fn from_u16(chunk: u16) -> RleChunk {
+        if chunk == 0 {
+            RleChunk::Null
+        } else if chunk & 0x8000 == 0 {
+            RleChunk::Run { ones: chunk & 0x4000 != 0, length: chunk & 0x3FFF }
+        } else {
+            RleChunk::Vector(chunk & 0x7FFF)
+        }
+    }
+
+    fn to_u16(self) -> u16 {
+        match self {
+            RleChunk::Null => 0,
+            RleChunk::Run { ones, length } => ((ones as u16) << 14) | (length & 0x3FFF),
+            RleChunk::Vector(bits) => 0x8000 | bits,
+        }
+    }
+}
+
+// RleReport is the content of Loss RLE (BT=1) and Duplicate RLE (BT=2) report blocks.
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |     BT=1/2    | rsvd. |   T   |         block length          |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                        SSRC of source                         |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |          begin_seq            |             end_seq           |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |          chunk 1              |             chunk 2           |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// :                              ...                              :
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+#[derive(Clone, Eq, PartialEq)]
+pub struct RleReport {
+    pub thinning: u8,
+    pub ssrc: u32,
+    pub begin_seq: u16,
+    pub end_seq: u16,
+    pub chunks: Vec<RleChunk>,
+}
+
+// ReceiptTimes is the content of Packet Receipt Times (BT=3) report block.
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |     BT=3      | rsvd. |   T   |         block length          |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                        SSRC of source                         |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |          begin_seq            |             end_seq           |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |       Receipt time of packet begin_seq                        |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// :                              ...                              :
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ReceiptTimes {
+    pub thinning: u8,
+    pub ssrc: u32,
+    pub begin_seq: u16,
+    pub end_seq: u16,
+    pub times: Vec<u32>,
+}
+
+// DlrrItem is a single sub-block of DLRR (BT=5) report block.
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |     BT=5      |   reserved    |         block length          |
+// +=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+
+// |                 SSRC_1 (SSRC of first receiver)               | sub-
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+ block
+// |                         last RR (LRR)                         |   1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                   delay since last RR (DLRR)                  |
+// +=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+=+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DlrrItem {
+    pub ssrc: u32,
+    pub last_rr: u32,
+    pub delay: u32,
+}
+
+// StatisticsSummary is the content of Statistics Summary (BT=6) report block.  Optional values are
+// present when respective flag is set in type-specific byte.
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |     BT=6      |L|D|J|ToH|rsvd.|       block length = 9        |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                        SSRC of source                         |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |          begin_seq            |             end_seq           |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                        lost_packets                           |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                        dup_packets                            |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                         min_jitter                            |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                         max_jitter                            |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                         mean_jitter                           |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                         dev_jitter                            |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// | min_ttl_or_hl | max_ttl_or_hl |mean_ttl_or_hl | dev_ttl_or_hl |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct StatisticsSummary {
+    pub ssrc: u32,
+    pub begin_seq: u16,
+    pub end_seq: u16,
+    pub lost_packets: Option<u32>,
+    pub dup_packets: Option<u32>,
+    pub jitter: Option<JitterSummary>,
+    pub ttl_or_hl: Option<TtlSummary>,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct JitterSummary {
+    pub min: u32,
+    pub max: u32,
+    pub mean: u32,
+    pub dev: u32,
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct TtlSummary {
+    pub ipv6: bool,  // Values are IPv6 Hop Limit rather than IPv4 TTL
+    pub min: u8,
+    pub max: u8,
+    pub mean: u8,
+    pub dev: u8,
+}
+
+// VoipMetrics is the content of VoIP Metrics (BT=7) report block.
+//  0                   1                   2                   3
+//  0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1 2 3 4 5 6 7 8 9 0 1
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |     BT=7      |   reserved    |       block length = 8        |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |                        SSRC of source                         |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |   loss rate   | discard rate  | burst density |  gap density  |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |       burst duration          |         gap duration          |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |     round trip delay          |       end system delay        |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// | signal level  |  noise level  |     RERL      |     Gmin      |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |   R factor    | ext. R factor |    MOS-LQ     |    MOS-CQ     |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |   RX config   |   reserved    |          JB nominal           |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+// |          JB maximum           |          JB abs max           |
+// +-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+-+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct VoipMetrics {
+    pub ssrc: u32,
+    pub loss_rate: u8,
+    pub discard_rate: u8,
+    pub burst_density: u8,
+    pub gap_density: u8,
+    pub burst_duration: u16,
+    pub gap_duration: u16,
+    pub round_trip_delay: u16,
+    pub end_system_delay: u16,
+    pub signal_level: i8,
+    pub noise_level: i8,
+    pub rerl: u8,
+    pub gmin: u8,
+    pub r_factor: u8,
+    pub ext_r_factor: u8,
+    pub mos_lq: u8,
+    pub mos_cq: u8,
+    pub rx_config: u8,
+    pub jb_nominal: u16,
+    pub jb_maximum: u16,
+    pub jb_abs_max: u16,
+}
+
+impl<'a> DataXR<'a> {
+    // Block types.
+    pub const LOSS_RLE: u8 = 1;
+    pub const DUPLICATE_RLE: u8 = 2;
+    pub const RECEIPT_TIMES: u8 = 3;
+    pub const RECEIVER_REFERENCE_TIME: u8 = 4;
+    pub const DLRR: u8 = 5;
+    pub const STATISTICS_SUMMARY: u8 = 6;
+    pub const VOIP_METRICS: u8 = 7;
+
+    const BLOCK_HEADER_SIZE: usize = 4;
+
+    pub fn new(ssrc: u32, blocks: Vec<XrBlock<'a>>) -> DataXR<'a> {
+        DataXR { ssrc, blocks }
+    }
+
+    pub fn from_packet(packet: &RtcpPacket<'a>) -> Result<DataXR<'a>, RtcpError> {
+        if packet.payload_type() != RtcpPacket::XR {
+            return Err(RtcpError::InvalidPayloadType(packet.payload_type()))
+        }
+        let slice = packet.payload();
+        if slice.len() < 4 {
+            return Err(RtcpError::InvalidLen(slice.len()))
+        }
+        let ssrc = u32::from_be_bytes([slice[0], slice[1], slice[2], slice[3]]);
+        let mut blocks = Vec::<XrBlock>::new();
+        let mut off = 4;
+        while off < slice.len() {
+            if off + DataXR::BLOCK_HEADER_SIZE > slice.len() {
+                return Err(RtcpError::InvalidLen(slice.len()))
+            }
+            let size = (u16::from_be_bytes([slice[off + 2], slice[off + 3]]) as usize + 1) * 4;
+            if off + size > slice.len() {
+                return Err(RtcpError::InvalidLen(slice.len()))
+            }
+            blocks.push(DataXR::parse_block(slice[off], slice[off + 1], &slice[off + 4..off + size])?);
+            off += size;
+        }
+        Ok(DataXR { ssrc, blocks })
+    }
+
+    pub fn ssrc(&self) -> u32 {
+        self.ssrc
+    }
+
+    pub fn blocks(&self) -> &[XrBlock<'a>] {
+        &self.blocks
+    }
+
+    fn parse_block(block_type: u8, type_specific: u8, data: &'a [u8]) -> Result<XrBlock<'a>, RtcpError> {
+        let u16_at = |off: usize| u16::from_be_bytes([data[off], data[off + 1]]);
+        let u32_at = |off: usize| u32::from_be_bytes([data[off], data[off + 1], data[off + 2], data[off + 3]]);
+        let min_len = match block_type {
+            DataXR::LOSS_RLE | DataXR::DUPLICATE_RLE | DataXR::RECEIPT_TIMES => 8,
+            DataXR::RECEIVER_REFERENCE_TIME => 8,
+            DataXR::STATISTICS_SUMMARY => 36,
+            DataXR::VOIP_METRICS => 32,
+            _ => 0,
+        };
+        if data.len() < min_len {
+            return Err(RtcpError::InvalidLen(data.len()))
+        }
+
+        let block = match block_type {
+            DataXR::LOSS_RLE | DataXR::DUPLICATE_RLE => {
+                let mut chunks = data[8..].chunks_exact(2).map(|c| RleChunk::from_u16(u16::from_be_bytes([c[0], c[1]]))).collect::<Vec<_>>();
+                // Null chunks only pad the block to a 32 bit boundary.
+                while chunks.last() == Some(&RleChunk::Null) {
+                    chunks.pop();
+                }
+                let report = RleReport {
+                    thinning: type_specific & 0x0F,
+                    ssrc: u32_at(0),
+                    begin_seq: u16_at(4),
+                    end_seq: u16_at(6),
+                    chunks,
+                };
+                if block_type == DataXR::LOSS_RLE {
+                    XrBlock::LossRle(report)
+                } else {
+                    XrBlock::DuplicateRle(report)
+                }
+            },
+            DataXR::RECEIPT_TIMES => XrBlock::ReceiptTimes(ReceiptTimes {
+                thinning: type_specific & 0x0F,
+                ssrc: u32_at(0),
+                begin_seq: u16_at(4),
+                end_seq: u16_at(6),
+                times: data[8..].chunks_exact(4).map(|c| u32::from_be_bytes([c[0], c[1], c[2], c[3]])).collect(),
+            }),
+            DataXR::RECEIVER_REFERENCE_TIME => XrBlock::ReceiverReferenceTime(NtpTimestamp::new(((u32_at(0) as u64) << 32) | u32_at(4) as u64)),
+            DataXR::DLRR => XrBlock::Dlrr(
+                data.chunks_exact(12)
+                    .map(|c| DlrrItem {
+                        ssrc: u32::from_be_bytes([c[0], c[1], c[2], c[3]]),
+                        last_rr: u32::from_be_bytes([c[4], c[5], c[6], c[7]]),
+                        delay: u32::from_be_bytes([c[8], c[9], c[10], c[11]]),
+                    })
+                    .collect()
+            ),
+            DataXR::STATISTICS_SUMMARY => {
+                let toh = (type_specific >> 3) & 0x03;
+                XrBlock::StatisticsSummary(StatisticsSummary {
+                    ssrc: u32_at(0),
+                    begin_seq: u16_at(4),
+                    end_seq: u16_at(6),
+                    lost_packets: (type_specific & 0x80 != 0).then(|| u32_at(8)),
+                    dup_packets: (type_specific & 0x40 != 0).then(|| u32_at(12)),
+                    jitter: (type_specific & 0x20 != 0).then(|| JitterSummary {
+                        min: u32_at(16),
+                        max: u32_at(20),
+                        mean: u32_at(24),
+                        dev: u32_at(28),
+                    }),
+                    ttl_or_hl: (toh == 1 || toh == 2).then(|| TtlSummary {
+                        ipv6: toh == 2,
+                        min: data[32],
+                        max: data[33],
+                        mean: data[34],
+                        dev: data[35],
+                    }),
+                })
+            },
+            DataXR::VOIP_METRICS => XrBlock::VoipMetrics(VoipMetrics {
+                ssrc: u32_at(0),
+                loss_rate: data[4],
+                discard_rate: data[5],
+                burst_density: data[6],
+                gap_density: data[7],
+                burst_duration: u16_at(8),
+                gap_duration: u16_at(10),
+                round_trip_delay: u16_at(12),
+                end_system_delay: u16_at(14),
+                signal_level: data[16] as i8,
+                noise_level: data[17] as i8,
+                rerl: data[18],
+                gmin: data[19],
+                r_factor: data[20],
+                ext_r_factor: data[21],
+                mos_lq: data[22],
+                mos_cq: data[23],
+                rx_config: data[24],
+                jb_nominal: u16_at(26),
+                jb_maximum: u16_at(28),
+                jb_abs_max: u16_at(30),
+            }),
+            _ => XrBlock::Unknown { block_type, type_specific, data },
+        };
+        Ok(block)
+    }
+
+    // Serializes report into a complete RTCP packet.
+    pub fn to_vec(&self) -> Vec<u8> {
+        let mut buf = Vec::<u8>::new();
+        RtcpPacket::write_header(&mut buf, 0, RtcpPacket::XR, RtcpPacket::HEADER_SIZE);
+        buf.extend_from_slice(&self.ssrc.to_be_bytes());
+        for block in self.blocks.iter() {
+            DataXR::write_block(&mut buf, block);
+        }
+        let length = (buf.len() / 4 - 1) as u16;
+        buf[2..4].copy_from_slice(&length.to_be_bytes());
+        buf
+    }
+
+    fn write_block(buf: &mut Vec<u8>, block: &XrBlock) {
+        let start = buf.len();
+        let (block_type, type_specific) = match block {
+            XrBlock::LossRle(report) => (DataXR::LOSS_RLE, report.thinning & 0x0F),
+            XrBlock::DuplicateRle(report) => (DataXR::DUPLICATE_RLE, report.thinning & 0x0F),
+            XrBlock::ReceiptTimes(report) => (DataXR::RECEIPT_TIMES, report.thinning & 0x0F),
+            XrBlock::ReceiverReferenceTime(_) => (DataXR::RECEIVER_REFERENCE_TIME, 0),
+            XrBlock::Dlrr(_) => (DataXR::DLRR, 0),
+            XrBlock::StatisticsSummary(summary) => {
+                let toh = match summary.ttl_or_hl {
+                    None => 0,
+                    Some(ttl) => if ttl.ipv6 { 2 } else { 1 },
+                };
+                let flags = ((summary.lost_packets.is_some() as u8) << 7)
+                    | ((summary.dup_packets.is_some() as u8) << 6)
+                    | ((summary.jitter.is_some() as u8) << 5)
+                    | (toh << 3);
+                (DataXR::STATISTICS_SUMMARY, flags)
+            },
+            XrBlock::VoipMetrics(_) => (DataXR::VOIP_METRICS, 0),
+            XrBlock::Unknown { block_type, type_specific, .. } => (*block_type, *type_specific),
+        };
+        buf.extend_from_slice(&[block_type, type_specific, 0, 0]);
+
+        match block {
+            XrBlock::LossRle(report) | XrBlock::DuplicateRle(report) => {
+                buf.extend_from_slice(&report.ssrc.to_be_bytes());
+                buf.extend_from_slice(&report.begin_seq.to_be_bytes());
+                buf.extend_from_slice(&report.end_seq.to_be_bytes());
+                for chunk in report.chunks.iter() {
+                    buf.extend_from_slice(&chunk.to_u16().to_be_bytes());
+                }
+            },
+            XrBlock::ReceiptTimes(report) => {
+                buf.extend_from_slice(&report.ssrc.to_be_bytes());
+                buf.extend_from_slice(&report.begin_seq.to_be_bytes());
+                buf.extend_from_slice(&report.end_seq.to_be_bytes());
+                for time in report.times.iter() {
+                    buf.extend_from_slice(&time.to_be_bytes());
+                }
+            },
+            XrBlock::ReceiverReferenceTime(ntpts) => buf.extend_from_slice(&ntpts.as_u64().to_be_bytes()),
+            XrBlock::Dlrr(items) => {
+                for item in items.iter() {
+                    buf.extend_from_slice(&item.ssrc.to_be_bytes());
+                    buf.extend_from_slice(&item.last_rr.to_be_bytes());
+                    buf.extend_from_slice(&item.delay.to_be_bytes());
+                }
+            },
+            XrBlock::StatisticsSummary(summary) => {
+                let jitter = summary.jitter.unwrap_or(JitterSummary { min: 0, max: 0, mean: 0, dev: 0 });
+                buf.extend_from_slice(&summary.ssrc.to_be_bytes());
+                buf.extend_from_slice(&summary.begin_seq.to_be_bytes());
+                buf.extend_from_slice(&summary.end_seq.to_be_bytes());
+                buf.extend_from_slice(&summary.lost_packets.unwrap_or(0).to_be_bytes());
+                buf.extend_from_slice(&summary.dup_packets.unwrap_or(0).to_be_bytes());
+                for value in [jitter.min, jitter.max, jitter.mean, jitter.dev] {
+                    buf.extend_from_slice(&value.to_be_bytes());
+                }
+                match summary.ttl_or_hl {
+                    Some(ttl) => buf.extend_from_slice(&[ttl.min, ttl.max, ttl.mean, ttl.dev]),
+                    None => buf.extend_from_slice(&[0; 4]),
+                }
+            },
+            XrBlock::VoipMetrics(metrics) => {
+                buf.extend_from_slice(&metrics.ssrc.to_be_bytes());
+                buf.extend_from_slice(&[metrics.loss_rate, metrics.discard_rate, metrics.burst_density, metrics.gap_density]);
+                for value in [metrics.burst_duration, metrics.gap_duration, metrics.round_trip_delay, metrics.end_system_delay] {
+                    buf.extend_from_slice(&value.to_be_bytes());
+                }
+                buf.extend_from_slice(&[
+                    metrics.signal_level as u8,
+                    metrics.noise_level as u8,
+                    metrics.rerl,
+                    metrics.gmin,
+                    metrics.r_factor,
+                    metrics.ext_r_factor,
+                    metrics.mos_lq,
+                    metrics.mos_cq,
+                    metrics.rx_config,
+                    0,
+                ]);
+                for value in [metrics.jb_nominal, metrics.jb_maximum, metrics.jb_abs_max] {
+                    buf.extend_from_slice(&value.to_be_bytes());
+                }
+            },
+            XrBlock::Unknown { data, .. } => buf.extend_from_slice(data),
+        }
+
+        // Odd number of chunks is terminated with a null chunk, unknown data is zero padded.
+        buf.resize(start + (buf.len() - start).div_ceil(4) * 4, 0);
+        let length = ((buf.len() - start) / 4 - 1) as u16;
+        buf[start + 2..start + 4].copy_from_slice(&length.to_be_bytes());
+    }
+}
+
+impl DlrrItem {
+    // Round-trip time between this host and the receiver that echoed our reference time, given the arrival
+    // time of the report.  Lets a receiver that never sends SR measure RTT.
+    pub fn rtt(&self, arrival: NtpTimestamp) -> Option<Duration> {
+        arrival.rtt(self.last_rr, self.delay)
+    }
+}
+
+impl fmt::Debug for RleReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        f.debug_struct("RleReport")
+            .field("thinning", &self.thinning)
+            .field("ssrc", &self.ssrc)
+            .field("begin_seq", &self.begin_seq)
+            .field("end_seq", &self.end_seq)
+            .field("chunk_count", &self.chunks.len())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rrt_and_dlrr_packet() {
+        let data: [u8; 36] = [
+            0x80, 0xcf, 0x00, 0x08, 0x12, 0x34, 0x56, 0x78, 0x04, 0x00,
+            0x00, 0x02, 0xe5, 0x35, 0x2f, 0x10, 0x80, 0x00, 0x00, 0x00,
+            0x05, 0x00, 0x00, 0x03, 0x0a, 0x0b, 0x0c, 0x0d, 0x2f, 0x10,
+            0x80, 0x00, 0x00, 0x01, 0x00, 0x00,
+        ];
+        let packet = RtcpPacket::from_slice(&data).unwrap();
+        let report = DataXR::from_packet(&packet).unwrap();
+        assert_eq!(0x12345678, report.ssrc());
+        assert_eq!(2, report.blocks().len());
+        assert_eq!(XrBlock::ReceiverReferenceTime(NtpTimestamp::new(0xe5352f1080000000)), report.blocks()[0]);
+        assert_eq!(
+            XrBlock::Dlrr(vec![DlrrItem { ssrc: 0x0a0b0c0d, last_rr: 0x2f108000, delay: 0x00010000 }]),
+            report.blocks()[1],
+        );
+        if let XrBlock::Dlrr(items) = &report.blocks()[1] {
+            assert_eq!(Some(Duration::from_millis(500)), items[0].rtt(NtpTimestamp::new(0x0000_2f12_0000_0000)));
+        }
+    }
+
+    #[test]
+    fn parse_truncated_block() {
+        let data: [u8; 16] = [
+            0x80, 0xcf, 0x00, 0x03, 0x12, 0x34, 0x56, 0x78, 0x04, 0x00,
+            0x00, 0x02, 0xe5, 0x35, 0x2f, 0x10,
+        ];
+        let packet = RtcpPacket::from_slice(&data).unwrap();
+        let error = DataXR::from_packet(&packet).unwrap_err();
+        assert!(matches!(error, RtcpError::InvalidLen(12)))
+    }
+
+    #[test]
+    fn parse_unknown_block() {
+        let data: [u8; 16] = [
+            0x80, 0xcf, 0x00, 0x03, 0x12, 0x34, 0x56, 0x78, 0x2a, 0x11,
+            0x00, 0x01, 0x01, 0x02, 0x03, 0x04,
+        ];
+        let packet = RtcpPacket::from_slice(&data).unwrap();
+        let report = DataXR::from_packet(&packet).unwrap();
+        assert_eq!(
+            XrBlock::Unknown { block_type: 42, type_specific: 0x11, data: &[1, 2, 3, 4] },
+            report.blocks()[0],
+        );
+        assert_eq!(&data[..], &report.to_vec()[..]);
+    }
+
+    #[test]
+    fn serialize_round_trip() {
+        let blocks = vec![
+            XrBlock::LossRle(RleReport {
+                thinning: 2,
+                ssrc: 1,
+                begin_seq: 100,
+                end_seq: 200,
+                chunks: vec![RleChunk::Run { ones: true, length: 50 }, RleChunk::Vector(0x5555)],
+            }),
+            XrBlock::DuplicateRle(RleReport {
+                thinning: 0,
+                ssrc: 1,
+                begin_seq: 100,
+                end_seq: 200,
+                chunks: vec![RleChunk::Run { ones: false, length: 100 }],
+            }),
+            XrBlock::ReceiptTimes(ReceiptTimes { thinning: 1, ssrc: 2, begin_seq: 5, end_seq: 9, times: vec![10, 20] }),
+            XrBlock::ReceiverReferenceTime(NtpTimestamp::new(0x0102030405060708)),
+            XrBlock::Dlrr(vec![DlrrItem { ssrc: 3, last_rr: 4, delay: 5 }, DlrrItem { ssrc: 6, last_rr: 7, delay: 8 }]),
+            XrBlock::StatisticsSummary(StatisticsSummary {
+                ssrc: 4,
+                begin_seq: 1,
+                end_seq: 2,
+                lost_packets: Some(3),
+                dup_packets: None,
+                jitter: Some(JitterSummary { min: 1, max: 9, mean: 4, dev: 2 }),
+                ttl_or_hl: Some(TtlSummary { ipv6: true, min: 60, max: 64, mean: 62, dev: 1 }),
+            }),
+            XrBlock::VoipMetrics(VoipMetrics {
+                ssrc: 5,
+                loss_rate: 1,
+                discard_rate: 2,
+                burst_density: 3,
+                gap_density: 4,
+                burst_duration: 5,
+                gap_duration: 6,
+                round_trip_delay: 7,
+                end_system_delay: 8,
+                signal_level: -20,
+                noise_level: -70,
+                rerl: 9,
+                gmin: 16,
+                r_factor: 90,
+                ext_r_factor: 127,
+                mos_lq: 41,
+                mos_cq: 40,
+                rx_config: 0x80,
+                jb_nominal: 40,
+                jb_maximum: 80,
+                jb_abs_max: 120,
+            }),
+        ];
+        let report = DataXR::new(0xCAFEBABE, blocks);
+        let data = report.to_vec();
+        let packet = RtcpPacket::from_slice(&data).unwrap();
+        assert_eq!(data.len(), packet.size());
+        assert_eq!(report, DataXR::from_packet(&packet).unwrap());
+    }
+
+    #[test]
+    fn pad_odd_chunk_count() {
+        let report = DataXR::new(1, vec![XrBlock::LossRle(RleReport {
+            thinning: 0,
+            ssrc: 1,
+            begin_seq: 0,
+            end_seq: 10,
+            chunks: vec![RleChunk::Run { ones: true, length: 10 }],
+        })]);
+        let data = report.to_vec();
+        assert_eq!(24, data.len());
+        assert_eq!(&[0x40, 0x0a, 0x00, 0x00][..], &data[20..]);
+        let packet = RtcpPacket::from_slice(&data).unwrap();
+        assert_eq!(report, DataXR::from_packet(&packet).unwrap());
+    }
+}